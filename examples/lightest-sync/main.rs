@@ -1,22 +1,26 @@
-mod relayer;
-
-#[macro_use]
-extern crate serde_derive;
+mod msg;
+mod server;
+mod store;
 
 #[macro_use]
 extern crate clap;
 
 extern crate celo_light_client;
 use celo_light_client::*;
-use relayer::*;
+use server::{SharedStatus, SyncStatus};
+use store::Store;
 
-use clap::{App, Arg};
+use clap::{App, Arg, SubCommand};
+use futures::StreamExt;
 use num::cast::ToPrimitive;
 
 extern crate log;
-use log::{info, error};
+use log::{info, warn, error};
 
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::error::Error;
+use std::net::SocketAddr;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 #[tokio::main]
 async fn main(){
@@ -50,13 +54,94 @@ async fn main(){
                 .default_value("./local.db")
                 .help("The path to local database")
         )
+        .arg(
+                Arg::with_name("history")
+                .long("history")
+                .takes_value(false)
+                .help("Keeps the snapshot of every verified epoch in the local database")
+        )
         .arg(
                 Arg::with_name("addr")
                 .short("a")
                 .long("addr")
                 .takes_value(true)
                 .default_value("http://127.0.0.1:8545")
-                .help("Removes the local database")
+                .help("The address of the Celo node JSON-RPC endpoint")
+        )
+        .arg(
+                Arg::with_name("listen")
+                .short("l")
+                .long("listen")
+                .takes_value(true)
+                .default_value("127.0.0.1:8080")
+                .help("The address to serve the verified state on")
+        )
+        .arg(
+                Arg::with_name("interval")
+                .short("i")
+                .long("interval")
+                .takes_value(true)
+                .default_value("5")
+                .help("The interval (in seconds) between polls for the new headers")
+        )
+        .arg(
+                Arg::with_name("window")
+                .short("w")
+                .long("window")
+                .takes_value(true)
+                .default_value("8")
+                .help("The number of epoch headers fetched concurrently")
+        )
+        .arg(
+                Arg::with_name("ws")
+                .long("ws")
+                .takes_value(true)
+                .help("The address of the Celo node WebSocket endpoint to subscribe to the new heads (otherwise the latest header is polled)")
+        )
+        .arg(
+                Arg::with_name("confirmations")
+                .short("c")
+                .long("confirmations")
+                .takes_value(true)
+                .default_value("1")
+                .help("The number of blocks built on top of the header before it's verified")
+        )
+        .arg(
+                Arg::with_name("checkpoint")
+                .long("checkpoint")
+                .takes_value(true)
+                .help("The path to the trusted checkpoint (JSON: epoch header hash, validator set sealing it and optionally the header) to start the sync from, instead of the genesis block")
+        )
+        .arg(
+                Arg::with_name("checkpoint-max-age")
+                .long("checkpoint-max-age")
+                .takes_value(true)
+                .default_value("259200")
+                .help("The age (in seconds) past which the checkpoint is reported as stale (default: the locked CELO unlocking period)")
+        )
+        .subcommand(
+            SubCommand::with_name("msg")
+                .about("Prints the contract message built from the last verified snapshot (run the sync first)")
+                .arg(
+                        Arg::with_name("kind")
+                        .required(true)
+                        .possible_values(&["init", "update"])
+                        .help("The message: InitializeState (init) or CheckHeaderAndUpdateState (update)")
+                )
+                .arg(
+                        Arg::with_name("code-id")
+                        .long("code-id")
+                        .takes_value(true)
+                        .default_value("")
+                        .help("The (base64 encoded) code id of the light client contract")
+                )
+                .arg(
+                        Arg::with_name("number")
+                        .short("n")
+                        .long("number")
+                        .takes_value(true)
+                        .help("The header number to update the client with (default: latest)")
+                )
         )
         .get_matches();
 
@@ -65,16 +150,36 @@ async fn main(){
         _ => true,
     };
 
-    let first_epoch = 0;
     let epoch_size = value_t!(matches.value_of("epoch-size"), u64).unwrap();
+    let interval = value_t!(matches.value_of("interval"), u64).unwrap();
+    let window = value_t!(matches.value_of("window"), usize).unwrap();
+    let confirmations = value_t!(matches.value_of("confirmations"), u64).unwrap();
+    let listen_addr = value_t!(matches.value_of("listen"), SocketAddr).unwrap();
+    let keep_history = matches.is_present("history");
+    let db_path = matches.value_of("db").unwrap();
     let addr = matches.value_of("addr").unwrap();
+    let ws_addr = matches.value_of("ws");
+    let checkpoint_path = matches.value_of("checkpoint");
+    let checkpoint_max_age = value_t!(matches.value_of("checkpoint-max-age"), u64).unwrap();
 
-    // setup relayer
-    info!("Setting up relayer");
-    let relayer: Relayer = Relayer::new(addr.to_string());
+    // setup header source
+    info!("Setting up header source: {}", addr);
+    let source = JsonRpcSource::new(addr);
+
+    // setup storage
+    info!("Setting up storage: {}", db_path);
+    let mut store = Store::open(db_path, keep_history).expect("unable to open local database");
+
+    if let Some(matches) = matches.subcommand_matches("msg") {
+        if let Err(e) = msg::run(&source, &store, matches).await {
+            error!("Unable to build the contract message: {}", e);
+            std::process::exit(1);
+        }
+
+        return;
+    }
 
     // setup state container
-    info!("Setting up storage");
     let state_config = Config {
        epoch_size,
        allowed_clock_skew: 5,
@@ -83,38 +188,180 @@ async fn main(){
        verify_non_epoch_headers: validate_all_headers,
        verify_header_timestamp: true,
     };
-    let snapshot = Snapshot::new();
+
+    // resume from the last verified epoch (if any), otherwise start from the checkpoint (if any)
+    let (snapshot, first_epoch) = match store.get_latest_snapshot().expect("unable to read snapshot") {
+        Some(snapshot) => {
+            let epoch = get_epoch_number(snapshot.number, epoch_size);
+            info!("Resuming from the last verified epoch: {} (header num: {})", epoch, snapshot.number);
+
+            (snapshot, epoch + 1)
+        }
+        None => match checkpoint_path {
+            Some(path) => {
+                let snapshot = checkpoint(&source, path, &state_config, checkpoint_max_age)
+                    .await
+                    .expect("unable to start from checkpoint");
+                store.put_snapshot(&snapshot).expect("unable to store snapshot");
+
+                let epoch = get_epoch_number(snapshot.number, epoch_size);
+                info!("Starting from the checkpoint epoch: {} (header num: {})", epoch, snapshot.number);
+
+                (snapshot, epoch + 1)
+            }
+            None => (Snapshot::new(), 0),
+        },
+    };
+
+    let status: SharedStatus = Arc::new(RwLock::new(SyncStatus {
+        epoch: first_epoch.checked_sub(1),
+        snapshot: first_epoch.checked_sub(1).map(|_| snapshot.clone()),
+        header: store.get_header().expect("unable to read header"),
+    }));
+
     let mut state = State::new(snapshot, &state_config);
 
-    info!("Fetching latest block header from: {}", addr);
-    let current_block_header: Header = relayer.get_block_header_by_number("latest").await.unwrap();
-    let current_epoch_number: u64 = get_epoch_number(current_block_header.number.to_u64().unwrap(), epoch_size);
-
-    info!(
-        "Syncing epoch headers from {} to epoch num: {} (last header num: {}, epoch size: {})",
-        first_epoch, current_epoch_number, current_block_header.number, epoch_size
-    );
-
-    // build up state from the genesis block to the latest
-    for epoch in first_epoch..current_epoch_number {
-        let current_timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
-        let epoch_block_num = get_epoch_last_block_number(epoch, epoch_size);
-        let epoch_block_number_hex = format!("0x{:x}", epoch_block_num);
-        let header = relayer.get_block_header_by_number(&epoch_block_number_hex).await;
-
-        if header.is_ok() {
-            match state.insert_header(&header.unwrap(), current_timestamp) {
-                Ok(_) => info!("[{}/{}] Inserted epoch header: {}", epoch + 1, current_epoch_number, epoch_block_number_hex),
-                Err(e) => error!("Failed to insert epoch header {}: {}", epoch_block_number_hex, e)
+    // serve the verified state
+    info!("Serving verified state on: {}", listen_addr);
+    let server = server::serve(listen_addr, status.clone(), store.clone(), epoch_size);
+    tokio::spawn(async move {
+        if let Err(e) = server.await {
+            error!("Server failed: {}", e);
+        }
+    });
+
+    let mut next_epoch = first_epoch;
+    loop {
+        // catch up with the chain, then keep following the new heads
+        match sync(&source, window, &mut store, &status, &mut state, &mut next_epoch).await {
+            Ok(_) => {
+                let follower = Follower::new(&source, state)
+                    .with_poll_interval(Duration::from_secs(interval))
+                    .with_confirmations(confirmations);
+                let follower = match ws_addr {
+                    Some(ws_addr) => follower.with_websocket(ws_addr),
+                    None => follower,
+                };
+
+                if let Err(e) = follow(follower, &mut store, &status, &mut next_epoch).await {
+                    error!("Follow failed (retrying in {}s): {}", interval, e);
+                }
             }
-        } else {
-            error!("Failed to fetch block header num: {}", epoch_block_number_hex);
+            Err(e) => error!("Sync failed (retrying in {}s): {}", interval, e),
         }
+
+        tokio::time::delay_for(Duration::from_secs(interval)).await;
+
+        // the state is restored from the last persisted epoch
+        state = match State::from_storage(&store, &state_config).expect("unable to read snapshot") {
+            Some(state) => state,
+            None => State::new(Snapshot::new(), &state_config),
+        };
     }
+}
+
+// sync applies the epoch headers from the next unverified epoch up to the latest one. The
+// snapshot is persisted after every epoch, so that an interrupted sync is resumed where it
+// stopped.
+async fn sync(
+    source: &dyn HeaderSource,
+    window: usize,
+    store: &mut Store,
+    status: &SharedStatus,
+    state: &mut State<'_>,
+    next_epoch: &mut u64,
+) -> Result<(), Box<dyn Error>> {
+    let epoch_size = state.epoch_size();
+    let latest_header: Header = source.latest_header().await?;
+    let current_epoch_number: u64 = get_epoch_number(latest_header.number.to_u64().unwrap(), epoch_size);
 
-    let current_timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
-    match state.verify_header(&current_block_header, current_timestamp) {
-        Ok(_) => info!("Succesfully validated latest header against local state: {}", current_block_header.number),
-        Err(e) => error!("Failed to validate latest header against local state: {}", e)
+    if *next_epoch < current_epoch_number {
+        info!(
+            "Syncing epoch headers from {} to epoch num: {} (last header num: {}, epoch size: {})",
+            next_epoch, current_epoch_number, latest_header.number, epoch_size
+        );
     }
+
+    // the epoch headers are fetched concurrently, but applied in order (each epoch header carries
+    // the validator set for the next one), the sync stops at the first header failing verification
+    // (the verified epochs are kept, even if the sync fails half-way)
+    let from_epoch = *next_epoch;
+    EpochSync::new(source)
+        .with_window(window)
+        .sync(state, store, from_epoch, current_epoch_number, current_timestamp(), |epoch, snapshot| {
+            *next_epoch = epoch + 1;
+            update_epoch(status, epoch, snapshot);
+
+            info!("[{}/{}] Inserted epoch header: {}", epoch + 1, current_epoch_number, snapshot.number);
+        })
+        .await?;
+
+    Ok(())
+}
+
+// follow verifies the new heads as they come, the verified headers and epochs are persisted
+async fn follow(
+    follower: Follower<'_>,
+    store: &mut Store,
+    status: &SharedStatus,
+    next_epoch: &mut u64,
+) -> Result<(), Box<dyn Error>> {
+    let events = follower.into_stream();
+    futures::pin_mut!(events);
+
+    while let Some(event) = events.next().await {
+        match event? {
+            FollowEvent::Header(header) => {
+                store.put_header(&header)?;
+                info!("Succesfully validated header against local state: {}", header.number);
+
+                status.write().unwrap().header = Some(header);
+            }
+            FollowEvent::Epoch { epoch, snapshot } => {
+                store.put_snapshot(&snapshot)?;
+                *next_epoch = epoch + 1;
+                update_epoch(status, epoch, &snapshot);
+
+                info!("Inserted epoch header: {}", snapshot.number);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// checkpoint loads the trusted checkpoint from the file and verifies it (the header is fetched by
+// the pinned hash, unless included). The operator is warned if the checkpoint is stale.
+async fn checkpoint(
+    source: &dyn HeaderSource,
+    path: &str,
+    config: &Config,
+    max_age: u64,
+) -> Result<Snapshot, Box<dyn Error>> {
+    let mut checkpoint: Checkpoint = serde_json::from_slice(&std::fs::read(path)?)?;
+    if checkpoint.header.is_none() {
+        info!("Fetching checkpoint header: 0x{}", hex::encode(checkpoint.hash));
+        checkpoint = Checkpoint::fetch(source, checkpoint.hash, checkpoint.validators).await?;
+    }
+
+    let now = current_timestamp();
+    let snapshot = checkpoint.verify(config, now)?;
+    if let Some(age) = checkpoint.staleness(max_age, now) {
+        warn!(
+            "The checkpoint (header num: {}) is {}s old, past the staleness bound of {}s. Use a more recent checkpoint, the validators might have withdrawn their stake since",
+            snapshot.number, age, max_age
+        );
+    }
+
+    Ok(snapshot)
+}
+
+fn update_epoch(status: &SharedStatus, epoch: u64, snapshot: &Snapshot) {
+    let mut status = status.write().unwrap();
+    status.epoch = Some(epoch);
+    status.snapshot = Some(snapshot.clone());
+}
+
+fn current_timestamp() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
 }
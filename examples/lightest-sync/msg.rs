@@ -0,0 +1,44 @@
+use crate::store::Store;
+
+use celo_light_client::contract::payload;
+use celo_light_client::*;
+
+use clap::ArgMatches;
+use std::error::Error;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// run prints the JSON payload of the contract message, built from the last verified snapshot:
+//  * init - `InitializeState`, creating the client at the snapshot
+//  * update - `CheckHeaderAndUpdateState`, with the header (default: latest) verified against the
+//    snapshot beforehand
+pub async fn run(source: &dyn HeaderSource, store: &Store, matches: &ArgMatches<'_>) -> Result<(), Box<dyn Error>> {
+    let code_id = matches.value_of("code-id").unwrap();
+    let light_client_state = payload::default_light_client_state();
+
+    let snapshot = store
+        .get_latest_snapshot()?
+        .ok_or("no verified snapshot in the local database, run the sync first")?;
+    let snapshot_header = source.header_by_number(snapshot.number).await?;
+
+    let msg = match matches.value_of("kind").unwrap() {
+        "init" => payload::initialize_state(&light_client_state, code_id, &snapshot, &snapshot_header)?,
+        _ => {
+            let header = match matches.value_of("number") {
+                Some(number) => source.header_by_number(number.parse()?).await?,
+                None => source.latest_header().await?,
+            };
+
+            let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+            State::new(snapshot.clone(), &light_client_state).verify_header(&header, now)?;
+
+            let me = payload::client_state(&light_client_state, code_id, light_client_state.celo_height(snapshot.number));
+            let consensus_state = payload::consensus_state(&snapshot, &snapshot_header, code_id)?;
+
+            payload::check_header_and_update_state(me, consensus_state, &header)?
+        }
+    };
+
+    println!("{}", payload::to_json(&msg)?);
+
+    Ok(())
+}
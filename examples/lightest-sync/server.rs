@@ -0,0 +1,109 @@
+use crate::store::Store;
+use celo_light_client::{Header, Snapshot, Storage};
+
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+
+use serde::Serialize;
+use serde_json::json;
+
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::{Arc, RwLock};
+
+/// SyncStatus holds the verified state served by the local API
+#[derive(Default)]
+pub struct SyncStatus {
+    /// Snapshot at the last verified epoch
+    pub snapshot: Option<Snapshot>,
+
+    /// Epoch of the snapshot
+    pub epoch: Option<u64>,
+
+    /// Latest header verified against the snapshot
+    pub header: Option<Header>,
+}
+
+pub type SharedStatus = Arc<RwLock<SyncStatus>>;
+
+/// Serves the verified state over HTTP:
+/// * `GET /header` - latest verified header
+/// * `GET /snapshot` - snapshot (validator set) at the last verified epoch
+/// * `GET /epochs/<num>` - snapshot at the given epoch (requires epoch history)
+/// * `GET /status` - sync progress
+pub async fn serve(
+    addr: SocketAddr,
+    status: SharedStatus,
+    store: Store,
+    epoch_size: u64,
+) -> Result<(), hyper::Error> {
+    let make_service = make_service_fn(move |_| {
+        let status = status.clone();
+        let store = store.clone();
+
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req| {
+                let response = route(&req, &status, &store, epoch_size);
+                async move { Ok::<_, Infallible>(response) }
+            }))
+        }
+    });
+
+    Server::bind(&addr).serve(make_service).await
+}
+
+fn route(req: &Request<Body>, status: &SharedStatus, store: &Store, epoch_size: u64) -> Response<Body> {
+    if req.method() != Method::GET {
+        return error(StatusCode::METHOD_NOT_ALLOWED, "method not allowed");
+    }
+
+    let status = status.read().unwrap();
+    let path = req.uri().path();
+
+    match path {
+        "/header" => match &status.header {
+            Some(header) => to_json(header),
+            None => error(StatusCode::SERVICE_UNAVAILABLE, "no verified header yet"),
+        },
+        "/snapshot" => match &status.snapshot {
+            Some(snapshot) => to_json(snapshot),
+            None => error(StatusCode::SERVICE_UNAVAILABLE, "no verified epoch yet"),
+        },
+        "/status" => to_json(&json!({
+            "epoch": status.epoch,
+            "snapshot_number": status.snapshot.as_ref().map(|snapshot| snapshot.number),
+            "header_number": status.header.as_ref().map(|header| header.number.to_string()),
+        })),
+        _ if path.starts_with("/epochs/") => {
+            let epoch = match path.trim_start_matches("/epochs/").parse::<u64>() {
+                Ok(epoch) => epoch,
+                Err(_) => return error(StatusCode::BAD_REQUEST, "invalid epoch number"),
+            };
+
+            match store.get_epoch_snapshot(epoch, epoch_size) {
+                Ok(Some(snapshot)) => to_json(&snapshot),
+                Ok(None) => error(StatusCode::NOT_FOUND, "epoch snapshot not found"),
+                Err(e) => error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
+            }
+        }
+        _ => error(StatusCode::NOT_FOUND, "not found"),
+    }
+}
+
+fn to_json<T: Serialize>(value: &T) -> Response<Body> {
+    match serde_json::to_string(value) {
+        Ok(body) => Response::builder()
+            .header("Content-Type", "application/json")
+            .body(Body::from(body))
+            .expect("response builder"),
+        Err(e) => error(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
+    }
+}
+
+fn error(code: StatusCode, msg: &str) -> Response<Body> {
+    Response::builder()
+        .status(code)
+        .header("Content-Type", "application/json")
+        .body(Body::from(json!({ "error": msg }).to_string()))
+        .expect("response builder")
+}
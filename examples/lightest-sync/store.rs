@@ -0,0 +1,64 @@
+use celo_light_client::{Error, FromRlp, Header, SledStorage, Snapshot, Storage, ToRlp};
+
+const HEADER_KEY: &[u8] = b"header";
+
+/// Store persists the verified state in the local sled database, so that the sync can be resumed
+/// from the last verified epoch.
+///
+/// The epoch snapshots are kept in the light client `SledStorage`. Unless epoch history is
+/// enabled, only the snapshot of the last verified epoch is kept.
+#[derive(Clone)]
+pub struct Store {
+    snapshots: SledStorage,
+    keep_history: bool,
+}
+
+impl Store {
+    pub fn open(path: &str, keep_history: bool) -> Result<Self, Error> {
+        Ok(Self {
+            snapshots: SledStorage::open(path)?,
+            keep_history,
+        })
+    }
+
+    /// Returns the latest header verified against the local state
+    pub fn get_header(&self) -> Result<Option<Header>, Box<dyn std::error::Error>> {
+        match self.snapshots.db().get(HEADER_KEY)? {
+            Some(bytes) => Ok(Some(Header::from_rlp(&bytes)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn put_header(&self, header: &Header) -> Result<(), Box<dyn std::error::Error>> {
+        let db = self.snapshots.db();
+        db.insert(HEADER_KEY, header.to_rlp())?;
+        db.flush()?;
+
+        Ok(())
+    }
+}
+
+impl Storage for Store {
+    fn get_snapshot(&self, number: u64) -> Result<Option<Snapshot>, Error> {
+        self.snapshots.get_snapshot(number)
+    }
+
+    /// Stores the snapshot of the verified epoch (the write is flushed to disk)
+    fn put_snapshot(&mut self, snapshot: &Snapshot) -> Result<(), Error> {
+        self.snapshots.put_snapshot(snapshot)?;
+
+        if !self.keep_history {
+            self.snapshots.prune(snapshot.number, usize::MAX)?;
+        }
+
+        Ok(())
+    }
+
+    fn get_latest_number(&self) -> Result<Option<u64>, Error> {
+        self.snapshots.get_latest_number()
+    }
+
+    fn prune(&mut self, before: u64, limit: usize) -> Result<usize, Error> {
+        self.snapshots.prune(before, limit)
+    }
+}
@@ -0,0 +1,427 @@
+use crate::errors::{Error, Kind};
+use crate::istanbul::is_last_block_of_epoch;
+use crate::serialization::rlp::rlp_list_field_from_bytes;
+use crate::state::State;
+use crate::traits::{FromRlp, StateConfig, ToRlp};
+use crate::types::header::{Hash, Header};
+use crate::types::state::Snapshot;
+
+use num::cast::ToPrimitive;
+use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
+
+/// Version of the proof bundle layout
+pub const BUNDLE_VERSION: u8 = 1;
+
+/// BundleMetadata describes the chain the proof bundle was built for
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct BundleMetadata {
+    pub version: u8,
+
+    /// Name of the network (ie. `mainnet`), informational only
+    pub chain: String,
+
+    pub epoch_size: u64,
+
+    /// Unix time the bundle was created at
+    pub created_at: u64,
+}
+
+/// BundleHeader is the header along with its hash, so that the bundle can be reviewed (and
+/// cross-referenced with a block explorer) without recomputing the hashes
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct BundleHeader {
+    #[serde(with = "crate::serialization::bytes::hexstring")]
+    pub hash: Hash,
+
+    pub header: Header,
+}
+
+/// ProofBundle is the self-contained proof of the validator set at the target header: the
+/// trusted starting snapshot, followed by the epoch headers of all the consecutive epochs up to
+/// the target header. The bundle is verified offline (see `verify`), so it can be checked on
+/// the air-gapped systems or cached by the relayer.
+///
+/// The bundle is serializable with serde (ie. to JSON, the hashes are hex encoded) or to RLP
+/// (`to_rlp` / `from_rlp`), which is the portable binary format.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct ProofBundle {
+    pub metadata: BundleMetadata,
+
+    /// Trusted starting point. The empty snapshot (`Snapshot::new`) starts the bundle from the
+    /// genesis block, that has to be the first epoch header then.
+    pub snapshot: Snapshot,
+
+    pub epoch_headers: Vec<BundleHeader>,
+
+    pub target: BundleHeader,
+}
+
+impl BundleHeader {
+    pub fn new(header: Header) -> Result<Self, Error> {
+        Ok(Self {
+            hash: header.hash()?,
+            header,
+        })
+    }
+}
+
+impl ProofBundle {
+    pub fn new(
+        chain: &str,
+        epoch_size: u64,
+        created_at: u64,
+        snapshot: Snapshot,
+        epoch_headers: Vec<Header>,
+        target: Header,
+    ) -> Result<Self, Error> {
+        Ok(Self {
+            metadata: BundleMetadata {
+                version: BUNDLE_VERSION,
+                chain: chain.to_string(),
+                epoch_size,
+                created_at,
+            },
+            snapshot,
+            epoch_headers: epoch_headers
+                .into_iter()
+                .map(BundleHeader::new)
+                .collect::<Result<_, _>>()?,
+            target: BundleHeader::new(target)?,
+        })
+    }
+
+    /// Verifies the bundle offline, by applying the epoch headers and the target header to the
+    /// state at the trusted snapshot. Returns the snapshot at the target header.
+    ///
+    /// On top of the header verification (see `State::insert_header`), the recorded hashes have
+    /// to match the headers and the epoch headers have to cover all the epochs up to the
+    /// target header (the validator set can't be carried over a missing epoch).
+    ///
+    /// The seals are verified regardless of the config (the `verify_*_headers` flags), as the
+    /// bundle is the only proof of the validator set. The genesis block has no seal, it is the
+    /// trusted starting point of the bundle built from the empty snapshot.
+    pub fn verify(
+        &self,
+        config: &dyn StateConfig,
+        current_timestamp: u64,
+    ) -> Result<Snapshot, Error> {
+        if self.metadata.version != BUNDLE_VERSION {
+            return Err(Kind::InvalidBundle {
+                msg: "unsupported bundle version",
+            }
+            .into());
+        }
+
+        let epoch_size = config.epoch_size();
+        if self.metadata.epoch_size != epoch_size {
+            return Err(Kind::InvalidBundle {
+                msg: "epoch size mismatch",
+            }
+            .into());
+        }
+
+        // the empty snapshot is followed by the genesis block
+        let mut state = State::new(self.snapshot.clone(), config);
+        let mut next_epoch_block = if self.snapshot.validators.is_empty() {
+            0
+        } else {
+            (self.snapshot.number / epoch_size + 1) * epoch_size
+        };
+
+        for entry in self.epoch_headers.iter() {
+            let number = entry.verify_hash()?;
+            if number != next_epoch_block || !is_last_block_of_epoch(number, epoch_size) {
+                return Err(Kind::InvalidBundle {
+                    msg: "epoch headers should cover the consecutive epochs",
+                }
+                .into());
+            }
+
+            verify_seal(&state, &entry.header, number)?;
+            state.insert_header(&entry.header, current_timestamp)?;
+            next_epoch_block += epoch_size;
+        }
+
+        let number = self.target.verify_hash()?;
+        if number > next_epoch_block {
+            return Err(Kind::InvalidBundle {
+                msg: "target header is past the last covered epoch",
+            }
+            .into());
+        }
+
+        verify_seal(&state, &self.target.header, number)?;
+        state.insert_header(&self.target.header, current_timestamp)?;
+
+        Ok(state.snapshot().clone())
+    }
+}
+
+// verify_seal verifies the header seal against the validator set of the state, except for the
+// genesis block
+fn verify_seal(state: &State, header: &Header, number: u64) -> Result<(), Error> {
+    if number == 0 {
+        return Ok(());
+    }
+
+    state.verify_header_seal(header)
+}
+
+impl BundleHeader {
+    // returns the header number, once the recorded hash is checked
+    fn verify_hash(&self) -> Result<u64, Error> {
+        if self.header.hash()? != self.hash {
+            return Err(Kind::InvalidBundle {
+                msg: "header hash mismatch",
+            }
+            .into());
+        }
+
+        self.header.number.to_u64().ok_or_else(|| {
+            Kind::InvalidBundle {
+                msg: "header number overflow",
+            }
+            .into()
+        })
+    }
+}
+
+impl Encodable for BundleMetadata {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(4);
+        s.append(&self.version);
+        s.append(&self.chain);
+        s.append(&self.epoch_size);
+        s.append(&self.created_at);
+    }
+}
+
+impl Decodable for BundleMetadata {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        Ok(BundleMetadata {
+            version: rlp.val_at(0)?,
+            chain: rlp.val_at(1)?,
+            epoch_size: rlp.val_at(2)?,
+            created_at: rlp.val_at(3)?,
+        })
+    }
+}
+
+impl Encodable for BundleHeader {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(2);
+        s.append(&self.hash.as_ref());
+        s.append(&self.header);
+    }
+}
+
+impl Decodable for BundleHeader {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        Ok(BundleHeader {
+            hash: rlp_list_field_from_bytes(rlp, 0)?,
+            header: rlp.val_at(1)?,
+        })
+    }
+}
+
+impl Encodable for ProofBundle {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(4);
+        s.append(&self.metadata);
+        s.append(&self.snapshot);
+        s.append_list(&self.epoch_headers);
+        s.append(&self.target);
+    }
+}
+
+impl Decodable for ProofBundle {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        Ok(ProofBundle {
+            metadata: rlp.val_at(0)?,
+            snapshot: rlp.val_at(1)?,
+            epoch_headers: rlp.list_at(2)?,
+            target: rlp.val_at(3)?,
+        })
+    }
+}
+
+impl ToRlp for ProofBundle {
+    fn to_rlp(&self) -> Vec<u8> {
+        rlp::encode(self)
+    }
+}
+
+impl FromRlp for ProofBundle {
+    fn from_rlp(bytes: &[u8]) -> Result<Self, Error> {
+        rlp::decode(&bytes).map_err(|e| Kind::RlpDecodeError.context(e).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockChain;
+    use crate::types::state::Config;
+
+    const EPOCH_SIZE: u64 = 5;
+
+    fn config() -> Config {
+        Config {
+            epoch_size: EPOCH_SIZE,
+            allowed_clock_skew: 5,
+            verify_epoch_headers: true,
+            verify_non_epoch_headers: true,
+            verify_header_timestamp: true,
+        }
+    }
+
+    fn chain() -> MockChain {
+        let mut chain = MockChain::new(EPOCH_SIZE, 4);
+        chain.extend(7);
+        chain.rotate_validators(3);
+        chain.extend(10);
+
+        chain
+    }
+
+    fn headers(chain: &MockChain, numbers: &[u64]) -> Vec<Header> {
+        numbers
+            .iter()
+            .map(|number| chain.header(*number).unwrap().clone())
+            .collect()
+    }
+
+    fn bundle(chain: &MockChain, snapshot: Snapshot, epochs: &[u64], target: u64) -> ProofBundle {
+        ProofBundle::new(
+            "mock",
+            EPOCH_SIZE,
+            0,
+            snapshot,
+            headers(chain, epochs),
+            chain.header(target).unwrap().clone(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_verify_bundle() {
+        let chain = chain();
+        let (config, now) = (config(), chain.latest().time);
+
+        // from genesis
+        let bundle = bundle(&chain, Snapshot::new(), &[0, 5, 10, 15], 17);
+        let snapshot = bundle.verify(&config, now).unwrap();
+        assert_eq!(snapshot.number, 17);
+        assert_eq!(snapshot.validators, chain.validators());
+
+        // from the trusted snapshot
+        let trusted = ProofBundle::new(
+            "mock",
+            EPOCH_SIZE,
+            0,
+            Snapshot::new(),
+            headers(&chain, &[0]),
+            chain.header(5).unwrap().clone(),
+        )
+        .unwrap()
+        .verify(&config, now)
+        .unwrap();
+        let bundle = self::bundle(&chain, trusted, &[10, 15], 15 + 1);
+        assert_eq!(bundle.verify(&config, now).unwrap().number, 16);
+
+        // the target can be the next epoch header
+        let bundle = self::bundle(&chain, Snapshot::new(), &[0, 5], 10);
+        assert_eq!(
+            bundle.verify(&config, now).unwrap().validators,
+            chain.validators()
+        );
+    }
+
+    #[test]
+    fn test_reject_invalid_bundle() {
+        let chain = chain();
+        let (config, now) = (config(), chain.latest().time);
+        let invalid_bundle = |bundle: &ProofBundle| match bundle.verify(&config, now) {
+            Err(e) => matches!(e.kind(), Kind::InvalidBundle { .. }),
+            _ => false,
+        };
+
+        // the epoch 2 is missing
+        assert!(invalid_bundle(&bundle(
+            &chain,
+            Snapshot::new(),
+            &[0, 5, 15],
+            17
+        )));
+
+        // the target is past the epoch 3
+        assert!(invalid_bundle(&bundle(
+            &chain,
+            Snapshot::new(),
+            &[0, 5, 10],
+            17
+        )));
+
+        // the recorded hash doesn't match
+        let mut bundle = self::bundle(&chain, Snapshot::new(), &[0, 5, 10, 15], 17);
+        bundle.epoch_headers[1].hash = Hash::default();
+        assert!(invalid_bundle(&bundle));
+
+        // the header is tampered with (along with the hash)
+        let mut header = chain.header(10).unwrap().clone();
+        header.gas_used += 1;
+        bundle.epoch_headers[2] = BundleHeader::new(header).unwrap();
+        bundle.epoch_headers[1] = BundleHeader::new(chain.header(5).unwrap().clone()).unwrap();
+        assert!(matches!(
+            bundle.verify(&config, now).unwrap_err().kind(),
+            Kind::BlsVerifyError
+        ));
+    }
+
+    #[test]
+    fn test_reject_forged_seal_without_header_verification() {
+        let chain = chain();
+        let now = chain.latest().time;
+        let config = Config {
+            verify_epoch_headers: false,
+            verify_non_epoch_headers: false,
+            verify_header_timestamp: false,
+            ..config()
+        };
+
+        let bundle = bundle(&chain, Snapshot::new(), &[0, 5, 10, 15], 17);
+        assert_eq!(bundle.verify(&config, now).unwrap().number, 17);
+
+        // the epoch header isn't sealed by the validators (the recorded hash matches)
+        let mut forged = bundle.clone();
+        let mut header = chain.header(10).unwrap().clone();
+        header.gas_used += 1;
+        forged.epoch_headers[2] = BundleHeader::new(header).unwrap();
+        assert!(matches!(
+            forged.verify(&config, now).unwrap_err().kind(),
+            Kind::BlsVerifyError
+        ));
+
+        // nor is the target header
+        let mut forged = bundle;
+        let mut header = chain.header(17).unwrap().clone();
+        header.gas_used += 1;
+        forged.target = BundleHeader::new(header).unwrap();
+        assert!(matches!(
+            forged.verify(&config, now).unwrap_err().kind(),
+            Kind::BlsVerifyError
+        ));
+    }
+
+    #[test]
+    fn test_bundle_serialization() {
+        let chain = chain();
+        let bundle = bundle(&chain, Snapshot::new(), &[0, 5, 10, 15], 17);
+
+        assert_eq!(ProofBundle::from_rlp(&bundle.to_rlp()).unwrap(), bundle);
+
+        let json = serde_json::to_vec(&bundle).unwrap();
+        let decoded: ProofBundle = serde_json::from_slice(&json).unwrap();
+        assert_eq!(decoded, bundle);
+    }
+}
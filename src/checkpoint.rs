@@ -0,0 +1,221 @@
+use crate::errors::{Error, Kind};
+use crate::istanbul::is_last_block_of_epoch;
+use crate::state::State;
+use crate::traits::StateConfig;
+use crate::types::header::{Hash, Header};
+use crate::types::istanbul::IstanbulAggregatedSeal;
+use crate::types::state::{Snapshot, Validator};
+
+use num::cast::ToPrimitive;
+
+#[cfg(feature = "sync")]
+use crate::traits::HeaderSource;
+
+/// Checkpoint is the trusted (weak subjectivity) starting point of the sync, so that the
+/// validator set doesn't have to be built up from the genesis block: the epoch header, pinned
+/// by its hash, along with the validator set that sealed it (ie. the validator set of the epoch
+/// closed by the header).
+///
+/// The checkpoint is verified before use (see `verify`): the header has to match the pinned
+/// hash and carry a valid aggregated seal of the validator set. The validator set diff of the
+/// header is then applied, so the state starts at the next epoch.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct Checkpoint {
+    #[serde(with = "crate::serialization::bytes::hexstring")]
+    pub hash: Hash,
+
+    pub validators: Vec<Validator>,
+
+    /// The epoch header, unless fetched by the hash from the header source (see `fetch`)
+    #[serde(default)]
+    pub header: Option<Header>,
+}
+
+impl Checkpoint {
+    /// Returns the snapshot at the checkpoint header, once the header is verified against the
+    /// pinned hash and the validator set. The seal is verified regardless of the config.
+    pub fn verify(
+        &self,
+        config: &dyn StateConfig,
+        current_timestamp: u64,
+    ) -> Result<Snapshot, Error> {
+        let header = self.header.as_ref().ok_or(Kind::InvalidCheckpoint {
+            msg: "checkpoint header is missing",
+        })?;
+
+        if header.hash()? != self.hash {
+            return Err(Kind::InvalidCheckpoint {
+                msg: "checkpoint header doesn't match the pinned hash",
+            }
+            .into());
+        }
+
+        let number = header.number.to_u64().unwrap_or_default();
+        let epoch_size = config.epoch_size();
+        if number == 0 || !is_last_block_of_epoch(number, epoch_size) {
+            return Err(Kind::InvalidCheckpoint {
+                msg: "checkpoint header should be an epoch header past the genesis block",
+            }
+            .into());
+        }
+
+        // the validator set at the previous epoch header, that is the one sealing the checkpoint
+        let mut state = State::new(
+            Snapshot {
+                number: number - epoch_size,
+                timestamp: 0,
+                validators: self.validators.clone(),
+                hash: Hash::default(),
+                aggregated_seal: IstanbulAggregatedSeal::new(),
+            },
+            config,
+        );
+
+        state.verify_header_seal(header)?;
+        state.insert_header(header, current_timestamp)?;
+
+        Ok(state.snapshot().clone())
+    }
+
+    /// Returns the age of the checkpoint (in seconds), if it's older than the staleness bound.
+    /// The validators of a stale checkpoint might have withdrawn their stake, so that the
+    /// checkpoint no longer protects against the long-range attacks.
+    pub fn staleness(&self, max_age: u64, current_timestamp: u64) -> Option<u64> {
+        let age = current_timestamp.saturating_sub(self.header.as_ref()?.time);
+
+        if age > max_age {
+            Some(age)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(feature = "sync")]
+impl Checkpoint {
+    /// Returns the checkpoint with the header fetched from the source by the pinned hash
+    pub async fn fetch(
+        source: &dyn HeaderSource,
+        hash: Hash,
+        validators: Vec<Validator>,
+    ) -> Result<Self, Error> {
+        let header = source.header_by_hash(&hash).await?;
+
+        Ok(Self {
+            hash,
+            validators,
+            header: Some(header),
+        })
+    }
+}
+
+#[cfg(all(test, feature = "sync"))]
+mod tests {
+    use super::*;
+    use crate::sync::{MockChain, MockSource};
+    use crate::types::state::Config;
+
+    const EPOCH_SIZE: u64 = 5;
+
+    fn config() -> Config {
+        Config {
+            epoch_size: EPOCH_SIZE,
+            allowed_clock_skew: 5,
+            verify_epoch_headers: true,
+            verify_non_epoch_headers: true,
+            verify_header_timestamp: true,
+        }
+    }
+
+    // returns the chain rotating the validators at the epoch 2 header, along with the initial
+    // validators (sealing the epoch 2 header)
+    fn chain() -> (MockChain, Vec<Validator>) {
+        let mut chain = MockChain::new(EPOCH_SIZE, 4);
+        chain.extend(7);
+        let validators = chain.validators();
+        chain.rotate_validators(3);
+        chain.extend(10);
+
+        (chain, validators)
+    }
+
+    fn checkpoint(chain: &MockChain, number: u64, validators: Vec<Validator>) -> Checkpoint {
+        let header = chain.header(number).unwrap().clone();
+
+        Checkpoint {
+            hash: header.hash().unwrap(),
+            validators,
+            header: Some(header),
+        }
+    }
+
+    #[test]
+    fn test_verify_checkpoint() {
+        let (chain, validators) = chain();
+        let (config, now) = (config(), chain.latest().time);
+
+        let checkpoint = checkpoint(&chain, 10, validators);
+        let state = State::from_checkpoint(&checkpoint, &config, now).unwrap();
+        assert_eq!(state.snapshot().number, 10);
+        assert_eq!(state.snapshot().hash, checkpoint.hash);
+        assert_eq!(state.snapshot().validators, chain.validators());
+
+        // the headers of the next epoch are verified against the new validator set
+        state.verify_header(chain.latest(), now).unwrap();
+    }
+
+    #[test]
+    fn test_reject_invalid_checkpoint() {
+        let (chain, validators) = chain();
+        let (config, now) = (config(), chain.latest().time);
+        let invalid_checkpoint = |checkpoint: &Checkpoint| match checkpoint.verify(&config, now) {
+            Err(e) => matches!(e.kind(), Kind::InvalidCheckpoint { .. }),
+            _ => false,
+        };
+
+        // the hash isn't the pinned one
+        let mut checkpoint = checkpoint(&chain, 10, validators.clone());
+        checkpoint.hash = Hash::default();
+        assert!(invalid_checkpoint(&checkpoint));
+
+        // the header is missing
+        checkpoint.header = None;
+        assert!(invalid_checkpoint(&checkpoint));
+
+        // not an epoch header
+        assert!(invalid_checkpoint(&self::checkpoint(&chain, 7, validators)));
+
+        // the header isn't sealed by the validator set
+        let checkpoint = self::checkpoint(&chain, 10, chain.validators());
+        assert!(matches!(
+            checkpoint.verify(&config, now).unwrap_err().kind(),
+            Kind::BlsVerifyError
+        ));
+    }
+
+    #[test]
+    fn test_checkpoint_staleness() {
+        let (chain, validators) = chain();
+        let checkpoint = checkpoint(&chain, 10, validators);
+        let time = checkpoint.header.as_ref().unwrap().time;
+
+        assert_eq!(checkpoint.staleness(100, time + 100), None);
+        assert_eq!(checkpoint.staleness(100, time + 101), Some(101));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_checkpoint() {
+        let (chain, validators) = chain();
+        let expected = checkpoint(&chain, 10, validators.clone());
+        let source = MockSource::new(chain);
+
+        let checkpoint = Checkpoint::fetch(&source, expected.hash, validators.clone())
+            .await
+            .unwrap();
+        assert_eq!(checkpoint, expected);
+
+        assert!(Checkpoint::fetch(&source, Hash::default(), validators)
+            .await
+            .is_err());
+    }
+}
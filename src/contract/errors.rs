@@ -0,0 +1,173 @@
+use crate::errors::Kind;
+use cosmwasm_std::StdError;
+use std::fmt::Display;
+use thiserror::Error;
+
+/// All errors the light client contract may return.
+///
+/// Every variant maps to a stable code (see [`ContractError::code`]), so that the relayer and the
+/// 10-wasm module can tell the failures apart without parsing the error message. The codes are part
+/// of the contract interface and must never be reused or renumbered.
+#[derive(Debug, Error)]
+pub enum ContractError {
+    #[error("{0}")]
+    Std(StdError),
+
+    #[error("unable to parse {target}: {msg}")]
+    Parse { target: String, msg: String },
+
+    #[error("invalid message: {msg}")]
+    InvalidMessage { msg: String },
+
+    #[error("verification failed: {msg}")]
+    Verification { kind: Kind, msg: String },
+
+    #[error("invalid proof: {msg}")]
+    InvalidProof { msg: String },
+
+    #[error("client is frozen: {msg}")]
+    Frozen { msg: String },
+
+    #[error("client is expired: {msg}")]
+    Expired { msg: String },
+
+    #[error("delay period has not passed: {msg}")]
+    DelayPeriodNotPassed { msg: String },
+
+    #[error("{what} not found")]
+    NotFound { what: String },
+
+    #[error("invalid misbehaviour: {msg}")]
+    InvalidMisbehaviour { msg: String },
+}
+
+impl ContractError {
+    /// Returns the stable code of the error
+    pub fn code(&self) -> u32 {
+        match self {
+            ContractError::Std(_) => 1,
+            ContractError::Parse { .. } => 2,
+            ContractError::InvalidMessage { .. } => 3,
+            ContractError::Verification { .. } => 4,
+            ContractError::InvalidProof { .. } => 5,
+            ContractError::Frozen { .. } => 6,
+            ContractError::Expired { .. } => 7,
+            ContractError::DelayPeriodNotPassed { .. } => 8,
+            ContractError::NotFound { .. } => 9,
+            ContractError::InvalidMisbehaviour { .. } => 10,
+        }
+    }
+
+    /// Returns the error message prefixed with the error code (ie. `[5] invalid proof: ...`)
+    pub fn err_msg(&self) -> String {
+        format!("[{}] {}", self.code(), self)
+    }
+
+    /// Wraps the light client library error, along with the context of the failed operation
+    pub fn verification<C: Display>(err: crate::Error, context: C) -> Self {
+        ContractError::Verification {
+            kind: err.kind().clone(),
+            msg: format!("{}. Error: {}", context, err),
+        }
+    }
+
+    /// Wraps the snapshot storage error, along with the context of the failed operation
+    pub fn storage<C: Display>(err: crate::Error, context: C) -> Self {
+        ContractError::Std(StdError::generic_err(format!("{}. Error: {}", context, err)))
+    }
+
+    pub fn invalid_message<M: Into<String>>(msg: M) -> Self {
+        ContractError::InvalidMessage { msg: msg.into() }
+    }
+
+    pub fn invalid_identifier<E: Display>(err: E) -> Self {
+        ContractError::InvalidMessage {
+            msg: format!("invalid identifier: {}", err),
+        }
+    }
+
+    pub fn invalid_proof<M: Display>(msg: M) -> Self {
+        ContractError::InvalidProof {
+            msg: msg.to_string(),
+        }
+    }
+
+    pub fn invalid_misbehaviour<M: Into<String>>(msg: M) -> Self {
+        ContractError::InvalidMisbehaviour { msg: msg.into() }
+    }
+
+    pub fn frozen<M: Into<String>>(msg: M) -> Self {
+        ContractError::Frozen { msg: msg.into() }
+    }
+
+    pub fn expired<M: Into<String>>(msg: M) -> Self {
+        ContractError::Expired { msg: msg.into() }
+    }
+}
+
+impl From<StdError> for ContractError {
+    fn from(err: StdError) -> Self {
+        match err {
+            StdError::ParseErr {
+                target_type, msg, ..
+            } => ContractError::Parse {
+                target: target_type,
+                msg,
+            },
+            StdError::NotFound { kind, .. } => ContractError::NotFound { what: kind },
+            err => ContractError::Std(err),
+        }
+    }
+}
+
+impl From<ContractError> for StdError {
+    fn from(err: ContractError) -> Self {
+        StdError::generic_err(err.err_msg())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_std_error_mapping() {
+        let err: ContractError = StdError::parse_err("msg.header", "bad rlp").into();
+        assert_eq!(err.code(), 2);
+        assert_eq!(err.err_msg(), "[2] unable to parse msg.header: bad rlp");
+
+        let err: ContractError = StdError::not_found("consensus state").into();
+        assert_eq!(err.code(), 9);
+
+        let err: ContractError = StdError::generic_err("oops").into();
+        assert_eq!(err.code(), 1);
+    }
+
+    #[test]
+    fn test_verification_error_keeps_kind() {
+        let err = ContractError::verification(
+            Kind::BlsInvalidSignature.into(),
+            "Unable to ingest header num: 5",
+        );
+
+        assert_eq!(err.code(), 4);
+        match err {
+            ContractError::Verification { kind, .. } => match kind {
+                Kind::BlsInvalidSignature => {}
+                kind => panic!("unexpected kind: {:?}", kind),
+            },
+            err => panic!("unexpected error: {:?}", err),
+        }
+    }
+
+    #[test]
+    fn test_std_error_carries_code() {
+        let err: StdError = ContractError::expired("trusting period has passed").into();
+        match err {
+            StdError::GenericErr { msg, .. } => {
+                assert_eq!(msg, "[7] client is expired: trusting period has passed")
+            }
+            err => panic!("unexpected error: {:?}", err),
+        }
+    }
+}
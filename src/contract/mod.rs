@@ -1,42 +1,67 @@
-mod serialization;
+pub mod errors;
+pub mod payload;
+pub(crate) mod serialization;
 mod store;
 pub mod types;
 mod util;
 use prost::Message;
 
 use crate::contract::{
-    serialization::{from_base64, from_base64_json_slice, from_base64_rlp},
+    errors::ContractError,
+    serialization::{
+        from_base64, from_base64_json_slice, from_base64_rlp, from_base64_versioned,
+    },
     store::{
-        get_consensus_state, get_processed_height, get_processed_time, get_self_height,
-        set_consensus_meta, set_consensus_state, EMPTY_PREFIX, SUBJECT_PREFIX, SUBSTITUTE_PREFIX,
+        backfill_iteration_keys, copy_consensus_states, get_consensus_heights, get_consensus_state,
+        get_consensus_states_count, get_latest_consensus_height, get_next_consensus_height,
+        get_prev_consensus_height, get_processed_height, get_processed_time, get_self_height,
+        get_state_version, prune_expired_consensus_states, set_consensus_meta,
+        set_consensus_state, set_state_version, EMPTY_PREFIX, PRUNE_LIMIT, SUBJECT_PREFIX,
+        SUBSTITUTE_PREFIX,
     },
     types::ibc::{
-        apply_prefix, verify_membership, Channel, ChannelId, ClientId, ClientUpgradePath,
-        ConnectionEnd, ConnectionId, Height, MerklePath, MerklePrefix, MerkleProof, MerkleRoot,
-        Path as IcsPath, PortId, Sequence,
+        apply_prefix, verify_membership, verify_non_membership, Channel, ChannelId, ClientId,
+        ClientUpgradePath, ConnectionEnd, ConnectionId, Height, MerklePath, MerklePrefix,
+        MerkleProof, MerkleRoot, Path as IcsPath, PortId, Sequence,
     },
     types::msg::{
-        CheckHeaderAndUpdateStateResult, CheckMisbehaviourAndUpdateStateResult,
-        CheckSubstituteAndUpdateStateResult, ClientStateCallResponseResult, HandleMsg,
-        InitializeStateResult, ProcessedTimeResponse, QueryMsg, StatusResult,
+        AdjacentConsensusStateResponse, CheckHeaderAndUpdateStateResult, CheckMisbehaviourAndUpdateStateResult,
+        CheckSubstituteAndUpdateStateResult, ClientStateCallResponseResult,
+        ConsensusStateResponse, ConsensusStatesCountResponse, HandleMsg, InitializeStateResult,
+        ListConsensusHeightsResponse, MigrateMsg, ProcessedTimeResponse, QueryMsg, StatusResult,
         VerifyChannelStateResult, VerifyClientConsensusStateResult, VerifyClientStateResult,
-        VerifyConnectionStateResult, VerifyPacketAcknowledgementResult,
-        VerifyPacketCommitmentResult, VerifyPacketReceiptAbsenceResult,
-        VerifyUpgradeAndUpdateStateResult, ZeroCustomFieldsResult
+        VerifyConnectionStateResult, VerifyMembershipResult, VerifyNonMembershipResult,
+        VerifyPacketAcknowledgementResult, VerifyPacketCommitmentResult,
+        VerifyPacketReceiptAbsenceResult, VerifyUpgradeAndUpdateStateResult,
+        ZeroCustomFieldsResult
+    },
+    types::evm::{verify_evm_membership, verify_evm_non_membership, EvmProof},
+    types::proto::{
+        decode_stored_consensus_state, encode_any, encode_message, ProtoChannel, ProtoClientState,
+        ProtoConnectionEnd, ProtoConsensusState, WASM_CLIENT_STATE_TYPE_URL,
+        WASM_CONSENSUS_STATE_TYPE_URL,
+    },
+    types::tendermint::{decode_client_state, decode_consensus_state},
+    types::state::{
+        CommitmentScheme, LightClientState, LightConsensusState, PrefixRule, ProofSpec, Versioned,
     },
-    types::state::{LightClientState, LightConsensusState},
     types::wasm::{
-        ClientState, ConsensusState, CosmosClientState, CosmosConsensusState, Misbehaviour,
-        PartialConsensusState, Status, WasmHeader,
+        ClientState, ConsensusState, Misbehaviour, Status, WasmHeader,
     },
-    util::{to_generic_err, u64_to_big_endian, wrap_response, to_binary},
+    util::{u64_to_big_endian, wrap_failure, wrap_response, to_binary},
 };
 use crate::{state::State, traits::FromRlp, traits::ToRlp, types::header::Header};
+use crate::storage::CosmwasmStorage;
+use crate::traits::Storage as SnapshotStorage;
+use crate::istanbul::is_last_block_of_epoch;
 
-use cosmwasm_std::{attr, to_vec, Binary};
-use cosmwasm_std::{Deps, DepsMut, Env, MessageInfo};
-use cosmwasm_std::{HandleResponse, InitResponse, StdError, StdResult};
+use cosmwasm_std::{attr, from_slice, to_vec, Binary};
+use cosmwasm_std::{Deps, DepsMut, Env, MessageInfo, Storage};
+use cosmwasm_std::{HandleResponse, InitResponse, MigrateResponse, StdError, StdResult};
 
+use num::cast::ToPrimitive;
+use serde::Serialize;
+use std::convert::TryFrom;
 use std::str::FromStr;
 
 // # A few notes on certain design decisions
@@ -72,12 +97,11 @@ use std::str::FromStr;
 // implemented
 //
 // ### Counterparty Consensus State
-// Essentially this is Cosmos/Tendermint consensus state coming from the other side of the bridge. For now it's almost empty datastructure,
-// use as a placeholder.
+// Essentially this is Cosmos/Tendermint consensus state coming from the other side of the bridge.
 //
 // ### Serialization
-// I assumed that proof and counterparty_consensus_state are encoded with JsonMarshaller.
-// It's likely that amino / protobuf binary encoding will be used...
+// I assumed that proof is encoded with JsonMarshaller. The counterparty client and consensus states
+// are passed as protobuf `Any` bytes (exactly as ibc-go stores them), see `types/tendermint.rs`.
 //
 // ### Vocabulary (hint for the reader)
 // CeloLC on CosmosNetwork:
@@ -89,7 +113,7 @@ use std::str::FromStr;
 // * counterparty_consensus_state - CeloConsensusState
 
 pub(crate) fn init(
-    _deps: DepsMut,
+    deps: DepsMut,
     _env: Env,
     _info: MessageInfo,
     _msg: HandleMsg,
@@ -98,16 +122,28 @@ pub(crate) fn init(
     // call expects ClientState included in the return.
     //
     // Therefore it's better to execute whole logic in the second call.
+    set_state_version(deps.storage, LightConsensusState::VERSION)?;
+
     Ok(InitResponse::default())
 }
 
+pub(crate) fn migrate(
+    deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    _msg: MigrateMsg,
+) -> Result<MigrateResponse, StdError> {
+    // The error code is carried along with the error message
+    migrate_stored_states(deps).map_err(StdError::from)
+}
+
 pub(crate) fn handle(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
     msg: HandleMsg,
 ) -> Result<HandleResponse, StdError> {
-    match msg {
+    let response = match msg {
         HandleMsg::InitializeState {
             consensus_state,
             me,
@@ -119,6 +155,20 @@ pub(crate) fn handle(
             me,
         } => check_header_and_update_state(deps, env, me, consensus_state, header),
 
+        HandleMsg::CheckEpochHeadersAndUpdateState {
+            epoch_headers,
+            header,
+            consensus_state,
+            me,
+        } => check_epoch_headers_and_update_state(
+            deps,
+            env,
+            me,
+            consensus_state,
+            epoch_headers,
+            header,
+        ),
+
         HandleMsg::CheckMisbehaviourAndUpdateState {
             me,
             misbehaviour,
@@ -172,7 +222,10 @@ pub(crate) fn handle(
             env,
             me,
         ),
-    }
+    };
+
+    // The error code is carried along with the error message
+    response.map_err(StdError::from)
 }
 
 pub(crate) fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
@@ -195,7 +248,9 @@ pub(crate) fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
             proof,
             counterparty_client_state,
             consensus_state,
-        ).map(to_binary),
+        )
+        .or_else(|err| wrap_failure(&err, "verify_client_state"))
+        .map(to_binary),
 
         QueryMsg::VerifyClientConsensusState {
             me,
@@ -217,7 +272,9 @@ pub(crate) fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
             proof,
             counterparty_consensus_state,
             consensus_state,
-        ).map(to_binary),
+        )
+        .or_else(|err| wrap_failure(&err, "verify_client_consensus_state"))
+        .map(to_binary),
 
         QueryMsg::VerifyConnectionState {
             me,
@@ -237,7 +294,9 @@ pub(crate) fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
             connection_id,
             connection_end,
             consensus_state,
-        ).map(to_binary),
+        )
+        .or_else(|err| wrap_failure(&err, "verify_connection_state"))
+        .map(to_binary),
 
         QueryMsg::VerifyChannelState {
             me,
@@ -259,7 +318,9 @@ pub(crate) fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
             channel_id,
             channel,
             consensus_state,
-        ).map(to_binary),
+        )
+        .or_else(|err| wrap_failure(&err, "verify_channel_state"))
+        .map(to_binary),
 
         QueryMsg::VerifyPacketCommitment {
             me,
@@ -287,7 +348,9 @@ pub(crate) fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
             sequence,
             commitment_bytes,
             consensus_state,
-        ).map(to_binary),
+        )
+        .or_else(|err| wrap_failure(&err, "verify_packet_commitment"))
+        .map(to_binary),
 
         QueryMsg::VerifyPacketAcknowledgement {
             me,
@@ -315,7 +378,9 @@ pub(crate) fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
             sequence,
             acknowledgement,
             consensus_state,
-        ).map(to_binary),
+        )
+        .or_else(|err| wrap_failure(&err, "verify_packet_acknowledgment"))
+        .map(to_binary),
 
         QueryMsg::VerifyPacketReceiptAbsence {
             me,
@@ -341,7 +406,9 @@ pub(crate) fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
             delay_block_period,
             sequence,
             consensus_state,
-        ).map(to_binary),
+        )
+        .or_else(|err| wrap_failure(&err, "verify_packet_receipt_absence"))
+        .map(to_binary),
 
         QueryMsg::VerifyNextSequenceRecv {
             me,
@@ -367,7 +434,55 @@ pub(crate) fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
             delay_block_period,
             next_sequence_recv,
             consensus_state,
-        ).map(to_binary),
+        )
+        .or_else(|err| wrap_failure(&err, "verify_next_sequence_recv"))
+        .map(to_binary),
+
+        QueryMsg::VerifyMembership {
+            me,
+            height,
+            delay_time_period,
+            delay_block_period,
+            proof,
+            path,
+            value,
+            consensus_state,
+        } => verify_membership_at_path(
+            deps,
+            env,
+            me,
+            height,
+            delay_time_period,
+            delay_block_period,
+            proof,
+            path,
+            value,
+            consensus_state,
+        )
+        .or_else(|err| wrap_failure(&err, "verify_membership"))
+        .map(to_binary),
+
+        QueryMsg::VerifyNonMembership {
+            me,
+            height,
+            delay_time_period,
+            delay_block_period,
+            proof,
+            path,
+            consensus_state,
+        } => verify_non_membership_at_path(
+            deps,
+            env,
+            me,
+            height,
+            delay_time_period,
+            delay_block_period,
+            proof,
+            path,
+            consensus_state,
+        )
+        .or_else(|err| wrap_failure(&err, "verify_non_membership"))
+        .map(to_binary),
 
         QueryMsg::ProcessedTime { height } => {
             let processed_time = get_processed_time(deps.storage, EMPTY_PREFIX, &height)?;
@@ -376,11 +491,126 @@ pub(crate) fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
             })?)
         },
 
+        QueryMsg::ConsensusStatesCount {} => {
+            let count = get_consensus_states_count(deps.storage, EMPTY_PREFIX)?;
+            Ok(cosmwasm_std::to_binary(&ConsensusStatesCountResponse { count })?)
+        },
+
+        QueryMsg::ConsensusState { height } => {
+            let response = query_consensus_state(deps, &height)?;
+            Ok(cosmwasm_std::to_binary(&response)?)
+        },
+
+        QueryMsg::LatestConsensusState {} => {
+            let height = match get_latest_consensus_height(deps.storage, EMPTY_PREFIX)? {
+                Some(height) => height,
+                None => return Err(StdError::not_found("consensus state not found")),
+            };
+            let response = query_consensus_state(deps, &height)?;
+            Ok(cosmwasm_std::to_binary(&response)?)
+        },
+
+        QueryMsg::ListConsensusHeights { start_after, limit } => {
+            let limit = limit.unwrap_or(DEFAULT_QUERY_LIMIT).min(MAX_QUERY_LIMIT) as usize;
+            let heights =
+                get_consensus_heights(deps.storage, EMPTY_PREFIX, start_after.as_ref(), limit)?;
+            Ok(cosmwasm_std::to_binary(&ListConsensusHeightsResponse { heights })?)
+        },
+
+        QueryMsg::NextConsensusStateAfter { height } => {
+            let next_height = get_next_consensus_height(deps.storage, EMPTY_PREFIX, &height)?;
+            let consensus_state = match next_height {
+                Some(height) => Some(query_consensus_state(deps, &height)?),
+                None => None,
+            };
+            Ok(cosmwasm_std::to_binary(&AdjacentConsensusStateResponse { consensus_state })?)
+        },
+
+        QueryMsg::PrevConsensusStateBefore { height } => {
+            let prev_height = get_prev_consensus_height(deps.storage, EMPTY_PREFIX, &height)?;
+            let consensus_state = match prev_height {
+                Some(height) => Some(query_consensus_state(deps, &height)?),
+                None => None,
+            };
+            Ok(cosmwasm_std::to_binary(&AdjacentConsensusStateResponse { consensus_state })?)
+        },
+
         QueryMsg::Status {
             me,
             consensus_state,
-        } => status(deps, env, me, consensus_state).map(to_binary),
+        } => status(deps, env, me, consensus_state)
+            .map(to_binary)
+            .map_err(StdError::from),
+    }
+}
+
+// MAX_EPOCH_HEADERS_PER_UPDATE bounds the size of a single headers bundle. Every epoch header
+// requires an aggregated BLS signature verification, which dominates the cost of the update. At the
+// gas budget of 470k per verified header, 20 epoch headers and the final header fit a 10M gas
+// transaction (see README and `test_epoch_headers_gas_per_bundle_size`).
+const MAX_EPOCH_HEADERS_PER_UPDATE: usize = 20;
+
+// Bounds for the number of entries returned by list queries
+const DEFAULT_QUERY_LIMIT: u32 = 10;
+const MAX_QUERY_LIMIT: u32 = 30;
+
+// migrate_stored_states re-encodes the consensus states held by the contract store with the
+// current encoding version.
+//
+// The client state and the consensus states kept by the host are not stored by the contract,
+// these are decoded from any known version and re-encoded with the current one on update.
+fn migrate_stored_states(deps: DepsMut) -> Result<MigrateResponse, ContractError> {
+    let from_version = get_state_version(deps.storage)?;
+    let to_version = LightConsensusState::VERSION;
+
+    if from_version > to_version {
+        return Err(ContractError::invalid_message(format!(
+            "cannot migrate stored states from newer version {} to {}",
+            from_version, to_version
+        )));
+    }
+
+    let mut migrated: u64 = 0;
+    let mut backfilled: u64 = 0;
+    if from_version < to_version {
+        for &prefix in [EMPTY_PREFIX, SUBJECT_PREFIX, SUBSTITUTE_PREFIX].iter() {
+            // The legacy contract didn't index the consensus heights
+            backfilled += backfill_iteration_keys(deps.storage, prefix)?;
+            let heights = get_consensus_heights(deps.storage, prefix, None, usize::MAX)?;
+
+            for height in heights.iter() {
+                // Most of the consensus states are kept by the host, only the metadata is stored
+                let bytes = match get_consensus_state(deps.storage, prefix, height) {
+                    Ok(bytes) => bytes,
+                    Err(_) => continue,
+                };
+
+                // The legacy contract stored the copied consensus states JSON encoded
+                let bytes = from_slice::<Vec<u8>>(&bytes).unwrap_or(bytes);
+
+                let mut consensus_state = decode_stored_consensus_state(&bytes)?;
+                consensus_state.data = consensus_state.light_consensus_state()?.to_versioned();
+
+                let bytes = encode_any(WASM_CONSENSUS_STATE_TYPE_URL, &consensus_state)?;
+                set_consensus_state(deps.storage, prefix, height, &bytes)?;
+                migrated += 1;
+            }
+        }
+
+        set_state_version(deps.storage, to_version)?;
     }
+
+    Ok(MigrateResponse {
+        messages: vec![],
+        attributes: vec![
+            attr("action", "migrate"),
+            attr("from_version", from_version),
+            attr("to_version", to_version),
+            attr("migrated_consensus_states", migrated),
+            attr("backfilled_consensus_states", backfilled),
+        ],
+        data: None,
+    })
 }
 
 fn init_contract(
@@ -389,24 +619,51 @@ fn init_contract(
     _info: MessageInfo,
     consensus_state: ConsensusState,
     me: ClientState,
-) -> Result<HandleResponse, StdError> {
+) -> Result<HandleResponse, ContractError> {
     // Unmarshal initial state entry (ie. validator set, epoch_size etc.)
     let light_consensus_state: LightConsensusState =
-        from_base64_rlp(&consensus_state.data, "msg.initial_state_entry")?;
+        from_base64_versioned(&consensus_state.data, "msg.initial_state_entry")?;
 
     // Verify initial state
     match light_consensus_state.verify() {
         Err(e) => {
-            return Err(StdError::generic_err(format!(
-                "Initial state verification failed. Error: {}",
-                e
-            )))
+            return Err(ContractError::verification(
+                e,
+                "Initial state verification failed",
+            ))
         }
         _ => {}
     }
 
-    // Set metadata for initial consensus state
-    set_consensus_meta(&env, deps.storage, EMPTY_PREFIX, &me.latest_height.unwrap())?;
+    // Unmarshal state config
+    let light_client_state: LightClientState = from_base64_versioned(&me.data, "msg.light_client_state")?;
+    let is_ascending = light_client_state
+        .revision_start_heights
+        .windows(2)
+        .all(|pair| pair[0] < pair[1]);
+    if !is_ascending {
+        return Err(ContractError::invalid_message(
+            "Revision start heights must be in ascending order",
+        ));
+    }
+
+    // Initial height must follow the Celo revision scheme
+    let height = light_client_state.celo_height(light_consensus_state.number);
+    if me.latest_height != Some(height) {
+        return Err(ContractError::invalid_message(format!(
+            "Initial height mismatch, expected: {}, got: {:?}",
+            height, me.latest_height
+        )));
+    }
+
+    // Store the initial consensus state along with its metadata and snapshot
+    store_consensus_state(
+        &env,
+        deps.storage,
+        &height,
+        &consensus_state,
+        &light_consensus_state,
+    )?;
 
     // Update the state
     let response_data = Binary(to_vec(&InitializeStateResult {
@@ -430,44 +687,162 @@ fn check_header_and_update_state(
     me: ClientState,
     consensus_state: ConsensusState,
     wasm_header: WasmHeader,
-) -> Result<HandleResponse, StdError> {
-    let current_timestamp: u64 = env.block.time;
+) -> Result<HandleResponse, ContractError> {
+    ingest_headers_and_update_state(
+        deps,
+        env,
+        me,
+        consensus_state,
+        Vec::new(),
+        Some(wasm_header),
+        "update_block",
+    )
+}
 
-    // Unmarshal header
-    let header: Header = from_base64_rlp(&wasm_header.data, "msg.header")?;
+fn check_epoch_headers_and_update_state(
+    deps: DepsMut,
+    env: Env,
+    me: ClientState,
+    consensus_state: ConsensusState,
+    epoch_headers: Vec<WasmHeader>,
+    header: Option<WasmHeader>,
+) -> Result<HandleResponse, ContractError> {
+    if epoch_headers.is_empty() && header.is_none() {
+        return Err(ContractError::invalid_message(
+            "Headers bundle should contain at least one header",
+        ));
+    }
+
+    if epoch_headers.len() > MAX_EPOCH_HEADERS_PER_UPDATE {
+        return Err(ContractError::invalid_message(format!(
+            "Headers bundle contains too many epoch headers, {} > {}",
+            epoch_headers.len(),
+            MAX_EPOCH_HEADERS_PER_UPDATE
+        )));
+    }
+
+    ingest_headers_and_update_state(
+        deps,
+        env,
+        me,
+        consensus_state,
+        epoch_headers,
+        header,
+        "update_epoch_headers",
+    )
+}
+
+// ingest_headers_and_update_state applies the epoch headers (in order) followed by the optional final
+// header on top of the trusted consensus state. A failure of any header fails the whole update, so
+// the contract state is never partially updated.
+fn ingest_headers_and_update_state(
+    deps: DepsMut,
+    env: Env,
+    me: ClientState,
+    consensus_state: ConsensusState,
+    epoch_headers: Vec<WasmHeader>,
+    header: Option<WasmHeader>,
+    action: &'static str,
+) -> Result<HandleResponse, ContractError> {
+    let current_timestamp: u64 = env.block.time;
 
     // Unmarshal state entry
     let light_consensus_state: LightConsensusState =
-        from_base64_rlp(&consensus_state.data, "msg.light_consensus_state")?;
+        from_base64_versioned(&consensus_state.data, "msg.light_consensus_state")?;
 
     // Unmarshal state config
-    let light_client_state: LightClientState = from_base64_rlp(&me.data, "msg.light_client_state")?;
+    let light_client_state: LightClientState = from_base64_versioned(&me.data, "msg.light_client_state")?;
+
+    // Unmarshal headers
+    let mut headers: Vec<(Header, Height)> = Vec::new();
+    for wasm_header in epoch_headers.iter() {
+        let header: Header = from_base64_rlp(&wasm_header.data, "msg.epoch_header")?;
+        let header_number = header_number(&header)?;
+
+        if !is_last_block_of_epoch(header_number, light_client_state.epoch_size) {
+            return Err(ContractError::invalid_message(format!(
+                "Header num: {} is not an epoch header",
+                header_number
+            )));
+        }
+
+        headers.push((header, wasm_header.height));
+    }
+
+    if let Some(wasm_header) = header {
+        let header: Header = from_base64_rlp(&wasm_header.data, "msg.header")?;
+        headers.push((header, wasm_header.height));
+    }
+
+    // Heights must follow the Celo revision scheme
+    for (header, height) in headers.iter() {
+        let expected_height = light_client_state.celo_height(header_number(header)?);
+        if *height != expected_height {
+            return Err(ContractError::invalid_message(format!(
+                "Header height mismatch, expected: {}, got: {}",
+                expected_height, height
+            )));
+        }
+    }
+
+    // Headers are expected to be ordered by height
+    for pair in headers.windows(2) {
+        if pair[0].1 >= pair[1].1 {
+            return Err(ContractError::invalid_message(format!(
+                "Headers should be ordered by height, {} >= {}",
+                pair[0].1, pair[1].1
+            )));
+        }
+    }
 
-    // Ingest new header
+    // Ingest new headers
     let mut state: State = State::new(light_consensus_state, &light_client_state);
-    match state.insert_header(&header, current_timestamp) {
-        Err(e) => {
-            return Err(StdError::generic_err(format!(
-                "Unable to ingest header. Error: {}",
-                e
-            )))
+    for (header, _) in headers.iter() {
+        match state.insert_header(&header, current_timestamp) {
+            Err(e) => {
+                return Err(ContractError::verification(
+                    e,
+                    format!("Unable to ingest header num: {}", header.number),
+                ))
+            }
+            _ => {}
         }
-        _ => {}
     }
 
+    // The consensus state is built from the last applied header
+    let (header, height) = headers.last().unwrap();
+
     // Update the state
     let new_client_state = me.clone();
     let new_consensus_state = ConsensusState {
         code_id: consensus_state.code_id,
-        data: base64::encode(state.snapshot().to_rlp().as_slice()),
+        data: base64::encode(state.snapshot().to_versioned().as_slice()),
         timestamp: header.time,
         root: MerkleRoot {
             hash: base64::encode(header.root.to_vec().as_slice()),
         },
     };
 
-    // set metadata for this consensus state
-    set_consensus_meta(&env, deps.storage, EMPTY_PREFIX, &wasm_header.height)?;
+    // Remove (a bounded number of) consensus states past the trusting period
+    let pruned = prune_expired_consensus_states(
+        deps.storage,
+        EMPTY_PREFIX,
+        current_timestamp,
+        light_client_state.trusting_period,
+        PRUNE_LIMIT,
+    )?;
+
+    // Store the new consensus state along with its metadata and snapshot
+    store_consensus_state(
+        &env,
+        deps.storage,
+        height,
+        &new_consensus_state,
+        state.snapshot(),
+    )?;
+
+    // Remove (a bounded number of) snapshots no consensus state is backed by anymore
+    let pruned_snapshots = prune_snapshots(deps.storage, EMPTY_PREFIX)?;
 
     let response_data = Binary(to_vec(&CheckHeaderAndUpdateStateResult {
         new_client_state,
@@ -478,8 +853,11 @@ fn check_header_and_update_state(
     Ok(HandleResponse {
         messages: vec![],
         attributes: vec![
-            attr("action", "update_block"),
+            attr("action", action),
             attr("last_consensus_state_height", state.snapshot().number),
+            attr("applied_headers", headers.len()),
+            attr("pruned_consensus_states", pruned),
+            attr("pruned_snapshots", pruned_snapshots),
         ],
         data: Some(response_data),
     })
@@ -494,111 +872,141 @@ pub fn verify_upgrade_and_update_state(
     client_upgrade_proof: String,
     consensus_state_upgrade_proof: String,
     last_height_consensus_state: ConsensusState,
-) -> Result<HandleResponse, StdError> {
-    let specs = vec![ics23::iavl_spec(), ics23::tendermint_spec()];
+) -> Result<HandleResponse, ContractError> {
+    let light_client_state: LightClientState = from_base64_versioned(&me.data, "msg.light_client_state")?;
+    if light_client_state.upgrade_path.is_empty() {
+        return Err(ContractError::invalid_message(
+            "cannot upgrade client, no upgrade path set",
+        ));
+    }
 
     // Sanity check
-    if !(new_client_state.latest_height > me.latest_height) {
-        return Err(StdError::generic_err(format!(
-            "upgraded client height {:?} must be at greater than current client height {:?}",
-            new_client_state.latest_height, me.latest_height
+    let last_height = me
+        .latest_height
+        .ok_or_else(|| ContractError::invalid_message("client latest height is missing"))?;
+    let new_height = new_client_state
+        .latest_height
+        .ok_or_else(|| {
+            ContractError::invalid_message("upgraded client latest height is missing")
+        })?;
+
+    if !(new_height > last_height) {
+        return Err(ContractError::invalid_message(format!(
+            "upgraded client height {} must be at greater than current client height {}",
+            new_height, last_height
         )));
     }
 
-    // Unmarshal proofs
-    let proof_client: MerkleProof =
-        from_base64_json_slice(&client_upgrade_proof, "msg.client_proof")?;
-    let proof_consensus: MerkleProof =
-        from_base64_json_slice(&consensus_state_upgrade_proof, "msg.consensus_proof")?;
-
-    // Unmarshal root
-    let root: Vec<u8> = from_base64(
-        &last_height_consensus_state.root.hash,
-        "msg.last_height_consensus_state.root",
-    )?;
-
     // Check consensus state expiration
     let current_timestamp: u64 = env.block.time;
-    let light_client_state: LightClientState = from_base64_rlp(&me.data, "msg.light_client_state")?;
     if is_expired(
         current_timestamp,
         last_height_consensus_state.timestamp,
         &light_client_state,
     ) {
-        return Err(StdError::generic_err("cannot upgrade an expired client"));
+        return Err(ContractError::expired("cannot upgrade an expired client"));
+    }
+
+    // The upgraded client state must be committed by the chain with custom fields zeroed
+    let new_light_client_state: LightClientState =
+        from_base64_versioned(&new_client_state.data, "msg.new_light_client_state")?;
+    if new_light_client_state != new_light_client_state.zero_custom_fields() {
+        return Err(ContractError::invalid_message(
+            "upgraded client state must have custom fields zeroed",
+        ));
     }
 
-    // Verify client proof
-    let value: Vec<u8> = to_vec(&new_client_state)?;
+    // Unmarshal root (Celo state root at the last height of the current client)
+    let root: Vec<u8> = from_base64(
+        &last_height_consensus_state.root.hash,
+        "msg.last_height_consensus_state.root",
+    )?;
+
+    // Verify client proof (the chain stores the upgraded client as protobuf Any)
+    let value = encode_any(
+        WASM_CLIENT_STATE_TYPE_URL,
+        &ProtoClientState::try_from(&new_client_state)?,
+    )?;
     let upgrade_client_path = construct_upgrade_merkle_path(
         &light_client_state.upgrade_path,
-        ClientUpgradePath::UpgradedClientState(me.latest_height.unwrap().revision_number),
+        ClientUpgradePath::UpgradedClientState(last_height.revision_height),
     );
-    if !verify_membership(
-        &proof_consensus,
-        &specs,
+    verify_commitment_membership(
+        &light_client_state,
+        &client_upgrade_proof,
         &root,
         &upgrade_client_path,
         value,
-        0,
-    )? {
-        return Err(StdError::generic_err(
-            "proof membership verification failed (invalid proof)",
-        ));
-    }
+    )?;
 
     // Verify consensus proof
-    let value: Vec<u8> = to_vec(&new_consensus_state)?;
+    let new_consensus_state_bytes = encode_any(
+        WASM_CONSENSUS_STATE_TYPE_URL,
+        &ProtoConsensusState::try_from(&new_consensus_state)?,
+    )?;
     let upgrade_consensus_state_path = construct_upgrade_merkle_path(
         &light_client_state.upgrade_path,
-        ClientUpgradePath::UpgradedClientConsensusState(me.latest_height.unwrap().revision_number),
+        ClientUpgradePath::UpgradedClientConsensusState(last_height.revision_height),
     );
-    if !verify_membership(
-        &proof_client,
-        &specs,
+    verify_commitment_membership(
+        &light_client_state,
+        &consensus_state_upgrade_proof,
         &root,
         &upgrade_consensus_state_path,
-        value,
-        0,
-    )? {
-        return Err(StdError::generic_err(
-            "proof membership verification failed (invalid proof)",
-        ));
-    }
+        new_consensus_state_bytes,
+    )?;
+
+    // The chain-specified parameters come from the upgraded client, while the custom fields
+    // are preserved from the current one
+    let upgraded_light_client_state = new_light_client_state.with_custom_fields(&light_client_state);
+    let upgraded_client_state = ClientState {
+        data: base64::encode(upgraded_light_client_state.to_versioned()),
+        code_id: new_client_state.code_id,
+        frozen: false,
+        frozen_height: None,
+        latest_height: Some(new_height),
+    };
 
-    // set metadata for this consensus state
-    set_consensus_meta(
+    // Persist the upgraded consensus state along with its metadata and snapshot
+    let new_light_consensus_state: LightConsensusState =
+        from_base64_versioned(&new_consensus_state.data, "msg.new_light_consensus_state")?;
+    store_consensus_state(
         &env,
         deps.storage,
-        EMPTY_PREFIX,
-        &new_client_state.latest_height.unwrap(),
+        &new_height,
+        &new_consensus_state,
+        &new_light_consensus_state,
     )?;
 
     // Build up the response
-    wrap_response(
-        &VerifyUpgradeAndUpdateStateResult {
-            result: ClientStateCallResponseResult::success(),
-            // NOTE: The contents of client or consensus state
-            // are subject to change (once we have end-to-end test flow)
-            new_client_state,
-            new_consensus_state,
-        },
-        "verify_client_state",
-    )
+    let response_data = Binary(to_vec(&VerifyUpgradeAndUpdateStateResult {
+        result: ClientStateCallResponseResult::success(),
+        new_client_state: upgraded_client_state,
+        new_consensus_state,
+    })?);
+
+    Ok(HandleResponse {
+        messages: vec![],
+        attributes: vec![
+            attr("action", "verify_upgrade_and_update_state"),
+            attr("upgraded_height", new_height),
+        ],
+        data: Some(response_data),
+    })
 }
 
 pub fn check_misbehaviour(
     _deps: DepsMut,
-    _env: Env,
+    env: Env,
     me: ClientState,
     misbehaviour: Misbehaviour,
     consensus_state1: ConsensusState,
     consensus_state2: ConsensusState,
-) -> Result<HandleResponse, StdError> {
-    // The header heights are expected to be the same
-    if misbehaviour.header_1.height != misbehaviour.header_2.height {
-        return Err(StdError::generic_err(format!(
-            "Misbehaviour header heights differ, {} != {}",
+) -> Result<HandleResponse, ContractError> {
+    // The first header is expected to be at the same or greater height than the second one
+    if misbehaviour.header_1.height < misbehaviour.header_2.height {
+        return Err(ContractError::invalid_misbehaviour(format!(
+            "Misbehaviour header_1 height is less than header_2 height, {} < {}",
             misbehaviour.header_1.height, misbehaviour.header_2.height
         )));
     }
@@ -608,7 +1016,7 @@ pub fn check_misbehaviour(
         && me.frozen_height.is_some()
         && me.frozen_height.unwrap() <= misbehaviour.header_1.height
     {
-        return Err(StdError::generic_err(format!(
+        return Err(ContractError::frozen(format!(
             "Client is already frozen at earlier height {} than misbehaviour height {}",
             me.frozen_height.unwrap(),
             misbehaviour.header_1.height
@@ -619,17 +1027,17 @@ pub fn check_misbehaviour(
     let header_1: Header = from_base64_rlp(&misbehaviour.header_1.data, "msg.header")?;
     let header_2: Header = from_base64_rlp(&misbehaviour.header_2.data, "msg.header")?;
 
-    // The header state root should differ
-    if header_1.root == header_2.root {
-        return Err(StdError::generic_err(
-            "Header's state roots should differ, but are the same",
-        ));
-    }
+    // Unmarshal state config
+    let light_client_state: LightClientState = from_base64_versioned(&me.data, "msg.light_client_state")?;
+
+    // Make sure the headers are an actual evidence of misbehaviour
+    verify_misbehaviour_evidence(&misbehaviour, &light_client_state, &header_1, &header_2)?;
 
     // Check the validity of the two conflicting headers against their respective
     // trusted consensus states
-    check_misbehaviour_header(1, &me, &consensus_state1, &header_1)?;
-    check_misbehaviour_header(2, &me, &consensus_state2, &header_2)?;
+    let current_timestamp: u64 = env.block.time;
+    check_misbehaviour_header(1, &me, &consensus_state1, &header_1, current_timestamp)?;
+    check_misbehaviour_header(2, &me, &consensus_state2, &header_2, current_timestamp)?;
 
     // Store the new state
     let mut new_client_state = me.clone();
@@ -651,21 +1059,91 @@ pub fn check_misbehaviour(
     })
 }
 
+// verify_misbehaviour_evidence checks whether two headers are a valid proof of misbehaviour, that is:
+// * headers at the same height have different hashes (equivocation)
+// * header at greater height has timestamp not greater than the other one (BFT time violation)
+// The heights of the misbehaviour headers must match the block numbers of the (RLP) headers,
+// otherwise honest headers at different heights could be passed off as the conflicting ones.
+fn verify_misbehaviour_evidence(
+    misbehaviour: &Misbehaviour,
+    light_client_state: &LightClientState,
+    header_1: &Header,
+    header_2: &Header,
+) -> Result<(), ContractError> {
+    let number_1 = misbehaviour_header_number(1, light_client_state, &misbehaviour.header_1, header_1)?;
+    let number_2 = misbehaviour_header_number(2, light_client_state, &misbehaviour.header_2, header_2)?;
+
+    if number_1 == number_2 {
+        let hash_1 = header_1
+            .hash()
+            .map_err(|e| ContractError::verification(e, "Unable to hash header_1"))?;
+        let hash_2 = header_2
+            .hash()
+            .map_err(|e| ContractError::verification(e, "Unable to hash header_2"))?;
+
+        if hash_1 == hash_2 {
+            return Err(ContractError::invalid_misbehaviour(
+                "Headers at the same height should have different hashes, but are the same",
+            ));
+        }
+    } else if header_1.time > header_2.time {
+        return Err(ContractError::invalid_misbehaviour(format!(
+            "Headers at different heights should violate BFT time monotonicity, {} > {}",
+            header_1.time, header_2.time
+        )));
+    }
+
+    Ok(())
+}
+
+// header_number returns the block number of the submitted header
+fn header_number(header: &Header) -> Result<u64, ContractError> {
+    header.number.to_u64().ok_or_else(|| {
+        ContractError::invalid_message(format!("Header number is out of range: {}", header.number))
+    })
+}
+
+// misbehaviour_header_number returns the block number of the header, once checked against the
+// height the header was submitted at
+fn misbehaviour_header_number(
+    num: u16,
+    light_client_state: &LightClientState,
+    wasm_header: &WasmHeader,
+    header: &Header,
+) -> Result<u64, ContractError> {
+    let number = header.number.to_u64().ok_or_else(|| {
+        ContractError::invalid_misbehaviour(format!(
+            "Header_{} number is out of range: {}",
+            num, header.number
+        ))
+    })?;
+
+    let height = light_client_state.celo_height(number);
+    if height != wasm_header.height {
+        return Err(ContractError::invalid_misbehaviour(format!(
+            "Header_{} height mismatch, expected: {}, got: {}",
+            num, height, wasm_header.height
+        )));
+    }
+
+    Ok(number)
+}
+
 // zero_custom_fields returns a ClientState that is a copy of the current ClientState
 // with all client customizable fields zeroed out
 pub fn zero_custom_fields(
     _deps: DepsMut,
     _env: Env,
     me: ClientState,
-) -> Result<HandleResponse, StdError> {
+) -> Result<HandleResponse, ContractError> {
+    let light_client_state: LightClientState = from_base64_versioned(&me.data, "msg.light_client_state")?;
+
     let new_client_state = ClientState {
         code_id: me.code_id,
         frozen: false,
         frozen_height: None,
-        latest_height: None,
-
-        // No custom fields in light client state
-        data: me.data.clone(),
+        latest_height: me.latest_height,
+        data: base64::encode(light_client_state.zero_custom_fields().to_versioned()),
     };
 
     // Build up the response
@@ -682,63 +1160,84 @@ pub fn check_misbehaviour_header(
     me: &ClientState,
     consensus_state: &ConsensusState,
     header: &Header,
-) -> Result<(), StdError> {
+    current_timestamp: u64,
+) -> Result<(), ContractError> {
     // Unmarshal state entry
     let light_consensus_state: LightConsensusState =
-        from_base64_rlp(&consensus_state.data, "msg.light_consensus_state")?;
+        from_base64_versioned(&consensus_state.data, "msg.light_consensus_state")?;
 
     // Unmarshal state config
-    let light_client_state: LightClientState = from_base64_rlp(&me.data, "msg.light_client_state")?;
+    let light_client_state: LightClientState = from_base64_versioned(&me.data, "msg.light_client_state")?;
+
+    // Trusted consensus state must be within the trusting period
+    if is_expired(
+        current_timestamp,
+        light_consensus_state.timestamp,
+        &light_client_state,
+    ) {
+        return Err(ContractError::expired(format!(
+            "Consensus state for header num: {} is expired (trusting period has passed)",
+            num
+        )));
+    }
 
     // Verify header
     let state: State = State::new(light_consensus_state, &light_client_state);
     match state.verify_header_seal(&header) {
         Err(e) => {
-            return Err(StdError::generic_err(format!(
-                "Failed to verify header num: {} against it's consensus state. Error: {}",
-                num, e
-            )))
+            return Err(ContractError::verification(
+                e,
+                format!("Failed to verify header num: {} against it's consensus state", num),
+            ))
         }
         _ => return Ok(()),
     }
 }
 
 pub fn verify_client_state(
-    _deps: Deps,
-    _env: Env,
-    _me: ClientState,
-    _height: Height,
+    deps: Deps,
+    env: Env,
+    me: ClientState,
+    height: Height,
     commitment_prefix: MerklePrefix,
     counterparty_client_identifier: String,
     proof: String,
-    counterparty_client_state: CosmosClientState,
+    counterparty_client_state: String,
     proving_consensus_state: ConsensusState,
-) -> Result<HandleResponse, StdError> {
-    // Unmarshal proof
-    let proof: MerkleProof = from_base64_json_slice(&proof, "msg.proof")?;
-    let specs = vec![ics23::iavl_spec(), ics23::tendermint_spec()];
-
-    // Get root from proving (celo) consensus state
-    let root: Vec<u8> = from_base64(
-        &proving_consensus_state.root.hash,
-        "msg.proving_consensus_state.root",
-    )?;
+) -> Result<HandleResponse, ContractError> {
+    // Unmarshal state config
+    let light_client_state: LightClientState = from_base64_versioned(&me.data, "msg.light_client_state")?;
 
     // Build path (proof is used to validate the existance of value under that path)
     let client_prefixed_path = IcsPath::ClientState(
-        ClientId::from_str(&counterparty_client_identifier).map_err(to_generic_err)?,
+        ClientId::from_str(&counterparty_client_identifier).map_err(ContractError::invalid_identifier)?,
     )
     .to_string();
 
     // Verify proof against key-value pair
-    let path = apply_prefix(&commitment_prefix, vec![client_prefixed_path])?;
-    let value: Vec<u8> = to_vec(&counterparty_client_state)?;
+    let path = apply_commitment_prefix(
+        &light_client_state,
+        &commitment_prefix,
+        vec![client_prefixed_path],
+    )?;
+    // The proof commits to the exact (protobuf Any) bytes stored by the counterparty
+    let value: Vec<u8> = from_base64(
+        &counterparty_client_state,
+        "msg.counterparty_client_state",
+    )?;
+    decode_client_state(&value)?;
 
-    if !verify_membership(&proof, &specs, &root, &path, value, 0)? {
-        return Err(StdError::generic_err(
-            "proof membership verification failed (invalid proof)",
-        ));
-    }
+    verify_path(
+        deps,
+        &env,
+        &light_client_state,
+        height,
+        None,
+        &proof,
+        &path,
+        Some(value),
+        &proving_consensus_state,
+    )?;
 
     // Build up the response
     wrap_response(
@@ -750,44 +1249,52 @@ pub fn verify_client_state(
 }
 
 pub fn verify_client_consensus_state(
-    _deps: Deps,
-    _env: Env,
-    _me: ClientState,
-    _height: Height,
+    deps: Deps,
+    env: Env,
+    me: ClientState,
+    height: Height,
     consensus_height: Height,
     commitment_prefix: MerklePrefix,
     counterparty_client_identifier: String,
     proof: String,
-    counterparty_consensus_state: CosmosConsensusState,
+    counterparty_consensus_state: String,
     proving_consensus_state: ConsensusState,
-) -> Result<HandleResponse, StdError> {
-    // Unmarshal proof
-    let proof: MerkleProof = from_base64_json_slice(&proof, "msg.proof")?;
-    let specs = vec![ics23::iavl_spec(), ics23::tendermint_spec()];
-
-    // Get root from proving (celo) consensus state
-    let root: Vec<u8> = from_base64(
-        &proving_consensus_state.root.hash,
-        "msg.proving_consensus_state.root",
-    )?;
+) -> Result<HandleResponse, ContractError> {
+    // Unmarshal state config
+    let light_client_state: LightClientState = from_base64_versioned(&me.data, "msg.light_client_state")?;
 
     // Build path (proof is used to validate the existance of value under that path)
     let client_prefixed_path = IcsPath::ClientConsensusState {
-        client_id: ClientId::from_str(&counterparty_client_identifier).map_err(to_generic_err)?,
+        client_id: ClientId::from_str(&counterparty_client_identifier).map_err(ContractError::invalid_identifier)?,
         epoch: consensus_height.revision_number,
         height: consensus_height.revision_height,
     }
     .to_string();
 
     // Verify proof against key-value pair
-    let path = apply_prefix(&commitment_prefix, vec![client_prefixed_path])?;
-    let value: Vec<u8> = to_vec(&counterparty_consensus_state)?;
+    let path = apply_commitment_prefix(
+        &light_client_state,
+        &commitment_prefix,
+        vec![client_prefixed_path],
+    )?;
+    // The proof commits to the exact (protobuf Any) bytes stored by the counterparty
+    let value: Vec<u8> = from_base64(
+        &counterparty_consensus_state,
+        "msg.counterparty_consensus_state",
+    )?;
+    decode_consensus_state(&value)?;
 
-    if !verify_membership(&proof, &specs, &root, &path, value, 0)? {
-        return Err(StdError::generic_err(
-            "proof membership verification failed (invalid proof)",
-        ));
-    }
+    verify_path(
+        deps,
+        &env,
+        &light_client_state,
+        height,
+        None,
+        &proof,
+        &path,
+        Some(value),
+        &proving_consensus_state,
+    )?;
 
     // Build up the response
     wrap_response(
@@ -799,40 +1306,41 @@ pub fn verify_client_consensus_state(
 }
 
 pub fn verify_connection_state(
-    _deps: Deps,
-    _env: Env,
-    _me: ClientState,
-    _height: Height,
+    deps: Deps,
+    env: Env,
+    me: ClientState,
+    height: Height,
     commitment_prefix: MerklePrefix,
     proof: String,
     connection_id: String,
     connection_end: ConnectionEnd,
     consensus_state: ConsensusState,
-) -> Result<HandleResponse, StdError> {
-    // Unmarshal proof
-    let proof: MerkleProof = from_base64_json_slice(&proof, "msg.proof")?;
-    let specs = vec![ics23::iavl_spec(), ics23::tendermint_spec()];
-
-    // Get root from proving (celo) consensus state
-    let root: Vec<u8> = from_base64(
-        &consensus_state.root.hash,
-        "msg.proving_consensus_state.root",
-    )?;
+) -> Result<HandleResponse, ContractError> {
+    // Unmarshal state config
+    let light_client_state: LightClientState = from_base64_versioned(&me.data, "msg.light_client_state")?;
 
     // Build path (proof is used to validate the existance of value under that path)
     let connection_path =
-        IcsPath::Connections(ConnectionId::from_str(&connection_id).map_err(to_generic_err)?)
+        IcsPath::Connections(ConnectionId::from_str(&connection_id).map_err(ContractError::invalid_identifier)?)
             .to_string();
 
     // Verify proof against key-value pair
-    let path = apply_prefix(&commitment_prefix, vec![connection_path])?;
-    let value: Vec<u8> = to_vec(&connection_end)?;
+    let path =
+        apply_commitment_prefix(&light_client_state, &commitment_prefix, vec![connection_path])?;
+    let value: Vec<u8> =
+        committed_value::<_, ProtoConnectionEnd>(&light_client_state, &connection_end)?;
 
-    if !verify_membership(&proof, &specs, &root, &path, value, 0)? {
-        return Err(StdError::generic_err(
-            "proof membership verification failed (invalid proof)",
-        ));
-    }
+    verify_path(
+        deps,
+        &env,
+        &light_client_state,
+        height,
+        None,
+        &proof,
+        &path,
+        Some(value),
+        &consensus_state,
+    )?;
 
     // Build up the response
     wrap_response(
@@ -844,43 +1352,43 @@ pub fn verify_connection_state(
 }
 
 pub fn verify_channel_state(
-    _deps: Deps,
-    _env: Env,
-    _me: ClientState,
-    _height: Height,
+    deps: Deps,
+    env: Env,
+    me: ClientState,
+    height: Height,
     commitment_prefix: MerklePrefix,
     proof: String,
     port_id: String,
     channel_id: String,
     channel: Channel,
     consensus_state: ConsensusState,
-) -> Result<HandleResponse, StdError> {
-    // Unmarshal proof
-    let proof: MerkleProof = from_base64_json_slice(&proof, "msg.proof")?;
-    let specs = vec![ics23::iavl_spec(), ics23::tendermint_spec()];
-
-    // Get root from proving (celo) consensus state
-    let root: Vec<u8> = from_base64(
-        &consensus_state.root.hash,
-        "msg.proving_consensus_state.root",
-    )?;
+) -> Result<HandleResponse, ContractError> {
+    // Unmarshal state config
+    let light_client_state: LightClientState = from_base64_versioned(&me.data, "msg.light_client_state")?;
 
     // Build path (proof is used to validate the existance of value under that path)
     let channel_path = IcsPath::ChannelEnds(
-        PortId::from_str(&port_id).map_err(to_generic_err)?,
-        ChannelId::from_str(&channel_id).map_err(to_generic_err)?,
+        PortId::from_str(&port_id).map_err(ContractError::invalid_identifier)?,
+        ChannelId::from_str(&channel_id).map_err(ContractError::invalid_identifier)?,
     )
     .to_string();
 
     // Verify proof against key-value pair
-    let path = apply_prefix(&commitment_prefix, vec![channel_path])?;
-    let value: Vec<u8> = to_vec(&channel)?;
+    let path =
+        apply_commitment_prefix(&light_client_state, &commitment_prefix, vec![channel_path])?;
+    let value: Vec<u8> = committed_value::<_, ProtoChannel>(&light_client_state, &channel)?;
 
-    if !verify_membership(&proof, &specs, &root, &path, value, 0)? {
-        return Err(StdError::generic_err(
-            "proof membership verification failed (invalid proof)",
-        ));
-    }
+    verify_path(
+        deps,
+        &env,
+        &light_client_state,
+        height,
+        None,
+        &proof,
+        &path,
+        Some(value),
+        &consensus_state,
+    )?;
 
     // Build up the response
     wrap_response(
@@ -894,7 +1402,7 @@ pub fn verify_channel_state(
 pub fn verify_packet_commitment(
     deps: Deps,
     env: Env,
-    _me: ClientState,
+    me: ClientState,
     height: Height,
     commitment_prefix: MerklePrefix,
     proof: String,
@@ -905,41 +1413,34 @@ pub fn verify_packet_commitment(
     sequence: u64,
     commitment_bytes: String,
     consensus_state: ConsensusState,
-) -> Result<HandleResponse, StdError> {
-    // Unmarshal proof
-    let proof: MerkleProof = from_base64_json_slice(&proof, "msg.proof")?;
-    let specs = vec![ics23::iavl_spec(), ics23::tendermint_spec()];
-
-    // Get root from proving (celo) consensus state
-    let root: Vec<u8> = from_base64(&consensus_state.root.hash, "msg.consensus_state.root")?;
-
-    // Check delay period has passed
-    verify_delay_period_passed(
-        deps,
-        height,
-        env.block.height,
-        env.block.time,
-        delay_time_period,
-        delay_block_period,
-    )?;
+) -> Result<HandleResponse, ContractError> {
+    // Unmarshal state config
+    let light_client_state: LightClientState = from_base64_versioned(&me.data, "msg.light_client_state")?;
 
     // Build path (proof is used to validate the existance of value under that path)
     let commitment_path = IcsPath::Commitments {
-        port_id: PortId::from_str(&port_id).map_err(to_generic_err)?,
-        channel_id: ChannelId::from_str(&channel_id).map_err(to_generic_err)?,
+        port_id: PortId::from_str(&port_id).map_err(ContractError::invalid_identifier)?,
+        channel_id: ChannelId::from_str(&channel_id).map_err(ContractError::invalid_identifier)?,
         sequence: Sequence::from(sequence),
     }
     .to_string();
 
     // Verify proof against key-value pair
-    let path = apply_prefix(&commitment_prefix, vec![commitment_path])?;
+    let path =
+        apply_commitment_prefix(&light_client_state, &commitment_prefix, vec![commitment_path])?;
     let value: Vec<u8> = from_base64(&commitment_bytes, "msg.commitment_bytes")?;
 
-    if !verify_membership(&proof, &specs, &root, &path, value, 0)? {
-        return Err(StdError::generic_err(
-            "proof membership verification failed (invalid proof)",
-        ));
-    }
+    verify_path(
+        deps,
+        &env,
+        &light_client_state,
+        height,
+        Some((delay_time_period, delay_block_period)),
+        &proof,
+        &path,
+        Some(value),
+        &consensus_state,
+    )?;
 
     // Build up the response
     wrap_response(
@@ -953,7 +1454,7 @@ pub fn verify_packet_commitment(
 pub fn verify_packet_acknowledgment(
     deps: Deps,
     env: Env,
-    _me: ClientState,
+    me: ClientState,
     height: Height,
     commitment_prefix: MerklePrefix,
     proof: String,
@@ -964,41 +1465,34 @@ pub fn verify_packet_acknowledgment(
     sequence: u64,
     acknowledgement: String,
     consensus_state: ConsensusState,
-) -> Result<HandleResponse, StdError> {
-    // Unmarshal proof
-    let proof: MerkleProof = from_base64_json_slice(&proof, "msg.proof")?;
-    let specs = vec![ics23::iavl_spec(), ics23::tendermint_spec()];
-
-    // Get root from proving (celo) consensus state
-    let root: Vec<u8> = from_base64(&consensus_state.root.hash, "msg.consensus_state.root")?;
-
-    // Check delay period has passed
-    verify_delay_period_passed(
-        deps,
-        height,
-        env.block.height,
-        env.block.time,
-        delay_time_period,
-        delay_block_period,
-    )?;
+) -> Result<HandleResponse, ContractError> {
+    // Unmarshal state config
+    let light_client_state: LightClientState = from_base64_versioned(&me.data, "msg.light_client_state")?;
 
     // Build path (proof is used to validate the existance of value under that path)
     let ack_path = IcsPath::Acks {
-        port_id: PortId::from_str(&port_id).map_err(to_generic_err)?,
-        channel_id: ChannelId::from_str(&channel_id).map_err(to_generic_err)?,
+        port_id: PortId::from_str(&port_id).map_err(ContractError::invalid_identifier)?,
+        channel_id: ChannelId::from_str(&channel_id).map_err(ContractError::invalid_identifier)?,
         sequence: Sequence::from(sequence),
     }
     .to_string();
 
     // Verify proof against key-value pair
-    let path = apply_prefix(&commitment_prefix, vec![ack_path])?;
+    let path =
+        apply_commitment_prefix(&light_client_state, &commitment_prefix, vec![ack_path])?;
     let value: Vec<u8> = from_base64(&acknowledgement, "msg.acknowledgement")?;
 
-    if !verify_membership(&proof, &specs, &root, &path, value, 0)? {
-        return Err(StdError::generic_err(
-            "proof membership verification failed (invalid proof)",
-        ));
-    }
+    verify_path(
+        deps,
+        &env,
+        &light_client_state,
+        height,
+        Some((delay_time_period, delay_block_period)),
+        &proof,
+        &path,
+        Some(value),
+        &consensus_state,
+    )?;
 
     // Build up the response
     wrap_response(
@@ -1012,7 +1506,7 @@ pub fn verify_packet_acknowledgment(
 pub fn verify_packet_receipt_absence(
     deps: Deps,
     env: Env,
-    _me: ClientState,
+    me: ClientState,
     height: Height,
     commitment_prefix: MerklePrefix,
     proof: String,
@@ -1022,45 +1516,34 @@ pub fn verify_packet_receipt_absence(
     delay_block_period: u64,
     sequence: u64,
     consensus_state: ConsensusState,
-) -> Result<HandleResponse, StdError> {
-    // Unmarshal proof
-    let proof: MerkleProof = from_base64_json_slice(&proof, "msg.proof")?;
-    let specs = vec![ics23::iavl_spec(), ics23::tendermint_spec()];
-
-    // Get root from proving (celo) consensus state
-    let root: Vec<u8> = from_base64(&consensus_state.root.hash, "msg.consensus_state.root")?;
-
-    // Check delay period has passed
-    verify_delay_period_passed(
-        deps,
-        height,
-        env.block.height,
-        env.block.time,
-        delay_time_period,
-        delay_block_period,
-    )?;
+) -> Result<HandleResponse, ContractError> {
+    // Unmarshal state config
+    let light_client_state: LightClientState = from_base64_versioned(&me.data, "msg.light_client_state")?;
 
     // Build path (proof is used to validate the existance of value under that path)
     let reciept_path = IcsPath::Receipts {
-        port_id: PortId::from_str(&port_id).map_err(to_generic_err)?,
-        channel_id: ChannelId::from_str(&channel_id).map_err(to_generic_err)?,
+        port_id: PortId::from_str(&port_id).map_err(ContractError::invalid_identifier)?,
+        channel_id: ChannelId::from_str(&channel_id).map_err(ContractError::invalid_identifier)?,
         sequence: Sequence::from(sequence),
     }
     .to_string();
 
     // Apply prefix
-    let path = apply_prefix(&commitment_prefix, vec![reciept_path])?;
-
-    // Verify single proof against key-value pair
-    let key: &[u8] = path.key_path.last().unwrap().as_bytes();
+    let path =
+        apply_commitment_prefix(&light_client_state, &commitment_prefix, vec![reciept_path])?;
 
-    // Reference: cosmos-sdk/x/ibc/core/23-commitment/types/merkle.go
-    // TODO: ics23-rs library doesn't seem to offer subroot calculation for non_exist
-    if !ics23::verify_non_membership(&proof.proofs[0], &specs[0], &root, key) {
-        return Err(StdError::generic_err(
-            "proof non membership verification failed (invalid proof)",
-        ));
-    }
+    // Verify absence of the key
+    verify_path(
+        deps,
+        &env,
+        &light_client_state,
+        height,
+        Some((delay_time_period, delay_block_period)),
+        &proof,
+        &path,
+        None,
+        &consensus_state,
+    )?;
 
     // Build up the response
     wrap_response(
@@ -1074,7 +1557,7 @@ pub fn verify_packet_receipt_absence(
 pub fn verify_next_sequence_recv(
     deps: Deps,
     env: Env,
-    _me: ClientState,
+    me: ClientState,
     height: Height,
     commitment_prefix: MerklePrefix,
     proof: String,
@@ -1084,40 +1567,36 @@ pub fn verify_next_sequence_recv(
     delay_block_period: u64,
     next_sequence_recv: u64,
     consensus_state: ConsensusState,
-) -> Result<HandleResponse, StdError> {
-    // Unmarshal proof
-    let proof: MerkleProof = from_base64_json_slice(&proof, "msg.proof")?;
-    let specs = vec![ics23::iavl_spec(), ics23::tendermint_spec()];
-
-    // Get root from proving (celo) consensus state
-    let root: Vec<u8> = from_base64(&consensus_state.root.hash, "msg.consensus_state.root")?;
-
-    // Check delay period has passed
-    verify_delay_period_passed(
-        deps,
-        height,
-        env.block.height,
-        env.block.time,
-        delay_time_period,
-        delay_block_period,
-    )?;
+) -> Result<HandleResponse, ContractError> {
+    // Unmarshal state config
+    let light_client_state: LightClientState = from_base64_versioned(&me.data, "msg.light_client_state")?;
 
     // Build path (proof is used to validate the existance of value under that path)
     let next_sequence_recv_path = IcsPath::SeqRecvs(
-        PortId::from_str(&port_id).map_err(to_generic_err)?,
-        ChannelId::from_str(&channel_id).map_err(to_generic_err)?,
+        PortId::from_str(&port_id).map_err(ContractError::invalid_identifier)?,
+        ChannelId::from_str(&channel_id).map_err(ContractError::invalid_identifier)?,
     )
     .to_string();
 
     // Verify proof against key-value pair
-    let path = apply_prefix(&commitment_prefix, vec![next_sequence_recv_path])?;
+    let path = apply_commitment_prefix(
+        &light_client_state,
+        &commitment_prefix,
+        vec![next_sequence_recv_path],
+    )?;
     let value: Vec<u8> = u64_to_big_endian(next_sequence_recv);
 
-    if !verify_membership(&proof, &specs, &root, &path, value, 0)? {
-        return Err(StdError::generic_err(
-            "proof membership verification failed (invalid proof)",
-        ));
-    }
+    verify_path(
+        deps,
+        &env,
+        &light_client_state,
+        height,
+        Some((delay_time_period, delay_block_period)),
+        &proof,
+        &path,
+        Some(value),
+        &consensus_state,
+    )?;
 
     // Build up the response
     wrap_response(
@@ -1128,6 +1607,84 @@ pub fn verify_next_sequence_recv(
     )
 }
 
+pub fn verify_membership_at_path(
+    deps: Deps,
+    env: Env,
+    me: ClientState,
+    height: Height,
+    delay_time_period: u64,
+    delay_block_period: u64,
+    proof: String,
+    path: MerklePath,
+    value: String,
+    consensus_state: ConsensusState,
+) -> Result<HandleResponse, ContractError> {
+    // Unmarshal state config
+    let light_client_state: LightClientState = from_base64_versioned(&me.data, "msg.light_client_state")?;
+
+    // The path is expected to be prefixed already
+    check_prefix_rule(&light_client_state, &path)?;
+    let value: Vec<u8> = from_base64(&value, "msg.value")?;
+
+    verify_path(
+        deps,
+        &env,
+        &light_client_state,
+        height,
+        Some((delay_time_period, delay_block_period)),
+        &proof,
+        &path,
+        Some(value),
+        &consensus_state,
+    )?;
+
+    // Build up the response
+    wrap_response(
+        &VerifyMembershipResult {
+            result: ClientStateCallResponseResult::success(),
+        },
+        "verify_membership",
+    )
+}
+
+pub fn verify_non_membership_at_path(
+    deps: Deps,
+    env: Env,
+    me: ClientState,
+    height: Height,
+    delay_time_period: u64,
+    delay_block_period: u64,
+    proof: String,
+    path: MerklePath,
+    consensus_state: ConsensusState,
+) -> Result<HandleResponse, ContractError> {
+    // Unmarshal state config
+    let light_client_state: LightClientState = from_base64_versioned(&me.data, "msg.light_client_state")?;
+
+    // The path is expected to be prefixed already
+    check_prefix_rule(&light_client_state, &path)?;
+
+    verify_path(
+        deps,
+        &env,
+        &light_client_state,
+        height,
+        Some((delay_time_period, delay_block_period)),
+        &proof,
+        &path,
+        None,
+        &consensus_state,
+    )?;
+
+    // Build up the response
+    wrap_response(
+        &VerifyNonMembershipResult {
+            result: ClientStateCallResponseResult::success(),
+        },
+        "verify_non_membership",
+    )
+}
+
 pub fn check_substitute_client_state(
     deps: DepsMut,
     env: Env,
@@ -1135,93 +1692,99 @@ pub fn check_substitute_client_state(
     substitute_client_state: ClientState,
     subject_consensus_state: ConsensusState,
     initial_height: Height,
-) -> Result<HandleResponse, StdError> {
-    if substitute_client_state.latest_height.unwrap() != initial_height {
-        return Err(StdError::generic_err(format!(
+) -> Result<HandleResponse, ContractError> {
+    let substitute_latest_height = substitute_client_state
+        .latest_height
+        .ok_or_else(|| {
+            ContractError::invalid_message("substitute client latest height is missing")
+        })?;
+
+    if substitute_latest_height.revision_number != initial_height.revision_number {
+        return Err(ContractError::invalid_message(format!(
             "substitute client revision number must equal initial height revision number ({} != {})",
-            me.latest_height.unwrap(), initial_height
+            substitute_latest_height.revision_number, initial_height.revision_number
         )));
     }
 
     let light_subject_client_state: LightClientState =
-        from_base64_rlp(&me.data, "msg.light_subject_client_state")?;
-    let light_substitute_client_state: LightClientState = from_base64_rlp(
+        from_base64_versioned(&me.data, "msg.light_subject_client_state")?;
+    let light_substitute_client_state: LightClientState = from_base64_versioned(
         &substitute_client_state.data,
         "msg.light_substitute_client_state",
     )?;
 
-    if light_substitute_client_state != light_subject_client_state {
-        return Err(StdError::generic_err(
+    // The client parameters must match, except for the client customizable fields
+    if light_substitute_client_state.zero_custom_fields()
+        != light_subject_client_state.zero_custom_fields()
+    {
+        return Err(ContractError::invalid_message(
             "subject client state does not match substitute client state",
         ));
     }
 
     let current_timestamp: u64 = env.block.time;
-    let mut new_client_state = me.clone();
 
-    if me.frozen && me.frozen_height.is_some() {
-        if light_subject_client_state.allow_update_after_misbehavior {
-            return Err(StdError::generic_err(
-                "client is not allowed to be unfrozen",
-            ));
+    // Only a frozen or expired client can be substituted
+    if is_frozen(&me) {
+        if !light_subject_client_state.allow_update_after_misbehavior {
+            return Err(ContractError::frozen("client is not allowed to be unfrozen"));
         }
-
-        new_client_state.frozen = false;
-        new_client_state.frozen_height = None;
     } else if is_expired(
         current_timestamp,
         subject_consensus_state.timestamp,
         &light_subject_client_state,
     ) {
         if !light_subject_client_state.allow_update_after_expiry {
-            return Err(StdError::generic_err(
-                "client is not allowed to be unexpired",
-            ));
+            return Err(ContractError::expired("client is not allowed to be unexpired"));
         }
+    } else {
+        return Err(ContractError::invalid_message("cannot substitute an active client"));
     }
 
-    // Copy consensus states and processed time from substitute to subject
-    // starting from initial height and ending on the latest height (inclusive)
-    let latest_height = substitute_client_state.latest_height.unwrap();
-    for i in initial_height.revision_height..latest_height.revision_height + 1 {
-        let height = Height {
-            revision_height: i,
-            revision_number: latest_height.revision_number,
-        };
-
-        let cs_bytes = get_consensus_state(deps.storage, SUBSTITUTE_PREFIX, &height);
-        if cs_bytes.is_ok() {
-            set_consensus_state(deps.storage, SUBJECT_PREFIX, &height, &cs_bytes.unwrap())?;
-        }
-
-        set_consensus_meta(&env, deps.storage, SUBJECT_PREFIX, &height)?;
-    }
+    // Copy consensus states and their metadata from substitute to subject
+    let copied = copy_consensus_states(deps.storage, SUBSTITUTE_PREFIX, SUBJECT_PREFIX)?;
+    copy_snapshots(deps.storage, SUBSTITUTE_PREFIX, SUBJECT_PREFIX)?;
 
-    new_client_state.latest_height = substitute_client_state.latest_height;
+    // The subject client keeps its custom fields, except for the trusting period
+    let mut light_client_state = light_subject_client_state;
+    light_client_state.trusting_period = light_substitute_client_state.trusting_period;
 
+    // Substitute client must not be expired
     let latest_consensus_state_bytes =
-        get_consensus_state(deps.storage, SUBJECT_PREFIX, &me.latest_height.unwrap())?;
-
-    let latest_consensus_state =
-        PartialConsensusState::decode(latest_consensus_state_bytes.as_slice()).unwrap();
+        get_consensus_state(deps.storage, SUBJECT_PREFIX, &substitute_latest_height)?;
     let latest_light_consensus_state: LightConsensusState =
-        LightConsensusState::from_rlp(&latest_consensus_state.data).map_err(to_generic_err)?;
+        decode_stored_consensus_state(&latest_consensus_state_bytes)?.light_consensus_state()?;
 
     if is_expired(
         current_timestamp,
         latest_light_consensus_state.timestamp,
-        &light_subject_client_state,
+        &light_client_state,
     ) {
-        return Err(StdError::generic_err("updated subject client is expired"));
+        return Err(ContractError::expired("updated subject client is expired"));
     }
 
-    wrap_response(
-        &CheckSubstituteAndUpdateStateResult {
-            result: ClientStateCallResponseResult::success(),
-            new_client_state,
-        },
-        "check_substitute_and_update_state",
-    )
+    let new_client_state = ClientState {
+        data: base64::encode(light_client_state.to_versioned()),
+        code_id: me.code_id,
+        frozen: false,
+        frozen_height: None,
+        latest_height: Some(substitute_latest_height),
+    };
+
+    // Build up the response
+    let response_data = Binary(to_vec(&CheckSubstituteAndUpdateStateResult {
+        result: ClientStateCallResponseResult::success(),
+        new_client_state,
+    })?);
+
+    Ok(HandleResponse {
+        messages: vec![],
+        attributes: vec![
+            attr("action", "check_substitute_and_update_state"),
+            attr("copied_consensus_states", copied),
+        ],
+        data: Some(response_data),
+    })
 }
 
 fn status(
@@ -1229,19 +1792,19 @@ fn status(
     env: Env,
     me: ClientState,
     consensus_state: ConsensusState,
-) -> Result<HandleResponse, StdError> {
+) -> Result<HandleResponse, ContractError> {
     let current_timestamp: u64 = env.block.time;
     let mut status = Status::Active;
 
     // Unmarshal state config
-    let light_client_state: LightClientState = from_base64_rlp(&me.data, "msg.light_client_state")?;
+    let light_client_state: LightClientState = from_base64_versioned(&me.data, "msg.light_client_state")?;
 
-    if me.frozen {
+    if is_frozen(&me) {
         status = Status::Frozen;
     } else {
         // Unmarshal state entry
         let light_consensus_state: LightConsensusState =
-            from_base64_rlp(&consensus_state.data, "msg.light_consensus_state")?;
+            from_base64_versioned(&consensus_state.data, "msg.light_consensus_state")?;
 
         if is_expired(
             current_timestamp,
@@ -1256,120 +1819,1798 @@ fn status(
     wrap_response(&StatusResult { status }, "status")
 }
 
-// verify_delay_period_passed will ensure that at least delayPeriod amount of time has passed since consensus state was submitted
-// before allowing verification to continue
-fn verify_delay_period_passed(
-    deps: Deps,
-    proof_height: Height,
-    current_height: u64,
-    current_timestamp: u64,
-    delay_time_period: u64,
-    delay_block_period: u64,
-) -> Result<(), StdError> {
-    let processed_time = get_processed_time(deps.storage, EMPTY_PREFIX, &proof_height)?;
-    let valid_time = processed_time + delay_time_period;
+// apply_commitment_prefix prefixes the ICS-24 path according to the client prefix rule
+fn apply_commitment_prefix(
+    light_client_state: &LightClientState,
+    prefix: &MerklePrefix,
+    path: Vec<String>,
+) -> Result<MerklePath, ContractError> {
+    match &light_client_state.prefix_rule {
+        PrefixRule::Counterparty => Ok(apply_prefix(prefix, path)?),
+        PrefixRule::Fixed { key_prefix } => {
+            if &prefix.key_prefix != key_prefix {
+                return Err(ContractError::invalid_message(format!(
+                    "commitment prefix mismatch, expected: {}, got: {}",
+                    key_prefix, prefix.key_prefix
+                )));
+            }
+
+            Ok(apply_prefix(prefix, path)?)
+        }
+        PrefixRule::NoPrefix => Ok(MerklePath { key_path: path }),
+    }
+}
 
-    if current_timestamp < valid_time {
-        return Err(StdError::generic_err(format!(
-            "cannot verify packet until time: {}, current time: {}",
-            valid_time, current_timestamp
-        )));
+// check_prefix_rule checks whether already prefixed path complies with the client prefix rule
+fn check_prefix_rule(
+    light_client_state: &LightClientState,
+    path: &MerklePath,
+) -> Result<(), ContractError> {
+    if path.key_path.is_empty() {
+        return Err(ContractError::invalid_message("empty merkle path"));
     }
 
-    let processed_height: Height = get_processed_height(deps.storage, EMPTY_PREFIX, &proof_height)?;
-    let valid_height = Height {
-        revision_number: processed_height.revision_number,
-        revision_height: processed_height.revision_height + delay_block_period,
-    };
+    if let PrefixRule::Fixed { key_prefix } = &light_client_state.prefix_rule {
+        let prefix = base64::decode(key_prefix).map_err(|e| ContractError::Parse {
+            target: String::from("client.prefix_rule.key_prefix"),
+            msg: e.to_string(),
+        })?;
+        if path.key_path[0].as_bytes() != prefix.as_slice() {
+            return Err(ContractError::invalid_message(format!(
+                "commitment prefix mismatch, expected: {}, got: {}",
+                key_prefix,
+                base64::encode(&path.key_path[0])
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+// verify_path verifies the proof of the merkle path against the consensus state root, once the
+// delay period (if any) has passed. `None` value stands for the non-membership proof.
+fn verify_path(
+    deps: Deps,
+    env: &Env,
+    light_client_state: &LightClientState,
+    height: Height,
+    delay_period: Option<(u64, u64)>,
+    proof: &String,
+    path: &MerklePath,
+    value: Option<Vec<u8>>,
+    consensus_state: &ConsensusState,
+) -> Result<(), ContractError> {
+    // Get root from proving (celo) consensus state
+    let root: Vec<u8> = from_base64(&consensus_state.root.hash, "msg.consensus_state.root")?;
+
+    // Check delay period has passed
+    if let Some((delay_time_period, delay_block_period)) = delay_period {
+        verify_delay_period_passed(
+            deps,
+            height,
+            &env.block.chain_id,
+            env.block.height,
+            env.block.time,
+            delay_time_period,
+            delay_block_period,
+        )?;
+    }
+
+    match value {
+        Some(value) => verify_commitment_membership(light_client_state, proof, &root, path, value),
+        None => verify_commitment_non_membership(light_client_state, proof, &root, path),
+    }
+}
+
+// to_ics23_specs translates the configured proof specs into ICS-23 proof specs
+fn to_ics23_specs(proof_specs: &[ProofSpec]) -> Result<Vec<ics23::ProofSpec>, ContractError> {
+    if proof_specs.is_empty() {
+        return Err(ContractError::invalid_message("no proof specs configured"));
+    }
+
+    proof_specs
+        .iter()
+        .map(|spec| match spec {
+            ProofSpec::Iavl => Ok(ics23::iavl_spec()),
+            ProofSpec::Tendermint => Ok(ics23::tendermint_spec()),
+            ProofSpec::Custom { spec } => {
+                ics23::ProofSpec::decode(spec.as_slice()).map_err(|e| ContractError::Parse {
+                    target: String::from("client.commitment_scheme.proof_specs"),
+                    msg: e.to_string(),
+                })
+            }
+        })
+        .collect()
+}
+
+// committed_value returns the bytes of the connection or channel end the counterparty commits to.
+// The EVM IBC handler hashes their protobuf encoding (`P`), while the ICS-23 proofs are checked
+// against the JSON encoding.
+fn committed_value<T, P>(
+    light_client_state: &LightClientState,
+    value: &T,
+) -> Result<Vec<u8>, ContractError>
+where
+    T: Serialize,
+    P: Message + for<'a> TryFrom<&'a T, Error = StdError>,
+{
+    match &light_client_state.commitment_scheme {
+        CommitmentScheme::Ics23 { .. } => Ok(to_vec(value)?),
+        CommitmentScheme::EvmMpt { .. } => Ok(encode_message(&P::try_from(value)?)?),
+    }
+}
+
+// verify_commitment_membership verifies the proof of the key-value pair against the consensus state root,
+// according to the client commitment scheme
+fn verify_commitment_membership(
+    light_client_state: &LightClientState,
+    proof: &String,
+    root: &Vec<u8>,
+    path: &MerklePath,
+    value: Vec<u8>,
+) -> Result<(), ContractError> {
+    let verified = match &light_client_state.commitment_scheme {
+        CommitmentScheme::Ics23 { proof_specs } => {
+            let proof: MerkleProof = from_base64_json_slice(proof, "msg.proof")?;
+            let specs = to_ics23_specs(proof_specs)?;
+
+            verify_membership(&proof, &specs, root, path, value, 0)
+                .map_err(ContractError::invalid_proof)?
+        }
+        CommitmentScheme::EvmMpt {
+            ibc_handler_address,
+            commitments_slot,
+            key_prefix,
+        } => {
+            let proof: EvmProof = from_base64_json_slice(proof, "msg.proof")?;
+
+            verify_evm_membership(
+                &proof,
+                root,
+                ibc_handler_address,
+                *commitments_slot,
+                key_prefix,
+                path,
+                value,
+            )
+            .map_err(ContractError::invalid_proof)?
+        }
+    };
+
+    if !verified {
+        return Err(ContractError::invalid_proof(
+            "proof membership verification failed",
+        ));
+    }
+
+    Ok(())
+}
+
+// verify_commitment_non_membership verifies the proof of the key absence against the consensus state root,
+// according to the client commitment scheme
+fn verify_commitment_non_membership(
+    light_client_state: &LightClientState,
+    proof: &String,
+    root: &Vec<u8>,
+    path: &MerklePath,
+) -> Result<(), ContractError> {
+    let verified = match &light_client_state.commitment_scheme {
+        CommitmentScheme::Ics23 { proof_specs } => {
+            let proof: MerkleProof = from_base64_json_slice(proof, "msg.proof")?;
+            let specs = to_ics23_specs(proof_specs)?;
+
+            verify_non_membership(&proof, &specs, root, path)
+                .map_err(ContractError::invalid_proof)?
+        }
+        CommitmentScheme::EvmMpt {
+            ibc_handler_address,
+            commitments_slot,
+            key_prefix,
+        } => {
+            let proof: EvmProof = from_base64_json_slice(proof, "msg.proof")?;
+
+            verify_evm_non_membership(
+                &proof,
+                root,
+                ibc_handler_address,
+                *commitments_slot,
+                key_prefix,
+                path,
+            )
+            .map_err(ContractError::invalid_proof)?
+        }
+    };
+
+    if !verified {
+        return Err(ContractError::invalid_proof(
+            "proof non membership verification failed",
+        ));
+    }
+
+    Ok(())
+}
+
+// store_consensus_state stores the consensus state (protobuf Any encoded, the way the host stores
+// it), so that the consensus state queries can serve it, along with its metadata. The verified
+// snapshot goes to the snapshot storage, keyed by block number.
+fn store_consensus_state(
+    env: &Env,
+    storage: &mut dyn Storage,
+    height: &Height,
+    consensus_state: &ConsensusState,
+    snapshot: &LightConsensusState,
+) -> Result<(), ContractError> {
+    let bytes = encode_any(
+        WASM_CONSENSUS_STATE_TYPE_URL,
+        &ProtoConsensusState::try_from(consensus_state)?,
+    )?;
+    set_consensus_state(storage, EMPTY_PREFIX, height, &bytes)?;
+    set_consensus_meta(env, storage, EMPTY_PREFIX, height)?;
+
+    CosmwasmStorage::new(storage, EMPTY_PREFIX)
+        .put_snapshot(snapshot)
+        .map_err(|e| ContractError::storage(e, "Unable to store snapshot"))
+}
+
+// prune_snapshots removes (a bounded number of) the snapshots taken before the oldest stored
+// consensus state. The latest snapshot is always kept.
+fn prune_snapshots(storage: &mut dyn Storage, prefix: &'static str) -> Result<usize, ContractError> {
+    let oldest_height = match get_consensus_heights(storage, prefix, None, 1)?.first() {
+        Some(height) => height.revision_height,
+        None => return Ok(0),
+    };
+
+    CosmwasmStorage::new(storage, prefix)
+        .prune(oldest_height, PRUNE_LIMIT)
+        .map_err(|e| ContractError::storage(e, "Unable to prune snapshots"))
+}
+
+// copy_snapshots copies the snapshots of the consensus states tracked under one prefix to the
+// other one (ie. from the substitute to the subject client)
+fn copy_snapshots(
+    storage: &mut dyn Storage,
+    from_prefix: &'static str,
+    to_prefix: &'static str,
+) -> Result<(), ContractError> {
+    let heights = get_consensus_heights(storage, from_prefix, None, usize::MAX)?;
+
+    let mut snapshots: Vec<LightConsensusState> = Vec::new();
+    let from = CosmwasmStorage::new(storage, from_prefix);
+    for height in heights.iter() {
+        let snapshot = from
+            .get_snapshot(height.revision_height)
+            .map_err(|e| ContractError::storage(e, "Unable to read snapshot"))?;
+        snapshots.extend(snapshot);
+    }
+
+    let mut to = CosmwasmStorage::new(storage, to_prefix);
+    for snapshot in snapshots.iter() {
+        to.put_snapshot(snapshot)
+            .map_err(|e| ContractError::storage(e, "Unable to store snapshot"))?;
+    }
+
+    Ok(())
+}
+
+fn query_consensus_state(deps: Deps, height: &Height) -> StdResult<ConsensusStateResponse> {
+    let consensus_state = get_consensus_state(deps.storage, EMPTY_PREFIX, height)?;
+
+    Ok(ConsensusStateResponse {
+        height: *height,
+        consensus_state: base64::encode(consensus_state),
+    })
+}
+
+// verify_delay_period_passed will ensure that at least delayPeriod amount of time has passed since consensus state was submitted
+// before allowing verification to continue
+fn verify_delay_period_passed(
+    deps: Deps,
+    proof_height: Height,
+    chain_id: &str,
+    current_height: u64,
+    current_timestamp: u64,
+    delay_time_period: u64,
+    delay_block_period: u64,
+) -> Result<(), ContractError> {
+    let processed_time = get_processed_time(deps.storage, EMPTY_PREFIX, &proof_height)?;
+    let valid_time = processed_time + delay_time_period;
+
+    if current_timestamp < valid_time {
+        return Err(ContractError::DelayPeriodNotPassed {
+            msg: format!(
+                "cannot verify packet until time: {}, current time: {}",
+                valid_time, current_timestamp
+            ),
+        });
+    }
+
+    let processed_height: Height = get_processed_height(deps.storage, EMPTY_PREFIX, &proof_height)?;
+    let valid_height = Height {
+        revision_number: processed_height.revision_number,
+        revision_height: processed_height.revision_height + delay_block_period,
+    };
+
+    let current_height = get_self_height(chain_id, current_height);
+    if current_height < valid_height {
+        return Err(ContractError::DelayPeriodNotPassed {
+            msg: format!(
+                "cannot verify packet until height: {}, current height: {}",
+                valid_height, current_height
+            ),
+        });
+    }
+
+    Ok(())
+}
+
+fn construct_upgrade_merkle_path(
+    upgrade_path: &Vec<String>,
+    client_upgrade_path: ibc::ics24_host::ClientUpgradePath,
+) -> MerklePath {
+    let appended_key = ibc::ics24_host::Path::Upgrade(client_upgrade_path).to_string();
+
+    let mut result: Vec<String> = upgrade_path.clone();
+    result.push(appended_key);
+
+    MerklePath { key_path: result }
+}
+
+// is_frozen returns true if the client is frozen, either by the flag or at a (non-zero) height
+fn is_frozen(me: &ClientState) -> bool {
+    me.frozen || me.frozen_height.map_or(false, |height| !height.is_zero())
+}
+
+fn is_expired(
+    current_timestamp: u64,
+    latest_timestamp: u64,
+    light_client_state: &LightClientState,
+) -> bool {
+    current_timestamp > latest_timestamp.saturating_add(light_client_state.trusting_period)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contract::store::{consensus_state_key, processed_height_key, processed_time_key};
+    use crate::contract::types::ibc::{ChannelCounterparty, MerklePrefix};
+    use crate::contract::types::msg::FailureResult;
+    use crate::contract::types::proto::ProtoMerkleRoot;
+    use crate::contract::types::state::LEGACY_VERSION;
+    use crate::contract::types::tendermint::{
+        TendermintConsensusState, Timestamp, TENDERMINT_CONSENSUS_STATE_TYPE_URL,
+    };
+    use crate::mock::MockChain;
+    use crate::types::istanbul::{IstanbulAggregatedSeal, IstanbulExtra, IstanbulExtraVanity};
+    use cosmwasm_std::testing::{
+        mock_dependencies, mock_env, mock_info, MockApi, MockQuerier, MockStorage,
+    };
+    use cosmwasm_std::OwnedDeps;
+    use num_bigint::BigInt as Integer;
+    use ics23::{
+        calculate_existence_root, CommitmentProof, ExistenceProof, HashOp, InnerOp, InnerSpec,
+        LeafOp, LengthOp, NonExistenceProof, ProofSpec,
+    };
+
+    #[test]
+    fn test_verify_client_consensus_state() {
+        let deps = mock_dependencies(&[]);
+        let env = mock_env();
+
+        let mut light_client_state = get_example_light_client_state(3);
+        light_client_state.commitment_scheme = get_example_commitment_scheme();
+        let mut client_state = get_example_client_state(0, 5);
+        client_state.data = base64::encode(light_client_state.to_versioned());
+
+        let height = new_height(0, 5);
+        let consensus_height = new_height(0, 5);
+
+        let commitment_prefix = MerklePrefix {
+            key_prefix: base64::encode("prefix"),
+        };
+        let counterparty_client_identifier = String::from("07-tendermint-0");
+
+        // The counterparty_consensus_state + commitment_proof comes from the other side of the
+        // bridge, while root "is local" (comes from proving consensus state).
+        //
+        // In the unittest we update provingConsensusState with "remote root", so that validation
+        // always succeeds (as long as verify_membership works properly)
+        let counterparty_consensus_state = get_example_tendermint_consensus_state();
+
+        let (commitment_proof, subroot) = get_example_proof(
+            b"clients/07-tendermint-0/consensusStates/0-5".to_vec(), // key (based on consensus_height)
+            counterparty_consensus_state.clone(),                     // value
+        );
+        let (commitment_proof, root) = get_example_store_proof("prefix", commitment_proof, subroot);
+
+        let proving_consensus_state = get_example_consenus_state(root, height);
+
+        let response = verify_client_consensus_state(
+            deps.as_ref(),
+            env,
+            client_state,
+            height,
+            consensus_height,
+            commitment_prefix,
+            counterparty_client_identifier,
+            base64::encode(to_vec(&commitment_proof).unwrap()),
+            base64::encode(&counterparty_consensus_state),
+            proving_consensus_state,
+        );
+
+        assert_eq!(response.is_err(), false);
+    }
+
+    #[test]
+    fn test_counterparty_state_type_url() {
+        let consensus_state = get_example_tendermint_consensus_state();
+        assert_eq!(decode_consensus_state(&consensus_state).is_ok(), true);
+
+        // Consensus state bytes can't pass for the client state
+        assert_eq!(decode_client_state(&consensus_state).is_err(), true);
+    }
+
+    #[test]
+    fn test_verify_membership_at_path() {
+        let mut deps = mock_dependencies(&[]);
+        let env = mock_env();
+
+        let mut light_client_state = get_example_light_client_state(3);
+        light_client_state.commitment_scheme = get_example_commitment_scheme();
+        let mut client_state = get_example_client_state(0, 5);
+        client_state.data = base64::encode(light_client_state.to_versioned());
+
+        let height = new_height(0, 5);
+        let path = MerklePath {
+            key_path: vec![
+                String::from("ibc"),
+                String::from("channelUpgrades/upgrades/ports/transfer/channels/channel-0"),
+            ],
+        };
+        let value = b"upgrade".to_vec();
+
+        let (proof, subroot) =
+            get_example_proof(path.key_path[1].as_bytes().to_vec(), value.clone());
+        let (proof, root) = get_example_store_proof("ibc", proof, subroot);
+        let proof = base64::encode(to_vec(&proof).unwrap());
+        let consensus_state = get_example_consenus_state(root, height);
+
+        let verify = |deps: Deps, value: &[u8]| {
+            let response = query(
+                deps,
+                mock_env(),
+                QueryMsg::VerifyMembership {
+                    me: client_state.clone(),
+                    height,
+                    delay_time_period: 0,
+                    delay_block_period: 0,
+                    proof: proof.clone(),
+                    path: path.clone(),
+                    value: base64::encode(value),
+                    consensus_state: consensus_state.clone(),
+                },
+            )
+            .unwrap();
+
+            from_slice::<VerifyMembershipResult>(&response).unwrap().result
+        };
+
+        // Delay period can't be checked until the consensus state is processed
+        let result = verify(deps.as_ref(), &value);
+        assert_eq!(result.is_valid, false);
+        assert_eq!(result.error_code, 9);
+
+        set_consensus_meta(&env, &mut deps.storage, EMPTY_PREFIX, &height).unwrap();
+        assert_eq!(verify(deps.as_ref(), &value), ClientStateCallResponseResult::success());
+
+        let result = verify(deps.as_ref(), b"other");
+        assert_eq!(result.is_valid, false);
+        assert_eq!(result.error_code, 5);
+    }
+
+    #[test]
+    fn test_migrate_stored_states() {
+        let mut deps = mock_dependencies(&[]);
+        let env = mock_env();
+
+        // Client store written by the legacy (unversioned) contract: the processed time and
+        // height of each consensus state, without the iteration keys or the count
+        let set_legacy_meta = |storage: &mut MockStorage, prefix: &'static str, height: &Height| {
+            storage.set(
+                &processed_time_key(prefix, height),
+                &to_vec(&env.block.time).unwrap(),
+            );
+            storage.set(
+                &processed_height_key(prefix, height),
+                &to_vec(&new_height(0, env.block.height)).unwrap(),
+            );
+        };
+
+        let mut light_consensus_state = LightConsensusState::new();
+        light_consensus_state.number = 10;
+        light_consensus_state.timestamp = 100;
+
+        let legacy_consensus_state = ProtoConsensusState {
+            code_id: vec![],
+            data: light_consensus_state.to_rlp(),
+            timestamp: 100,
+            root: Some(ProtoMerkleRoot {
+                hash: vec![1_u8; 32],
+            }),
+        };
+        let bytes = encode_any(WASM_CONSENSUS_STATE_TYPE_URL, &legacy_consensus_state).unwrap();
+
+        // Consensus state stored by the host
+        deps.storage.set(&consensus_state_key(EMPTY_PREFIX, &new_height(0, 10)), &bytes);
+        set_legacy_meta(&mut deps.storage, EMPTY_PREFIX, &new_height(0, 10));
+
+        // Consensus state kept by the host (metadata only)
+        set_legacy_meta(&mut deps.storage, EMPTY_PREFIX, &new_height(0, 11));
+
+        // Consensus state copied from the substitute client (JSON encoded)
+        deps.storage.set(
+            &consensus_state_key(SUBJECT_PREFIX, &new_height(0, 10)),
+            &to_vec(&bytes).unwrap(),
+        );
+        set_legacy_meta(&mut deps.storage, SUBJECT_PREFIX, &new_height(0, 10));
+
+        assert!(get_consensus_heights(&deps.storage, EMPTY_PREFIX, None, usize::MAX)
+            .unwrap()
+            .is_empty());
+        assert_eq!(get_consensus_states_count(&deps.storage, EMPTY_PREFIX).unwrap(), 0);
+        assert_eq!(get_state_version(&deps.storage).unwrap(), LEGACY_VERSION);
+
+        let response = migrate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("admin", &[]),
+            MigrateMsg {},
+        )
+        .unwrap();
+        assert_eq!(response.attributes[3], attr("migrated_consensus_states", 2));
+        assert_eq!(response.attributes[4], attr("backfilled_consensus_states", 3));
+        assert_eq!(
+            get_state_version(&deps.storage).unwrap(),
+            LightConsensusState::VERSION
+        );
+
+        assert_eq!(
+            get_consensus_heights(&deps.storage, EMPTY_PREFIX, None, usize::MAX).unwrap(),
+            vec![new_height(0, 10), new_height(0, 11)]
+        );
+        assert_eq!(get_consensus_states_count(&deps.storage, EMPTY_PREFIX).unwrap(), 2);
+        assert_eq!(get_consensus_states_count(&deps.storage, SUBJECT_PREFIX).unwrap(), 1);
+
+        for &prefix in [EMPTY_PREFIX, SUBJECT_PREFIX].iter() {
+            let bytes = get_consensus_state(&deps.storage, prefix, &new_height(0, 10)).unwrap();
+            let migrated = decode_stored_consensus_state(&bytes).unwrap();
+            assert_eq!(migrated.data, light_consensus_state.to_versioned());
+            assert_eq!(migrated.light_consensus_state().unwrap(), light_consensus_state);
+            assert_eq!(migrated.root, legacy_consensus_state.root);
+        }
+
+        // The backfilled consensus states are pruned once expired
+        let pruned = prune_expired_consensus_states(
+            &mut deps.storage,
+            EMPTY_PREFIX,
+            env.block.time + 11,
+            10,
+            PRUNE_LIMIT,
+        )
+        .unwrap();
+        assert_eq!(pruned, 2);
+        assert_eq!(get_consensus_states_count(&deps.storage, EMPTY_PREFIX).unwrap(), 0);
+
+        // Migration of the current version is a no-op
+        let response =
+            migrate(deps.as_mut(), env, mock_info("admin", &[]), MigrateMsg {}).unwrap();
+        assert_eq!(response.attributes[3], attr("migrated_consensus_states", 0));
+        assert_eq!(response.attributes[4], attr("backfilled_consensus_states", 0));
+
+        // Fresh contracts start with the current version
+        let mut deps = mock_dependencies(&[]);
+        init(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            HandleMsg::ZeroCustomFields {
+                me: get_example_client_state(0, 1),
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            get_state_version(&deps.storage).unwrap(),
+            LightConsensusState::VERSION
+        );
+    }
+
+    #[test]
+    fn test_error_codes() {
+        let mut deps = mock_dependencies(&[]);
+        let env = mock_env();
+
+        // Expired trusted consensus state
+        let mut light_client_state = get_example_light_client_state(3);
+        light_client_state.trusting_period = 10;
+        let mut client_state = get_example_client_state(0, 5);
+        client_state.data = base64::encode(light_client_state.to_versioned());
+
+        let misbehaviour = get_example_misbehaviour(
+            get_example_wasm_header(&get_example_header(5, 100), new_height(0, 5)),
+            get_example_wasm_header(&get_example_header(5, 200), new_height(0, 5)),
+        );
+        let err = check_misbehaviour(
+            deps.as_mut(),
+            env.clone(),
+            client_state.clone(),
+            misbehaviour.clone(),
+            get_example_consenus_state(vec![], new_height(0, 5)),
+            get_example_consenus_state(vec![], new_height(0, 5)),
+        )
+        .unwrap_err();
+        assert_eq!(err.code(), 7);
+
+        // Frozen client
+        client_state.frozen = true;
+        client_state.frozen_height = Some(new_height(0, 4));
+        let err = check_misbehaviour(
+            deps.as_mut(),
+            env.clone(),
+            client_state.clone(),
+            misbehaviour,
+            get_example_consenus_state(vec![], new_height(0, 5)),
+            get_example_consenus_state(vec![], new_height(0, 5)),
+        )
+        .unwrap_err();
+        assert_eq!(err.code(), 6);
+
+        // Malformed message
+        client_state.data = String::from("not base64");
+        let err = zero_custom_fields(deps.as_mut(), env.clone(), client_state.clone()).unwrap_err();
+        assert_eq!(err.code(), 2);
+
+        // The code is carried through the query result
+        let response = query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::VerifyClientState {
+                me: client_state,
+                height: new_height(0, 5),
+                commitment_prefix: MerklePrefix {
+                    key_prefix: base64::encode("prefix"),
+                },
+                counterparty_client_identifier: String::from("07-tendermint-0"),
+                proof: String::from(""),
+                counterparty_client_state: String::from(""),
+                consensus_state: get_example_consenus_state(vec![], new_height(0, 5)),
+            },
+        )
+        .unwrap();
+        let result = from_slice::<FailureResult>(&response).unwrap().result;
+        assert_eq!(result.is_valid, false);
+        assert_eq!(result.error_code, 2);
+
+        // The code is carried through the handle entry point
+        let err = handle(
+            deps.as_mut(),
+            env,
+            mock_info("relayer", &[]),
+            HandleMsg::ZeroCustomFields {
+                me: ClientState {
+                    data: String::from("not base64"),
+                    code_id: String::from(""),
+                    frozen: false,
+                    frozen_height: None,
+                    latest_height: None,
+                },
+            },
+        )
+        .unwrap_err();
+        assert_eq!(err.to_string().contains("[2] unable to parse"), true);
+    }
+
+    #[test]
+    fn test_misbehaviour_rejects_identical_headers() {
+        let mut deps = mock_dependencies(&[]);
+        let env = mock_env();
+
+        let header = get_example_header(5, 100);
+        let misbehaviour = get_example_misbehaviour(
+            get_example_wasm_header(&header, new_height(0, 5)),
+            get_example_wasm_header(&header, new_height(0, 5)),
+        );
+
+        let err = check_misbehaviour(
+            deps.as_mut(),
+            env,
+            get_example_misbehaviour_client_state(),
+            misbehaviour,
+            get_example_consenus_state(vec![], new_height(0, 5)),
+            get_example_consenus_state(vec![], new_height(0, 5)),
+        )
+        .unwrap_err();
+
+        assert_eq!(err.code(), 10);
+        assert_eq!(err.to_string().contains("should have different hashes"), true);
+    }
+
+    #[test]
+    fn test_misbehaviour_rejects_monotonic_time() {
+        let mut deps = mock_dependencies(&[]);
+        let env = mock_env();
+
+        let misbehaviour = get_example_misbehaviour(
+            get_example_wasm_header(&get_example_header(6, 200), new_height(0, 6)),
+            get_example_wasm_header(&get_example_header(5, 100), new_height(0, 5)),
+        );
+
+        let err = check_misbehaviour(
+            deps.as_mut(),
+            env,
+            get_example_misbehaviour_client_state(),
+            misbehaviour,
+            get_example_consenus_state(vec![], new_height(0, 6)),
+            get_example_consenus_state(vec![], new_height(0, 5)),
+        )
+        .unwrap_err();
+
+        assert_eq!(err.code(), 10);
+        assert_eq!(err.to_string().contains("should violate BFT time monotonicity"), true);
+    }
+
+    #[test]
+    fn test_misbehaviour_rejects_mislabelled_headers() {
+        let mut deps = mock_dependencies(&[]);
+        let env = mock_env();
+
+        // Honest headers at different heights, both submitted at the same height
+        let misbehaviour = get_example_misbehaviour(
+            get_example_wasm_header(&get_example_header(6, 200), new_height(0, 5)),
+            get_example_wasm_header(&get_example_header(5, 100), new_height(0, 5)),
+        );
+
+        let err = check_misbehaviour(
+            deps.as_mut(),
+            env,
+            get_example_misbehaviour_client_state(),
+            misbehaviour,
+            get_example_consenus_state(vec![], new_height(0, 5)),
+            get_example_consenus_state(vec![], new_height(0, 5)),
+        )
+        .unwrap_err();
+
+        assert_eq!(err.code(), 10);
+        assert_eq!(err.to_string().contains("Header_1 height mismatch"), true);
+
+        // Header submitted under the wrong revision
+        let mut light_client_state = get_example_light_client_state(3);
+        light_client_state.revision_start_heights = vec![4];
+        let header_1 = get_example_header(5, 100);
+        let mut header_2 = get_example_header(5, 100);
+        header_2.gas_used = 1;
+        let misbehaviour = get_example_misbehaviour(
+            get_example_wasm_header(&header_1, new_height(1, 5)),
+            get_example_wasm_header(&header_2, new_height(0, 5)),
+        );
+
+        let err = verify_misbehaviour_evidence(&misbehaviour, &light_client_state, &header_1, &header_2)
+            .unwrap_err();
+        assert_eq!(err.code(), 10);
+        assert_eq!(err.to_string().contains("Header_2 height mismatch"), true);
+    }
+
+    #[test]
+    fn test_misbehaviour_evidence() {
+        let light_client_state = get_example_light_client_state(3);
+        let header_1 = get_example_header(5, 100);
+        let mut header_2 = get_example_header(5, 100);
+        header_2.gas_used = 1;
+
+        // Same height, different hashes
+        let misbehaviour = get_example_misbehaviour(
+            get_example_wasm_header(&header_1, new_height(0, 5)),
+            get_example_wasm_header(&header_2, new_height(0, 5)),
+        );
+        assert_eq!(
+            verify_misbehaviour_evidence(&misbehaviour, &light_client_state, &header_1, &header_2).is_ok(),
+            true
+        );
+
+        // Greater height, earlier timestamp
+        let header_1 = get_example_header(6, 50);
+        let misbehaviour = get_example_misbehaviour(
+            get_example_wasm_header(&header_1, new_height(0, 6)),
+            get_example_wasm_header(&header_2, new_height(0, 5)),
+        );
+        assert_eq!(
+            verify_misbehaviour_evidence(&misbehaviour, &light_client_state, &header_1, &header_2).is_ok(),
+            true
+        );
+    }
+
+    #[test]
+    fn test_prune_expired_consensus_states() {
+        let mut deps = mock_dependencies(&[]);
+        let mut env = mock_env();
+        let trusting_period = 100;
+
+        env.block.time = 1000;
+        for height in 1..4 {
+            set_consensus_meta(&env, &mut deps.storage, EMPTY_PREFIX, &new_height(0, height))
+                .unwrap();
+        }
+
+        env.block.time = 2000;
+        set_consensus_meta(&env, &mut deps.storage, EMPTY_PREFIX, &new_height(0, 4)).unwrap();
+        assert_eq!(get_consensus_states_count(&deps.storage, EMPTY_PREFIX).unwrap(), 4);
+
+        // Prune is bounded by the limit
+        let pruned = prune_expired_consensus_states(
+            &mut deps.storage,
+            EMPTY_PREFIX,
+            1500,
+            trusting_period,
+            2,
+        )
+        .unwrap();
+        assert_eq!(pruned, 2);
+        assert_eq!(get_consensus_states_count(&deps.storage, EMPTY_PREFIX).unwrap(), 2);
+        assert_eq!(
+            get_processed_time(&deps.storage, EMPTY_PREFIX, &new_height(0, 1)).is_err(),
+            true
+        );
+
+        // Consensus states within the trusting period are kept
+        let pruned = prune_expired_consensus_states(
+            &mut deps.storage,
+            EMPTY_PREFIX,
+            1500,
+            trusting_period,
+            PRUNE_LIMIT,
+        )
+        .unwrap();
+        assert_eq!(pruned, 1);
+        assert_eq!(get_consensus_states_count(&deps.storage, EMPTY_PREFIX).unwrap(), 1);
+        assert_eq!(
+            get_processed_time(&deps.storage, EMPTY_PREFIX, &new_height(0, 4)).unwrap(),
+            2000
+        );
+
+        // Trusting period doesn't overflow the expiry time
+        let pruned = prune_expired_consensus_states(
+            &mut deps.storage,
+            EMPTY_PREFIX,
+            u64::MAX,
+            u64::MAX,
+            PRUNE_LIMIT,
+        )
+        .unwrap();
+        assert_eq!(pruned, 0);
+
+        let mut light_client_state = get_example_light_client_state(3);
+        light_client_state.trusting_period = u64::MAX;
+        assert_eq!(is_expired(u64::MAX, 2000, &light_client_state), false);
+    }
+
+    #[test]
+    fn test_consensus_height_queries() {
+        let mut deps = mock_dependencies(&[]);
+        let env = mock_env();
+
+        let mut chain = MockChain::new(3, 4);
+        chain.extend(5);
+
+        // The revision is bumped at block 5
+        let mut light_client_state = get_example_light_client_state(3);
+        light_client_state.verify_epoch_headers = true;
+        light_client_state.verify_non_epoch_headers = true;
+        light_client_state.revision_start_heights = vec![5];
+
+        let mut state = State::new(LightConsensusState::new(), &light_client_state);
+        for number in [0, 3].iter() {
+            state.insert_header(chain.header(*number).unwrap(), env.block.time).unwrap();
+        }
+        let consensus_state =
+            payload::consensus_state(state.snapshot(), chain.header(3).unwrap(), "").unwrap();
+        let me = payload::client_state(&light_client_state, "", new_height(0, 3));
+
+        handle(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("relayer", &[]),
+            HandleMsg::InitializeState {
+                consensus_state: consensus_state.clone(),
+                me: me.clone(),
+            },
+        )
+        .unwrap();
+
+        let mut consensus_states = vec![consensus_state.clone()];
+        for number in [4, 5].iter() {
+            let response = handle(
+                deps.as_mut(),
+                env.clone(),
+                mock_info("relayer", &[]),
+                payload::check_header_and_update_state(
+                    me.clone(),
+                    consensus_state.clone(),
+                    chain.header(*number).unwrap(),
+                )
+                .unwrap(),
+            )
+            .unwrap();
+            let result: CheckHeaderAndUpdateStateResult =
+                cosmwasm_std::from_binary(&response.data.unwrap()).unwrap();
+            consensus_states.push(result.new_consensus_state);
+        }
+
+        let query_json = |deps: Deps, msg: QueryMsg| query(deps, env.clone(), msg).unwrap();
+        let decode = |response: &ConsensusStateResponse| {
+            let bytes = base64::decode(&response.consensus_state).unwrap();
+            ConsensusState::from(&decode_stored_consensus_state(&bytes).unwrap())
+        };
+
+        let response: ListConsensusHeightsResponse = from_slice(&query_json(
+            deps.as_ref(),
+            QueryMsg::ListConsensusHeights {
+                start_after: None,
+                limit: None,
+            },
+        ))
+        .unwrap();
+        let heights = vec![new_height(0, 3), new_height(0, 4), new_height(1, 5)];
+        assert_eq!(response.heights, heights);
+
+        let response: ListConsensusHeightsResponse = from_slice(&query_json(
+            deps.as_ref(),
+            QueryMsg::ListConsensusHeights {
+                start_after: Some(new_height(0, 3)),
+                limit: Some(1),
+            },
+        ))
+        .unwrap();
+        assert_eq!(response.heights, vec![new_height(0, 4)]);
+
+        // Every tracked height serves the consensus state it was updated with
+        for (height, consensus_state) in heights.iter().zip(consensus_states.iter()) {
+            let response: ConsensusStateResponse = from_slice(&query_json(
+                deps.as_ref(),
+                QueryMsg::ConsensusState { height: *height },
+            ))
+            .unwrap();
+            assert_eq!(response.height, *height);
+            assert_eq!(&decode(&response), consensus_state);
+        }
+
+        let response: ConsensusStateResponse =
+            from_slice(&query_json(deps.as_ref(), QueryMsg::LatestConsensusState {})).unwrap();
+        assert_eq!(response.height, new_height(1, 5));
+        assert_eq!(decode(&response), consensus_states[2]);
+
+        let response: AdjacentConsensusStateResponse = from_slice(&query_json(
+            deps.as_ref(),
+            QueryMsg::NextConsensusStateAfter {
+                height: new_height(0, 4),
+            },
+        ))
+        .unwrap();
+        assert_eq!(response.consensus_state.unwrap().height, new_height(1, 5));
+
+        let response: AdjacentConsensusStateResponse = from_slice(&query_json(
+            deps.as_ref(),
+            QueryMsg::NextConsensusStateAfter {
+                height: new_height(1, 5),
+            },
+        ))
+        .unwrap();
+        assert_eq!(response.consensus_state, None);
+
+        let response: AdjacentConsensusStateResponse = from_slice(&query_json(
+            deps.as_ref(),
+            QueryMsg::PrevConsensusStateBefore {
+                height: new_height(0, 4),
+            },
+        ))
+        .unwrap();
+        let prev = response.consensus_state.unwrap();
+        assert_eq!(prev.height, new_height(0, 3));
+        assert_eq!(decode(&prev), consensus_states[0]);
+
+        let response: AdjacentConsensusStateResponse = from_slice(&query_json(
+            deps.as_ref(),
+            QueryMsg::PrevConsensusStateBefore {
+                height: new_height(0, 3),
+            },
+        ))
+        .unwrap();
+        assert_eq!(response.consensus_state, None);
+
+        let response: ConsensusStatesCountResponse =
+            from_slice(&query_json(deps.as_ref(), QueryMsg::ConsensusStatesCount {})).unwrap();
+        assert_eq!(response.count, 3);
+    }
+
+    #[test]
+    fn test_snapshots_follow_consensus_states() {
+        let mut deps = mock_dependencies(&[]);
+        let mut env = mock_env();
+
+        let mut chain = MockChain::new(3, 4);
+        chain.extend(5);
+
+        let mut light_client_state = get_example_light_client_state(3);
+        light_client_state.trusting_period = 10;
+
+        let mut state = State::new(LightConsensusState::new(), &light_client_state);
+        for number in [0, 3].iter() {
+            state.insert_header(chain.header(*number).unwrap(), env.block.time).unwrap();
+        }
+        let trusted_snapshot = state.snapshot().clone();
+        let consensus_state =
+            payload::consensus_state(&trusted_snapshot, chain.header(3).unwrap(), "").unwrap();
+        let me = payload::client_state(&light_client_state, "", new_height(0, 3));
+
+        handle(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("relayer", &[]),
+            HandleMsg::InitializeState {
+                consensus_state: consensus_state.clone(),
+                me: me.clone(),
+            },
+        )
+        .unwrap();
+
+        let update = |deps: DepsMut, env: Env, number: u64| {
+            handle(
+                deps,
+                env,
+                mock_info("relayer", &[]),
+                payload::check_header_and_update_state(
+                    me.clone(),
+                    consensus_state.clone(),
+                    chain.header(number).unwrap(),
+                )
+                .unwrap(),
+            )
+            .unwrap()
+        };
+
+        let response = update(deps.as_mut(), env.clone(), 4);
+        assert_eq!(response.attributes[4], attr("pruned_snapshots", 0));
+
+        let storage = CosmwasmStorage::new(&mut deps.storage, EMPTY_PREFIX);
+        assert_eq!(storage.get_snapshot(3).unwrap(), Some(trusted_snapshot));
+        assert_eq!(storage.get_latest_number().unwrap(), Some(4));
+
+        // Consensus states at 3 and 4 expire, so do their snapshots
+        env.block.time += 11;
+        let response = update(deps.as_mut(), env, 5);
+        assert_eq!(response.attributes[3], attr("pruned_consensus_states", 2));
+        assert_eq!(response.attributes[4], attr("pruned_snapshots", 2));
+
+        let mut state = State::new(LightConsensusState::new(), &light_client_state);
+        for number in [0, 3, 5].iter() {
+            state.insert_header(chain.header(*number).unwrap(), 0).unwrap();
+        }
+        let storage = CosmwasmStorage::new(&mut deps.storage, EMPTY_PREFIX);
+        assert_eq!(storage.get_snapshot(3).unwrap(), None);
+        assert_eq!(storage.get_snapshot(4).unwrap(), None);
+        assert_eq!(storage.get_latest_snapshot().unwrap().as_ref(), Some(state.snapshot()));
+    }
+
+    #[test]
+    fn test_check_epoch_headers_and_update_state() {
+        let mut deps = mock_dependencies(&[]);
+        let env = mock_env();
+
+        let mut client_state = get_example_client_state(0, 0);
+        client_state.data = base64::encode(get_example_light_client_state(3).to_versioned());
+        let consensus_state = get_example_consenus_state(vec![], new_height(0, 0));
+
+        let epoch_headers = vec![
+            get_example_wasm_header(&get_example_header(3, 100), new_height(0, 3)),
+            get_example_wasm_header(&get_example_header(6, 200), new_height(0, 6)),
+        ];
+        let header = get_example_wasm_header(&get_example_header(7, 300), new_height(0, 7));
 
-    let current_height = get_self_height(current_height);
-    if current_height < valid_height {
-        return Err(StdError::generic_err(format!(
-            "cannot verify packet until height: {}, current height: {}",
-            valid_height, current_height
-        )));
+        let response = check_epoch_headers_and_update_state(
+            deps.as_mut(),
+            env.clone(),
+            client_state.clone(),
+            consensus_state.clone(),
+            epoch_headers,
+            Some(header),
+        )
+        .unwrap();
+
+        let result: CheckHeaderAndUpdateStateResult =
+            cosmwasm_std::from_binary(&response.data.unwrap()).unwrap();
+        let light_consensus_state: LightConsensusState =
+            from_base64_versioned(&result.new_consensus_state.data, "light_consensus_state").unwrap();
+        assert_eq!(light_consensus_state.number, 7);
+        assert_eq!(result.new_consensus_state.timestamp, 300);
+
+        // Non-epoch header can't be part of the epoch headers
+        let response = check_epoch_headers_and_update_state(
+            deps.as_mut(),
+            env.clone(),
+            client_state.clone(),
+            consensus_state.clone(),
+            vec![get_example_wasm_header(&get_example_header(4, 100), new_height(0, 4))],
+            None,
+        );
+        assert_eq!(response.is_err(), true);
+
+        // Empty bundle
+        let response = check_epoch_headers_and_update_state(
+            deps.as_mut(),
+            env,
+            client_state,
+            consensus_state,
+            vec![],
+            None,
+        );
+        assert_eq!(response.is_err(), true);
     }
 
-    Ok(())
-}
+    #[test]
+    fn test_check_epoch_headers_rejects_bundle_with_invalid_header() {
+        let mut deps = mock_dependencies(&[]);
+        let env = mock_env();
 
-fn construct_upgrade_merkle_path(
-    upgrade_path: &Vec<String>,
-    client_upgrade_path: ibc::ics24_host::ClientUpgradePath,
-) -> MerklePath {
-    let appended_key = ibc::ics24_host::Path::Upgrade(client_upgrade_path).to_string();
+        let mut chain = MockChain::new(3, 4);
+        chain.extend(10);
 
-    let mut result: Vec<String> = upgrade_path.clone();
-    result.push(appended_key);
+        let mut light_client_state = get_example_light_client_state(3);
+        light_client_state.verify_epoch_headers = true;
+        light_client_state.verify_non_epoch_headers = true;
 
-    MerklePath { key_path: result }
-}
+        // Trusted state at the genesis block
+        let mut state = State::new(LightConsensusState::new(), &light_client_state);
+        state.insert_header(chain.header(0).unwrap(), env.block.time).unwrap();
 
-fn is_expired(
-    current_timestamp: u64,
-    latest_timestamp: u64,
-    light_client_state: &LightClientState,
-) -> bool {
-    current_timestamp > latest_timestamp + light_client_state.trusting_period
-}
+        let mut client_state = get_example_client_state(0, 0);
+        client_state.data = base64::encode(light_client_state.to_versioned());
+        let mut consensus_state = get_example_consenus_state(vec![], new_height(0, 0));
+        consensus_state.data = base64::encode(state.snapshot().to_versioned());
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::contract::types::ibc::MerklePrefix;
-    use cosmwasm_std::testing::{mock_dependencies, mock_env};
-    use ics23::{
-        calculate_existence_root, CommitmentProof, ExistenceProof, HashOp, InnerOp, LeafOp,
-        LengthOp,
-    };
+        let wasm_header = |header: &Header| {
+            get_example_wasm_header(header, new_height(0, header.number.to_u64().unwrap()))
+        };
+        let header = wasm_header(chain.header(10).unwrap());
+
+        // The middle epoch header isn't sealed by the validators
+        let mut invalid_header = chain.header(6).unwrap().clone();
+        invalid_header.gas_used += 1;
+        let epoch_headers = vec![
+            wasm_header(chain.header(3).unwrap()),
+            wasm_header(&invalid_header),
+            wasm_header(chain.header(9).unwrap()),
+        ];
+
+        let err = check_epoch_headers_and_update_state(
+            deps.as_mut(),
+            env.clone(),
+            client_state.clone(),
+            consensus_state.clone(),
+            epoch_headers,
+            Some(header.clone()),
+        )
+        .unwrap_err();
+        assert_eq!(err.code(), 4);
+        assert_eq!(err.to_string().contains("Unable to ingest header num: 6"), true);
+
+        // The whole bundle is rejected, none of the headers is stored
+        assert!(get_consensus_heights(&deps.storage, EMPTY_PREFIX, None, usize::MAX)
+            .unwrap()
+            .is_empty());
+        assert_eq!(get_consensus_states_count(&deps.storage, EMPTY_PREFIX).unwrap(), 0);
+
+        // The valid bundle is applied
+        let epoch_headers = [3, 6, 9]
+            .iter()
+            .map(|number| wasm_header(chain.header(*number).unwrap()))
+            .collect();
+        check_epoch_headers_and_update_state(
+            deps.as_mut(),
+            env,
+            client_state,
+            consensus_state,
+            epoch_headers,
+            Some(header),
+        )
+        .unwrap();
+        assert_eq!(
+            get_consensus_heights(&deps.storage, EMPTY_PREFIX, None, usize::MAX).unwrap(),
+            vec![new_height(0, 10)]
+        );
+    }
 
     #[test]
-    fn test_verify_client_consensus_state() {
+    fn test_verify_upgrade_and_update_state() {
+        let mut deps = mock_dependencies(&[]);
+        let env = mock_env();
+
+        let mut light_client_state = get_example_light_client_state(3);
+        light_client_state.upgrade_path = vec![String::from("upgrade")];
+        light_client_state.commitment_scheme = get_example_commitment_scheme();
+
+        let mut me = get_example_client_state(0, 5);
+        me.data = base64::encode(light_client_state.to_versioned());
+
+        // The chain commits to the upgraded client with custom fields zeroed
+        let mut upgraded_light_client_state = light_client_state.zero_custom_fields();
+        upgraded_light_client_state.epoch_size = 5;
+
+        let mut new_client_state = get_example_client_state(1, 1);
+        new_client_state.data = base64::encode(upgraded_light_client_state.to_versioned());
+        let new_consensus_state = get_example_consenus_state(vec![9_u8; 32], new_height(1, 1));
+
+        let client_key = construct_upgrade_merkle_path(
+            &light_client_state.upgrade_path,
+            ClientUpgradePath::UpgradedClientState(5),
+        )
+        .key_path
+        .pop()
+        .unwrap();
+        let consensus_key = construct_upgrade_merkle_path(
+            &light_client_state.upgrade_path,
+            ClientUpgradePath::UpgradedClientConsensusState(5),
+        )
+        .key_path
+        .pop()
+        .unwrap();
+
+        let client_value = encode_any(
+            WASM_CLIENT_STATE_TYPE_URL,
+            &ProtoClientState::try_from(&new_client_state).unwrap(),
+        )
+        .unwrap();
+        let consensus_value = encode_any(
+            WASM_CONSENSUS_STATE_TYPE_URL,
+            &ProtoConsensusState::try_from(&new_consensus_state).unwrap(),
+        )
+        .unwrap();
+
+        let (client_proof, consensus_proof, subroot) = get_example_sibling_proofs(
+            (client_key.into_bytes(), client_value),
+            (consensus_key.into_bytes(), consensus_value.clone()),
+        );
+        let (client_proof, root) = get_example_store_proof("upgrade", client_proof, subroot.clone());
+        let (consensus_proof, _) = get_example_store_proof("upgrade", consensus_proof, subroot);
+
+        let mut last_height_consensus_state = get_example_consenus_state(root, new_height(0, 5));
+        last_height_consensus_state.timestamp = env.block.time;
+
+        let upgrade = |deps: DepsMut, new_client_state: &ClientState, swap_proofs: bool| {
+            let (client_proof, consensus_proof) = if swap_proofs {
+                (&consensus_proof, &client_proof)
+            } else {
+                (&client_proof, &consensus_proof)
+            };
+
+            verify_upgrade_and_update_state(
+                deps,
+                mock_env(),
+                me.clone(),
+                new_client_state.clone(),
+                new_consensus_state.clone(),
+                base64::encode(to_vec(client_proof).unwrap()),
+                base64::encode(to_vec(consensus_proof).unwrap()),
+                last_height_consensus_state.clone(),
+            )
+        };
+
+        // Proofs must be matched with the right values
+        assert_eq!(upgrade(deps.as_mut(), &new_client_state, true).is_err(), true);
+
+        // Custom fields of the upgraded client must be zeroed
+        let mut custom_client_state = new_client_state.clone();
+        custom_client_state.data = base64::encode(
+            upgraded_light_client_state
+                .with_custom_fields(&light_client_state)
+                .to_versioned(),
+        );
+        assert_eq!(upgrade(deps.as_mut(), &custom_client_state, false).is_err(), true);
+
+        let response = upgrade(deps.as_mut(), &new_client_state, false).unwrap();
+        let result: VerifyUpgradeAndUpdateStateResult =
+            cosmwasm_std::from_binary(&response.data.unwrap()).unwrap();
+
+        assert_eq!(result.new_client_state.latest_height, Some(new_height(1, 1)));
+        let upgraded: LightClientState =
+            from_base64_versioned(&result.new_client_state.data, "light_client_state").unwrap();
+        assert_eq!(upgraded.epoch_size, 5);
+        assert_eq!(upgraded.trusting_period, light_client_state.trusting_period);
+
+        // Upgraded consensus state is persisted along with metadata
+        assert_eq!(
+            get_consensus_state(&deps.storage, EMPTY_PREFIX, &new_height(1, 1)).unwrap(),
+            consensus_value
+        );
+        assert_eq!(
+            get_processed_time(&deps.storage, EMPTY_PREFIX, &new_height(1, 1)).unwrap(),
+            env.block.time
+        );
+    }
+
+    #[test]
+    fn test_substitute_unfreezes_client() {
+        let mut deps = mock_dependencies(&[]);
+        let env = mock_env();
+        let heights = vec![new_height(0, 8), new_height(0, 10)];
+        set_substitute_consensus_states(&mut deps, &heights, env.block.time);
+
+        let mut light_client_state = get_example_light_client_state(3);
+        light_client_state.allow_update_after_misbehavior = true;
+
+        let mut me = get_example_client_state(0, 5);
+        me.data = base64::encode(light_client_state.to_versioned());
+        me.frozen = true;
+        me.frozen_height = Some(new_height(0, 6));
+
+        // Substitute may differ in the custom fields only
+        let mut substitute_light_client_state = light_client_state.clone();
+        substitute_light_client_state.trusting_period = 2_000_000_000;
+        substitute_light_client_state.allow_update_after_misbehavior = false;
+
+        let mut substitute = get_example_client_state(0, 10);
+        substitute.data = base64::encode(substitute_light_client_state.to_versioned());
+
+        let subject_consensus_state = get_example_consenus_state(vec![], new_height(0, 5));
+        let response = check_substitute_client_state(
+            deps.as_mut(),
+            env.clone(),
+            me,
+            substitute,
+            subject_consensus_state,
+            new_height(0, 1),
+        )
+        .unwrap();
+
+        let result: CheckSubstituteAndUpdateStateResult =
+            cosmwasm_std::from_binary(&response.data.unwrap()).unwrap();
+        assert_eq!(result.new_client_state.frozen, false);
+        assert_eq!(result.new_client_state.frozen_height, None);
+        assert_eq!(result.new_client_state.latest_height, Some(new_height(0, 10)));
+
+        let new_light_client_state: LightClientState =
+            from_base64_versioned(&result.new_client_state.data, "light_client_state").unwrap();
+        assert_eq!(new_light_client_state.trusting_period, 2_000_000_000);
+        assert_eq!(new_light_client_state.allow_update_after_misbehavior, true);
+
+        // All consensus states are copied along with metadata
+        assert_eq!(
+            get_consensus_heights(&deps.storage, SUBJECT_PREFIX, None, 10).unwrap(),
+            heights
+        );
+        assert_eq!(get_consensus_states_count(&deps.storage, SUBJECT_PREFIX).unwrap(), 2);
+        for height in heights.iter() {
+            assert_eq!(
+                get_consensus_state(&deps.storage, SUBJECT_PREFIX, height).unwrap(),
+                get_consensus_state(&deps.storage, SUBSTITUTE_PREFIX, height).unwrap()
+            );
+            assert_eq!(
+                get_processed_time(&deps.storage, SUBJECT_PREFIX, height).unwrap(),
+                get_processed_time(&deps.storage, SUBSTITUTE_PREFIX, height).unwrap()
+            );
+            assert_eq!(
+                get_processed_height(&deps.storage, SUBJECT_PREFIX, height).unwrap(),
+                get_processed_height(&deps.storage, SUBSTITUTE_PREFIX, height).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_substitute_rejections() {
         let mut deps = mock_dependencies(&[]);
         let env = mock_env();
+        set_substitute_consensus_states(&mut deps, &[new_height(0, 10)], env.block.time);
+
+        let light_client_state = get_example_light_client_state(3);
+        let client_state = |light_client_state: &LightClientState, height: Height| {
+            let mut client_state =
+                get_example_client_state(height.revision_number, height.revision_height);
+            client_state.data = base64::encode(light_client_state.to_versioned());
+            client_state
+        };
+        let substitute = client_state(&light_client_state, new_height(0, 10));
+
+        let mut live_consensus_state = get_example_consenus_state(vec![], new_height(0, 5));
+        live_consensus_state.timestamp = env.block.time;
+        let mut expired_consensus_state = live_consensus_state.clone();
+        expired_consensus_state.timestamp = 0;
+
+        let substitute_client = |deps: DepsMut,
+                                 me: ClientState,
+                                 substitute: &ClientState,
+                                 consensus_state: &ConsensusState,
+                                 initial_height: Height| {
+            check_substitute_client_state(
+                deps,
+                mock_env(),
+                me,
+                substitute.clone(),
+                consensus_state.clone(),
+                initial_height,
+            )
+        };
+
+        // Frozen client, but update after misbehaviour is not allowed
+        let mut frozen = client_state(&light_client_state, new_height(0, 5));
+        frozen.frozen = true;
+        frozen.frozen_height = Some(new_height(0, 6));
+        assert_eq!(
+            substitute_client(
+                deps.as_mut(),
+                frozen,
+                &substitute,
+                &live_consensus_state,
+                new_height(0, 1),
+            )
+            .is_err(),
+            true
+        );
+
+        // Expired client, but update after expiry is not allowed
+        let me = client_state(&light_client_state, new_height(0, 5));
+        assert_eq!(
+            substitute_client(
+                deps.as_mut(),
+                me.clone(),
+                &substitute,
+                &expired_consensus_state,
+                new_height(0, 1),
+            )
+            .is_err(),
+            true
+        );
 
-        let client_state = get_example_client_state(0, 5);
+        // Chain parameters differ
+        let mut other_light_client_state = light_client_state.clone();
+        other_light_client_state.epoch_size = 5;
+        let other = client_state(&other_light_client_state, new_height(0, 10));
+        assert_eq!(
+            substitute_client(
+                deps.as_mut(),
+                me.clone(),
+                &other,
+                &live_consensus_state,
+                new_height(0, 1),
+            )
+            .is_err(),
+            true
+        );
+
+        // Revision number of the initial height differs
+        assert_eq!(
+            substitute_client(
+                deps.as_mut(),
+                me.clone(),
+                &substitute,
+                &live_consensus_state,
+                new_height(1, 1),
+            )
+            .is_err(),
+            true
+        );
+
+        // Active client (neither frozen nor expired)
+        let err = substitute_client(
+            deps.as_mut(),
+            me.clone(),
+            &substitute,
+            &live_consensus_state,
+            new_height(0, 1),
+        )
+        .unwrap_err();
+        assert_eq!(err.code(), 3);
+        assert_eq!(err.to_string().contains("cannot substitute an active client"), true);
+
+        // Zero frozen height doesn't freeze the client
+        let mut unfrozen = me.clone();
+        unfrozen.frozen_height = Some(new_height(0, 0));
+        let err = substitute_client(
+            deps.as_mut(),
+            unfrozen,
+            &substitute,
+            &live_consensus_state,
+            new_height(0, 1),
+        )
+        .unwrap_err();
+        assert_eq!(err.code(), 3);
+
+        // Frozen at a height (without the flag), but update after misbehaviour is not allowed
+        let mut frozen = me.clone();
+        frozen.frozen_height = Some(new_height(0, 6));
+        let err = substitute_client(
+            deps.as_mut(),
+            frozen,
+            &substitute,
+            &live_consensus_state,
+            new_height(0, 1),
+        )
+        .unwrap_err();
+        assert_eq!(err.code(), 6);
+
+        // Expired client, update after expiry is allowed
+        let mut expiry_light_client_state = light_client_state.clone();
+        expiry_light_client_state.allow_update_after_expiry = true;
+        let me = client_state(&expiry_light_client_state, new_height(0, 5));
+        assert_eq!(
+            substitute_client(
+                deps.as_mut(),
+                me,
+                &substitute,
+                &expired_consensus_state,
+                new_height(0, 1),
+            )
+            .is_err(),
+            false
+        );
+    }
+
+    #[test]
+    fn test_self_height_revision() {
+        assert_eq!(get_self_height("cosmoshub-4", 10), new_height(4, 10));
+        assert_eq!(get_self_height("gaia-testnet-12", 10), new_height(12, 10));
+        assert_eq!(get_self_height("cosmoshub", 10), new_height(0, 10));
+        assert_eq!(get_self_height("cosmoshub-04", 10), new_height(0, 10));
+        assert_eq!(get_self_height("cosmoshub--4", 10), new_height(0, 10));
+        assert_eq!(get_self_height("-4", 10), new_height(0, 10));
+        assert_eq!(get_self_height("cosmoshub-", 10), new_height(0, 10));
+    }
+
+    #[test]
+    fn test_celo_height_mapping() {
+        let mut light_client_state = get_example_light_client_state(3);
+        assert_eq!(light_client_state.celo_height(100), new_height(0, 100));
+
+        // ie. L2 migration at block 100
+        light_client_state.revision_start_heights = vec![100];
+        assert_eq!(light_client_state.celo_height(99), new_height(0, 99));
+        assert_eq!(light_client_state.celo_height(100), new_height(1, 100));
+        assert_eq!(light_client_state.celo_height(101), new_height(1, 101));
+    }
+
+    #[test]
+    fn test_delay_period_across_revisions() {
+        let mut deps = mock_dependencies(&[]);
+        let mut env = mock_env();
+        env.block.chain_id = String::from("cosmoshub-3");
+        env.block.height = 1000;
 
         let height = new_height(0, 5);
-        let consensus_height = new_height(0, 5);
+        set_consensus_meta(&env, &mut deps.storage, EMPTY_PREFIX, &height).unwrap();
+        assert_eq!(
+            get_processed_height(&deps.storage, EMPTY_PREFIX, &height).unwrap(),
+            new_height(3, 1000)
+        );
 
-        let commitment_prefix = MerklePrefix {
-            key_prefix: base64::encode("prefix"),
+        // Block delay hasn't passed on the same revision
+        assert_eq!(
+            verify_delay_period_passed(
+                deps.as_ref(),
+                height,
+                "cosmoshub-3",
+                1005,
+                env.block.time,
+                0,
+                10
+            )
+            .is_err(),
+            true
+        );
+
+        // Host chain upgrade restarts the block numbers
+        assert_eq!(
+            verify_delay_period_passed(
+                deps.as_ref(),
+                height,
+                "cosmoshub-4",
+                1,
+                env.block.time,
+                0,
+                10
+            )
+            .is_err(),
+            false
+        );
+    }
+
+    #[test]
+    fn test_verify_non_membership() {
+        let spec = get_example_proof_spec();
+        let specs = vec![spec.clone(), spec];
+        let keys = MerklePath {
+            key_path: vec![String::from("prefix"), String::from("b")],
         };
-        let counterparty_client_identifier = String::from("07-tendermint-0");
 
-        // The counterparty_consensus_state + commitment_proof comes from the other side of the
-        // bridge, while root "is local" (comes from proving consensus state).
-        //
-        // In the unittest we update provingConsensusState with "remote root", so that validation
-        // always succeeds (as long as verify_membership works properly)
-        let counterparty_consensus_state = CosmosConsensusState {
-            root: MerkleRoot {
-                hash: String::from("base64_encoded_hash"),
+        // Absence of "b" is proven by the left-most right neighbour ("c") in the substore
+        let right = ExistenceProof {
+            key: b"c".to_vec(),
+            value: b"value".to_vec(),
+            leaf: Some(get_example_leaf_op()),
+            path: vec![InnerOp {
+                hash: HashOp::Sha256.into(),
+                prefix: vec![1_u8],
+                suffix: vec![0_u8; 32],
+            }],
+        };
+        let subroot = calculate_existence_root(&right).unwrap();
+        let non_exist_proof = CommitmentProof {
+            proof: Some(ics23::commitment_proof::Proof::Nonexist(NonExistenceProof {
+                key: b"b".to_vec(),
+                left: None,
+                right: Some(right),
+            })),
+        };
+
+        // Substore root is proven to be stored under "prefix" key in the final root
+        let (store_proof, root) = get_example_proof(b"prefix".to_vec(), subroot);
+        let proof = MerkleProof {
+            proofs: vec![non_exist_proof, store_proof.proofs[0].clone()],
+        };
+
+        assert_eq!(
+            verify_non_membership(&proof, &specs, &root, &keys).unwrap(),
+            true
+        );
+
+        // Subroot chained to a different root
+        assert_eq!(
+            verify_non_membership(&proof, &specs, &vec![0_u8; 32], &keys).is_err(),
+            true
+        );
+
+        // Number of proofs has to match the number of specs
+        let single_proof = MerkleProof {
+            proofs: vec![proof.proofs[0].clone()],
+        };
+        assert_eq!(
+            verify_non_membership(&single_proof, &specs, &root, &keys).is_err(),
+            true
+        );
+    }
+
+    #[test]
+    fn test_verify_membership_proof_lengths() {
+        let spec = get_example_proof_spec();
+        let specs = vec![spec.clone(), spec];
+        let keys = MerklePath {
+            key_path: vec![String::from("prefix"), String::from("a")],
+        };
+
+        let (proof, subroot) = get_example_proof(b"a".to_vec(), b"value".to_vec());
+        let (proof, root) = get_example_store_proof("prefix", proof, subroot);
+        assert_eq!(
+            verify_membership(&proof, &specs, &root, &keys, b"value".to_vec(), 0).unwrap(),
+            true
+        );
+
+        let verify = |proof: &MerkleProof, specs: &[ProofSpec], keys: &MerklePath| {
+            verify_membership(proof, specs, &root, keys, b"value".to_vec(), 0)
+                .unwrap_err()
+                .to_string()
+        };
+
+        // Empty proof
+        let empty = MerkleProof { proofs: vec![] };
+        assert_eq!(verify(&empty, &[], &keys).contains("proof cannot be empty"), true);
+
+        // Number of proofs has to match the number of specs
+        let single_proof = MerkleProof {
+            proofs: vec![proof.proofs[0].clone()],
+        };
+        assert_eq!(
+            verify(&single_proof, &specs, &keys).contains("length of specs: 2 not equal"),
+            true
+        );
+
+        // Number of keys has to match the number of specs
+        let single_key = MerklePath {
+            key_path: vec![String::from("a")],
+        };
+        assert_eq!(
+            verify(&proof, &specs, &single_key).contains("path length 1 not same as proof 2"),
+            true
+        );
+    }
+
+    #[test]
+    fn test_committed_value() {
+        let channel = Channel {
+            state: 3,
+            ordering: 2,
+            counterparty: ChannelCounterparty {
+                port_id: String::from("transfer"),
+                channel_id: String::from("channel-0"),
             },
+            connection_hops: vec![String::from("connection-0")],
+            version: String::from("ics20-1"),
         };
 
-        let (commitment_proof, root) = get_example_proof(
-            b"clients/07-tendermint-0/consensusStates/0-5".to_vec(), // key (based on consensus_height)
-            to_vec(&counterparty_consensus_state).unwrap(),          // value
+        let light_client_state = get_example_light_client_state(3);
+        let value = committed_value::<_, ProtoChannel>(&light_client_state, &channel).unwrap();
+        assert_eq!(value, to_vec(&channel).unwrap());
+
+        // The EVM IBC handler commits to the protobuf encoding
+        let mut light_client_state = get_example_light_client_state(3);
+        light_client_state.commitment_scheme = CommitmentScheme::EvmMpt {
+            ibc_handler_address: [1; 20],
+            commitments_slot: 0,
+            key_prefix: base64::encode("ibc"),
+        };
+        let value = committed_value::<_, ProtoChannel>(&light_client_state, &channel).unwrap();
+        assert_eq!(
+            hex::encode(value),
+            "080310021a150a087472616e7366657212096368616e6e656c2d30220c636f6e6e656374696f6e2d\
+             302a0769637332302d31"
         );
+    }
 
-        let proving_consensus_state = get_example_consenus_state(root, height);
+    #[test]
+    fn test_commitment_prefix_rules() {
+        let prefix = MerklePrefix {
+            key_prefix: base64::encode("ibc"),
+        };
+        let path = vec![String::from("connections/connection-0")];
+        let mut light_client_state = get_example_light_client_state(3);
+
+        light_client_state.prefix_rule = PrefixRule::Counterparty;
+        assert_eq!(
+            apply_commitment_prefix(&light_client_state, &prefix, path.clone())
+                .unwrap()
+                .key_path,
+            vec![String::from("ibc"), path[0].clone()]
+        );
 
-        let response = verify_client_consensus_state(
-            deps.as_mut(),
-            env,
-            client_state,
-            height,
-            consensus_height,
-            commitment_prefix,
-            counterparty_client_identifier,
-            base64::encode(to_vec(&commitment_proof).unwrap()),
-            counterparty_consensus_state,
-            proving_consensus_state,
+        light_client_state.prefix_rule = PrefixRule::Fixed {
+            key_prefix: base64::encode("store"),
+        };
+        assert_eq!(
+            apply_commitment_prefix(&light_client_state, &prefix, path.clone()).is_err(),
+            true
         );
 
-        assert_eq!(response.is_err(), false);
+        light_client_state.prefix_rule = PrefixRule::NoPrefix;
+        assert_eq!(
+            apply_commitment_prefix(&light_client_state, &prefix, path.clone())
+                .unwrap()
+                .key_path,
+            path
+        );
+    }
+
+    #[test]
+    fn test_configurable_proof_specs() {
+        let mut custom = Vec::new();
+        get_example_proof_spec().encode(&mut custom).unwrap();
+
+        let mut light_client_state = get_example_light_client_state(3);
+        light_client_state.commitment_scheme = CommitmentScheme::Ics23 {
+            proof_specs: vec![
+                crate::contract::types::state::ProofSpec::Custom { spec: custom },
+                crate::contract::types::state::ProofSpec::Tendermint,
+            ],
+        };
+        light_client_state.prefix_rule = PrefixRule::Fixed {
+            key_prefix: base64::encode("ibc"),
+        };
+
+        // Configuration survives the client state encoding
+        let decoded = LightClientState::from_versioned(&light_client_state.to_versioned()).unwrap();
+        assert_eq!(decoded, light_client_state);
+
+        let proof_specs = match decoded.commitment_scheme {
+            CommitmentScheme::Ics23 { proof_specs } => proof_specs,
+            _ => panic!("unexpected commitment scheme"),
+        };
+        assert_eq!(
+            to_ics23_specs(&proof_specs).unwrap(),
+            vec![get_example_proof_spec(), ics23::tendermint_spec()]
+        );
+        assert_eq!(to_ics23_specs(&[]).is_err(), true);
     }
 
     fn get_example_client_state(revision_number: u64, revision_height: u64) -> ClientState {
@@ -1385,6 +3626,55 @@ mod tests {
         }
     }
 
+    // Stores the consensus states (with metadata) in the substitute client store
+    fn set_substitute_consensus_states(
+        deps: &mut OwnedDeps<MockStorage, MockApi, MockQuerier>,
+        heights: &[Height],
+        timestamp: u64,
+    ) {
+        for height in heights.iter() {
+            let mut light_consensus_state = LightConsensusState::new();
+            light_consensus_state.number = height.revision_height;
+            light_consensus_state.timestamp = timestamp;
+
+            let consensus_state = ProtoConsensusState {
+                code_id: vec![],
+                data: light_consensus_state.to_versioned(),
+                timestamp,
+                root: Some(ProtoMerkleRoot {
+                    hash: vec![1_u8; 32],
+                }),
+            };
+            let bytes = encode_any(WASM_CONSENSUS_STATE_TYPE_URL, &consensus_state).unwrap();
+
+            set_consensus_state(&mut deps.storage, SUBSTITUTE_PREFIX, height, &bytes).unwrap();
+            set_consensus_meta(&mock_env(), &mut deps.storage, SUBSTITUTE_PREFIX, height).unwrap();
+        }
+    }
+
+    // Returns protobuf Any encoded Tendermint consensus state (as stored by ibc-go)
+    fn get_example_tendermint_consensus_state() -> Vec<u8> {
+        let consensus_state = TendermintConsensusState {
+            timestamp: Some(Timestamp {
+                seconds: 1_600_000_000,
+                nanos: 0,
+            }),
+            root: Some(ProtoMerkleRoot {
+                hash: vec![1_u8; 32],
+            }),
+            next_validators_hash: vec![2_u8; 32],
+        };
+
+        encode_any(TENDERMINT_CONSENSUS_STATE_TYPE_URL, &consensus_state).unwrap()
+    }
+
+    fn get_example_misbehaviour_client_state() -> ClientState {
+        let mut client_state = get_example_client_state(0, 5);
+        client_state.data = base64::encode(get_example_light_client_state(3).to_versioned());
+
+        client_state
+    }
+
     fn get_example_consenus_state(root: Vec<u8>, height: Height) -> ConsensusState {
         // In real life scenario this consensus state would be fetched
         // at the given Height. This makes the CS dependant on the arg.
@@ -1392,7 +3682,7 @@ mod tests {
         light_cs.number = height.revision_height + height.revision_number;
 
         ConsensusState {
-            data: base64::encode(light_cs.to_rlp()),
+            data: base64::encode(light_cs.to_versioned()),
             code_id: String::from(""),
             timestamp: 123,
             root: MerkleRoot {
@@ -1401,15 +3691,110 @@ mod tests {
         }
     }
 
-    fn get_example_proof(key: Vec<u8>, value: Vec<u8>) -> (MerkleProof, Vec<u8>) {
-        let leaf = LeafOp {
+    fn get_example_leaf_op() -> LeafOp {
+        LeafOp {
             hash: HashOp::Sha256.into(),
             prehash_key: 0,
             prehash_value: HashOp::Sha256.into(),
             length: LengthOp::VarProto.into(),
             prefix: vec![0_u8],
+        }
+    }
+
+    fn get_example_proof_spec() -> ProofSpec {
+        ProofSpec {
+            leaf_spec: Some(get_example_leaf_op()),
+            inner_spec: Some(InnerSpec {
+                child_order: vec![0, 1],
+                child_size: 32,
+                min_prefix_length: 1,
+                max_prefix_length: 1,
+                empty_child: vec![],
+                hash: HashOp::Sha256.into(),
+            }),
+            max_depth: 0,
+            min_depth: 0,
+        }
+    }
+
+    // Returns proofs of two sibling leaves (see `get_example_proof_spec`) and the common root
+    fn get_example_sibling_proofs(
+        left: (Vec<u8>, Vec<u8>),
+        right: (Vec<u8>, Vec<u8>),
+    ) -> (MerkleProof, MerkleProof, Vec<u8>) {
+        let leaf_hash = |(key, value): &(Vec<u8>, Vec<u8>)| {
+            calculate_existence_root(&ExistenceProof {
+                key: key.clone(),
+                value: value.clone(),
+                leaf: Some(get_example_leaf_op()),
+                path: vec![],
+            })
+            .unwrap()
         };
 
+        let left_proof = ExistenceProof {
+            key: left.0.clone(),
+            value: left.1.clone(),
+            leaf: Some(get_example_leaf_op()),
+            path: vec![InnerOp {
+                hash: HashOp::Sha256.into(),
+                prefix: vec![1_u8],
+                suffix: leaf_hash(&right),
+            }],
+        };
+        let right_proof = ExistenceProof {
+            key: right.0.clone(),
+            value: right.1.clone(),
+            leaf: Some(get_example_leaf_op()),
+            path: vec![InnerOp {
+                hash: HashOp::Sha256.into(),
+                prefix: [vec![1_u8], leaf_hash(&left)].concat(),
+                suffix: vec![],
+            }],
+        };
+        let root = calculate_existence_root(&left_proof).unwrap();
+
+        let to_merkle_proof = |proof: ExistenceProof| MerkleProof {
+            proofs: vec![CommitmentProof {
+                proof: Some(ics23::commitment_proof::Proof::Exist(proof)),
+            }],
+        };
+
+        (to_merkle_proof(left_proof), to_merkle_proof(right_proof), root)
+    }
+
+    // Returns the substore proof chained to the final root, under the store key
+    fn get_example_store_proof(
+        store_key: &str,
+        proof: MerkleProof,
+        subroot: Vec<u8>,
+    ) -> (MerkleProof, Vec<u8>) {
+        let (store_proof, root) = get_example_proof(store_key.as_bytes().to_vec(), subroot);
+
+        (
+            MerkleProof {
+                proofs: vec![proof.proofs[0].clone(), store_proof.proofs[0].clone()],
+            },
+            root,
+        )
+    }
+
+    // Returns the commitment scheme of the substore proofs (see `get_example_store_proof`)
+    fn get_example_commitment_scheme() -> CommitmentScheme {
+        let mut spec = Vec::new();
+        get_example_proof_spec().encode(&mut spec).unwrap();
+
+        CommitmentScheme::Ics23 {
+            proof_specs: vec![
+                crate::contract::types::state::ProofSpec::Custom { spec: spec.clone() },
+                crate::contract::types::state::ProofSpec::Custom { spec },
+            ],
+        }
+    }
+
+    fn get_example_proof(key: Vec<u8>, value: Vec<u8>) -> (MerkleProof, Vec<u8>) {
+        let leaf = get_example_leaf_op();
+
         let valid_inner = InnerOp {
             hash: HashOp::Sha256.into(),
             prefix: hex::decode("deadbeef00cafe00").unwrap(),
@@ -1436,6 +3821,60 @@ mod tests {
         )
     }
 
+    fn get_example_light_client_state(epoch_size: u64) -> LightClientState {
+        LightClientState {
+            epoch_size,
+            allowed_clock_skew: 5,
+            trusting_period: 1_000_000_000,
+            upgrade_path: vec![],
+
+            verify_epoch_headers: false,
+            verify_non_epoch_headers: false,
+            verify_header_timestamp: false,
+
+            allow_update_after_misbehavior: false,
+            allow_update_after_expiry: false,
+
+            commitment_scheme: CommitmentScheme::cosmos_sdk(),
+            prefix_rule: PrefixRule::Counterparty,
+            revision_start_heights: vec![],
+        }
+    }
+
+    fn get_example_header(number: u64, time: u64) -> Header {
+        let extra = IstanbulExtra {
+            added_validators: vec![],
+            added_validators_public_keys: vec![],
+            removed_validators: Integer::from(0),
+            seal: vec![],
+            aggregated_seal: IstanbulAggregatedSeal::new(),
+            parent_aggregated_seal: IstanbulAggregatedSeal::new(),
+        };
+
+        let mut header = Header::new();
+        header.number = number.into();
+        header.time = time;
+        header.extra = extra.to_rlp(&IstanbulExtraVanity::default());
+
+        header
+    }
+
+    fn get_example_wasm_header(header: &Header, height: Height) -> WasmHeader {
+        WasmHeader {
+            data: base64::encode(header.to_rlp()),
+            height,
+        }
+    }
+
+    fn get_example_misbehaviour(header_1: WasmHeader, header_2: WasmHeader) -> Misbehaviour {
+        Misbehaviour {
+            code_id: String::from(""),
+            client_id: String::from("10-wasm-0"),
+            header_1,
+            header_2,
+        }
+    }
+
     fn new_height(revision_number: u64, revision_height: u64) -> Height {
         Height {
             revision_number,
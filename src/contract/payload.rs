@@ -0,0 +1,231 @@
+// This module builds the contract messages on the relayer side, so that the verified snapshots
+// and headers are encoded exactly the way the contract decodes them: the client and consensus
+// states are base64 encoded versioned RLP, the headers are base64 encoded RLP.
+use crate::contract::serialization::from_base64_versioned;
+use crate::contract::types::ibc::{Height, MerkleRoot};
+use crate::contract::types::msg::HandleMsg;
+use crate::contract::types::state::{
+    CommitmentScheme, LightClientState, LightConsensusState, PrefixRule, Versioned,
+};
+use crate::contract::types::wasm::{ClientState, ConsensusState, WasmHeader};
+use crate::traits::ToRlp;
+use crate::types::header::Header;
+use crate::types::state::Snapshot;
+
+use cosmwasm_std::{to_vec, StdError};
+use num::cast::ToPrimitive;
+
+/// Returns the light client state the clients are created with. The Celo networks (mainnet,
+/// alfajores and baklava) share the epoch size and the revision scheme, they differ by the
+/// trusted snapshot only, so there is no per-network state. All the headers are verified, the
+/// trusting period (2 days) stays within the 3 days unlocking period of the locked CELO.
+pub fn default_light_client_state() -> LightClientState {
+    LightClientState {
+        epoch_size: 17280,
+        allowed_clock_skew: 5,
+        trusting_period: 2 * 24 * 60 * 60,
+        upgrade_path: vec![String::from("upgrade"), String::from("upgradedIBCState")],
+
+        verify_epoch_headers: true,
+        verify_non_epoch_headers: true,
+        verify_header_timestamp: true,
+
+        allow_update_after_misbehavior: false,
+        allow_update_after_expiry: false,
+
+        commitment_scheme: CommitmentScheme::cosmos_sdk(),
+        prefix_rule: PrefixRule::Counterparty,
+        revision_start_heights: vec![],
+    }
+}
+
+/// Returns the client state wrapping the light client state, at the given latest height
+pub fn client_state(
+    light_client_state: &LightClientState,
+    code_id: &str,
+    latest_height: Height,
+) -> ClientState {
+    ClientState {
+        data: base64::encode(light_client_state.to_versioned()),
+        code_id: code_id.to_string(),
+        frozen: false,
+        frozen_height: None,
+        latest_height: Some(latest_height),
+    }
+}
+
+/// Returns the consensus state of the snapshot. The header the snapshot was taken at provides
+/// the state root (the snapshot doesn't carry one), so it has to match the snapshot hash.
+pub fn consensus_state(
+    snapshot: &LightConsensusState,
+    header: &Header,
+    code_id: &str,
+) -> Result<ConsensusState, StdError> {
+    let hash = header
+        .hash()
+        .map_err(|e| StdError::generic_err(format!("Unable to hash header: {}", e)))?;
+    if hash != snapshot.hash {
+        return Err(StdError::generic_err(format!(
+            "Header 0x{} doesn't match snapshot 0x{}",
+            hex::encode(hash),
+            hex::encode(snapshot.hash)
+        )));
+    }
+
+    Ok(ConsensusState {
+        code_id: code_id.to_string(),
+        data: base64::encode(snapshot.to_versioned()),
+        timestamp: snapshot.timestamp,
+        root: MerkleRoot {
+            hash: base64::encode(header.root),
+        },
+    })
+}
+
+/// Returns the header, along with its height under the revision scheme of the light client
+pub fn wasm_header(header: &Header, light_client_state: &LightClientState) -> WasmHeader {
+    WasmHeader {
+        data: base64::encode(header.to_rlp()),
+        height: light_client_state.celo_height(header.number.to_u64().unwrap_or_default()),
+    }
+}
+
+/// Returns the `InitializeState` message, creating the client at the verified snapshot. The
+/// header is the one the snapshot was taken at (see `consensus_state`).
+pub fn initialize_state(
+    light_client_state: &LightClientState,
+    code_id: &str,
+    snapshot: &Snapshot,
+    header: &Header,
+) -> Result<HandleMsg, StdError> {
+    Ok(HandleMsg::InitializeState {
+        consensus_state: consensus_state(snapshot, header, code_id)?,
+        me: client_state(
+            light_client_state,
+            code_id,
+            light_client_state.celo_height(snapshot.number),
+        ),
+    })
+}
+
+/// Returns the `CheckHeaderAndUpdateState` message, updating the client (`me`) at the trusted
+/// consensus state with the new header
+pub fn check_header_and_update_state(
+    me: ClientState,
+    consensus_state: ConsensusState,
+    header: &Header,
+) -> Result<HandleMsg, StdError> {
+    let light_client_state: LightClientState =
+        from_base64_versioned(&me.data, "msg.light_client_state")?;
+
+    Ok(HandleMsg::CheckHeaderAndUpdateState {
+        header: wasm_header(header, &light_client_state),
+        consensus_state,
+        me,
+    })
+}
+
+/// Returns the JSON payload of the message, as submitted to the contract
+pub fn to_json(msg: &HandleMsg) -> Result<String, StdError> {
+    String::from_utf8(to_vec(msg)?).map_err(|e| StdError::generic_err(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contract::serialization::from_base64_rlp;
+    use crate::types::header::Hash;
+    use crate::types::istanbul::IstanbulAggregatedSeal;
+    use crate::types::state::Validator;
+
+    fn header(number: u64) -> Header {
+        let mut header = Header::new();
+        header.number = number.into();
+        header.time = 1_600_000_000 + number;
+        header.root = Hash::from([number as u8; 32]);
+
+        header
+    }
+
+    fn snapshot(header: &Header) -> Snapshot {
+        Snapshot {
+            number: header.number.to_u64().unwrap(),
+            timestamp: header.time,
+            validators: vec![Validator {
+                address: Default::default(),
+                public_key: [1; 96],
+            }],
+            hash: header.hash().unwrap(),
+            aggregated_seal: IstanbulAggregatedSeal::new(),
+        }
+    }
+
+    #[test]
+    fn test_initialize_state() {
+        let light_client_state = default_light_client_state();
+        let header = header(17280);
+        let snapshot = snapshot(&header);
+
+        let msg = initialize_state(&light_client_state, "code", &snapshot, &header).unwrap();
+        match msg {
+            HandleMsg::InitializeState {
+                consensus_state,
+                me,
+            } => {
+                let decoded: LightConsensusState =
+                    from_base64_versioned(&consensus_state.data, "consensus_state").unwrap();
+                assert_eq!(decoded, snapshot);
+                assert_eq!(consensus_state.timestamp, snapshot.timestamp);
+                assert_eq!(consensus_state.root.hash, base64::encode(header.root));
+
+                let decoded: LightClientState = from_base64_versioned(&me.data, "me").unwrap();
+                assert_eq!(decoded, light_client_state);
+                assert_eq!(
+                    me.latest_height,
+                    Some(light_client_state.celo_height(17280))
+                );
+                assert_eq!(me.code_id, "code");
+            }
+            _ => panic!("unexpected message"),
+        }
+
+        // the json payload is tagged with the lowercase message name
+        let json = to_json(&initialize_state(&light_client_state, "", &snapshot, &header).unwrap())
+            .unwrap();
+        assert!(json.starts_with("{\"initializestate\":{"));
+
+        // the header has to match the snapshot
+        assert!(initialize_state(&light_client_state, "", &snapshot, &self::header(1)).is_err());
+    }
+
+    #[test]
+    fn test_check_header_and_update_state() {
+        let mut light_client_state = default_light_client_state();
+        light_client_state.revision_start_heights = vec![20];
+        let (trusted, header) = (header(10), header(25));
+
+        let me = client_state(&light_client_state, "", light_client_state.celo_height(10));
+        let consensus_state = consensus_state(&snapshot(&trusted), &trusted, "").unwrap();
+
+        match check_header_and_update_state(me.clone(), consensus_state.clone(), &header).unwrap() {
+            HandleMsg::CheckHeaderAndUpdateState {
+                header: wasm_header,
+                consensus_state: msg_consensus_state,
+                me: msg_me,
+            } => {
+                let decoded: Header = from_base64_rlp(&wasm_header.data, "header").unwrap();
+                assert_eq!(decoded, header);
+                assert_eq!(
+                    wasm_header.height,
+                    Height {
+                        revision_number: 1,
+                        revision_height: 25
+                    }
+                );
+                assert_eq!(msg_consensus_state, consensus_state);
+                assert_eq!(msg_me, me);
+            }
+            _ => panic!("unexpected message"),
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use crate::contract::types::state::Versioned;
 use crate::traits::FromRlp;
 use cosmwasm_std::{from_slice, StdError, StdResult};
 use serde::de::DeserializeOwned;
@@ -33,6 +34,21 @@ where
     })?)
 }
 
+pub fn from_base64_versioned<T, S>(base64_data: &String, target_type: S) -> Result<T, StdError>
+where
+    T: Versioned,
+    S: Into<String> + Clone,
+{
+    let bytes = from_base64(&base64_data, target_type.clone())?;
+
+    Ok(T::from_versioned(bytes.as_slice()).map_err(|e| {
+        StdError::parse_err(
+            target_type,
+            format!("Unable to decode versioned state from base64 data. Error: {}", e),
+        )
+    })?)
+}
+
 pub fn from_base64_json_slice<T, S>(base64_data: &String, target_type: S) -> Result<T, StdError>
 where
     T: DeserializeOwned,
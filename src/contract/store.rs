@@ -1,12 +1,20 @@
 use crate::contract::serialization::must_deserialize;
 use crate::contract::types::ibc::Height;
+use crate::contract::types::state::LEGACY_VERSION;
+use crate::contract::util::u64_to_big_endian;
 
-use cosmwasm_std::{to_vec, Env, StdError, StdResult, Storage};
+use cosmwasm_std::{from_slice, to_vec, Env, Order, StdError, StdResult, Storage};
 
 pub const SUBJECT_PREFIX: &'static str = "subject/";
 pub const SUBSTITUTE_PREFIX: &'static str = "substitute/";
 pub const EMPTY_PREFIX: &'static str = "";
 
+// STATE_VERSION_KEY is the key under which the encoding version of the stored states is kept
+const STATE_VERSION_KEY: &[u8] = b"stateVersion";
+
+// PRUNE_LIMIT is the maximum number of expired consensus states removed in a single update
+pub const PRUNE_LIMIT: usize = 10;
+
 // processed_height_key returns the key under which the processed processed height will be stored in the client store
 pub fn processed_height_key(prefix: &'static str, height: &Height) -> Vec<u8> {
     // consensusStates/ path is defined in ICS 24
@@ -40,6 +48,30 @@ pub fn consensus_state_key(prefix: &'static str, height: &Height) -> Vec<u8> {
     .to_owned()
 }
 
+// iteration_key_prefix returns the common prefix of all iteration keys in the client store
+fn iteration_key_prefix(prefix: &'static str) -> Vec<u8> {
+    format!("{}iterateConsensusStates/", prefix)
+        .as_bytes()
+        .to_owned()
+}
+
+// iteration_key returns the key under which the consensus height is stored for ordered iteration.
+// Height is big endian encoded, so that lexicographic order of the keys matches the height order
+pub fn iteration_key(prefix: &'static str, height: &Height) -> Vec<u8> {
+    let mut key = iteration_key_prefix(prefix);
+    key.extend(u64_to_big_endian(height.revision_number));
+    key.extend(u64_to_big_endian(height.revision_height));
+
+    key
+}
+
+// consensus_states_count_key returns the key under which the number of stored consensus states is kept
+pub fn consensus_states_count_key(prefix: &'static str) -> Vec<u8> {
+    format!("{}consensusStatesCount", prefix)
+        .as_bytes()
+        .to_owned()
+}
+
 // set_processed_height stores the height at which a header was processed and the corresponding consensus state was created.
 // This is useful when validating whether a packet has reached the specified block delay period in the light client's
 // verification functions
@@ -94,6 +126,41 @@ pub fn get_processed_time(
     must_deserialize(&storage.get(&key))
 }
 
+// set_iteration_key stores the consensus height under the iteration key and keeps track of
+// the number of stored consensus states
+fn set_iteration_key(
+    storage: &mut dyn Storage,
+    prefix: &'static str,
+    height: &Height,
+) -> StdResult<()> {
+    let key = iteration_key(prefix, height);
+    if storage.get(&key).is_none() {
+        let count = get_consensus_states_count(storage, prefix)?;
+        set_consensus_states_count(storage, prefix, count + 1)?;
+    }
+    storage.set(&key, &to_vec(height)?);
+
+    Ok(())
+}
+
+// get_consensus_states_count returns the number of consensus states held by the client store
+pub fn get_consensus_states_count(storage: &dyn Storage, prefix: &'static str) -> StdResult<u64> {
+    match storage.get(&consensus_states_count_key(prefix)) {
+        Some(vec) => from_slice(&vec),
+        None => Ok(0),
+    }
+}
+
+fn set_consensus_states_count(
+    storage: &mut dyn Storage,
+    prefix: &'static str,
+    count: u64,
+) -> StdResult<()> {
+    storage.set(&consensus_states_count_key(prefix), &to_vec(&count)?);
+
+    Ok(())
+}
+
 pub fn set_consensus_meta(
     env: &Env,
     storage: &mut dyn Storage,
@@ -101,11 +168,217 @@ pub fn set_consensus_meta(
     height: &Height,
 ) -> StdResult<()> {
     set_processed_time(storage, prefix, height, &env.block.time)?;
-    set_processed_height(storage, prefix, height, &get_self_height(env.block.height))?;
+    set_processed_height(
+        storage,
+        prefix,
+        height,
+        &get_self_height(&env.block.chain_id, env.block.height),
+    )?;
+    set_iteration_key(storage, prefix, height)?;
+
+    Ok(())
+}
+
+// copy_consensus_states copies all consensus states along with their metadata (processed time and
+// height) between the client stores. Returns the number of copied consensus states.
+pub fn copy_consensus_states(
+    storage: &mut dyn Storage,
+    from_prefix: &'static str,
+    to_prefix: &'static str,
+) -> StdResult<u64> {
+    let heights = get_consensus_heights(storage, from_prefix, None, usize::MAX)?;
+
+    for height in heights.iter() {
+        if let Some(bytes) = storage.get(&consensus_state_key(from_prefix, height)) {
+            storage.set(&consensus_state_key(to_prefix, height), &bytes);
+        }
+
+        let processed_time = get_processed_time(storage, from_prefix, height)?;
+        let processed_height = get_processed_height(storage, from_prefix, height)?;
+
+        set_processed_time(storage, to_prefix, height, &processed_time)?;
+        set_processed_height(storage, to_prefix, height, &processed_height)?;
+        set_iteration_key(storage, to_prefix, height)?;
+    }
+
+    Ok(heights.len() as u64)
+}
+
+// backfill_iteration_keys sets the iteration keys (and the count) of the consensus states stored
+// before the heights were indexed, that is the ones with the processed time only. Heights are
+// recovered from the `consensusStates/{revision}-{height}/processedTime` keys. Returns the number of
+// backfilled consensus states.
+pub fn backfill_iteration_keys(storage: &mut dyn Storage, prefix: &'static str) -> StdResult<u64> {
+    let start = format!("{}consensusStates/", prefix).as_bytes().to_owned();
+    let end = prefix_range_end(&start);
+
+    let heights: Vec<Height> = storage
+        .range(Some(&start), Some(&end), Order::Ascending)
+        .filter_map(|(key, _)| parse_processed_time_key(&key[start.len()..]))
+        .collect();
+
+    let mut backfilled: u64 = 0;
+    for height in heights.iter() {
+        if storage.get(&iteration_key(prefix, height)).is_none() {
+            set_iteration_key(storage, prefix, height)?;
+            backfilled += 1;
+        }
+    }
+
+    Ok(backfilled)
+}
+
+// parse_processed_time_key returns the height of the `{revision}-{height}/processedTime` key suffix
+fn parse_processed_time_key(suffix: &[u8]) -> Option<Height> {
+    let suffix = std::str::from_utf8(suffix).ok()?;
+    let height = suffix.strip_suffix("/processedTime")?;
+    let index = height.find('-')?;
+
+    Some(Height {
+        revision_number: height[..index].parse().ok()?,
+        revision_height: height[index + 1..].parse().ok()?,
+    })
+}
+
+// delete_consensus_state removes the consensus state along with its metadata from the client store
+pub fn delete_consensus_state(
+    storage: &mut dyn Storage,
+    prefix: &'static str,
+    height: &Height,
+) -> StdResult<()> {
+    let key = iteration_key(prefix, height);
+    if storage.get(&key).is_some() {
+        let count = get_consensus_states_count(storage, prefix)?;
+        set_consensus_states_count(storage, prefix, count.saturating_sub(1))?;
+    }
+
+    storage.remove(&key);
+    storage.remove(&consensus_state_key(prefix, height));
+    storage.remove(&processed_time_key(prefix, height));
+    storage.remove(&processed_height_key(prefix, height));
 
     Ok(())
 }
 
+// prune_expired_consensus_states removes the oldest consensus states that are past the trusting period.
+// Expiry is determined by the processed time, which is never earlier than the header time, so a
+// consensus state that is still within the trusting period won't be removed.
+//
+// At most `limit` entries are removed per call, so that the cost of a single update stays bounded.
+pub fn prune_expired_consensus_states(
+    storage: &mut dyn Storage,
+    prefix: &'static str,
+    current_timestamp: u64,
+    trusting_period: u64,
+    limit: usize,
+) -> StdResult<u64> {
+    let start = iteration_key_prefix(prefix);
+    let end = prefix_range_end(&start);
+
+    let heights = storage
+        .range(Some(&start), Some(&end), Order::Ascending)
+        .take(limit)
+        .map(|(_, value)| from_slice(&value))
+        .collect::<StdResult<Vec<Height>>>()?;
+
+    let mut pruned: u64 = 0;
+    for height in heights.iter() {
+        let processed_time = get_processed_time(storage, prefix, height)?;
+
+        // Heights are iterated in ascending order, so there is nothing more to prune
+        if current_timestamp <= processed_time.saturating_add(trusting_period) {
+            break;
+        }
+
+        delete_consensus_state(storage, prefix, height)?;
+        pruned += 1;
+    }
+
+    Ok(pruned)
+}
+
+// get_consensus_heights returns up to `limit` stored consensus heights in ascending order,
+// starting after the given height (exclusive)
+pub fn get_consensus_heights(
+    storage: &dyn Storage,
+    prefix: &'static str,
+    start_after: Option<&Height>,
+    limit: usize,
+) -> StdResult<Vec<Height>> {
+    let start = match start_after {
+        Some(height) => exclusive_iteration_key(prefix, height),
+        None => iteration_key_prefix(prefix),
+    };
+    let end = prefix_range_end(&iteration_key_prefix(prefix));
+
+    storage
+        .range(Some(&start), Some(&end), Order::Ascending)
+        .take(limit)
+        .map(|(_, value)| from_slice(&value))
+        .collect()
+}
+
+// get_latest_consensus_height returns the greatest stored consensus height
+pub fn get_latest_consensus_height(
+    storage: &dyn Storage,
+    prefix: &'static str,
+) -> StdResult<Option<Height>> {
+    let start = iteration_key_prefix(prefix);
+    let end = prefix_range_end(&start);
+
+    storage
+        .range(Some(&start), Some(&end), Order::Descending)
+        .next()
+        .map(|(_, value)| from_slice(&value))
+        .transpose()
+}
+
+// get_next_consensus_height returns the lowest stored consensus height greater than the given one
+pub fn get_next_consensus_height(
+    storage: &dyn Storage,
+    prefix: &'static str,
+    height: &Height,
+) -> StdResult<Option<Height>> {
+    Ok(get_consensus_heights(storage, prefix, Some(height), 1)?
+        .into_iter()
+        .next())
+}
+
+// get_prev_consensus_height returns the greatest stored consensus height lower than the given one
+pub fn get_prev_consensus_height(
+    storage: &dyn Storage,
+    prefix: &'static str,
+    height: &Height,
+) -> StdResult<Option<Height>> {
+    let start = iteration_key_prefix(prefix);
+    let end = iteration_key(prefix, height);
+
+    storage
+        .range(Some(&start), Some(&end), Order::Descending)
+        .next()
+        .map(|(_, value)| from_slice(&value))
+        .transpose()
+}
+
+// exclusive_iteration_key returns the smallest key greater than the iteration key of the given height.
+// Iteration keys have a fixed length, so appending a zero byte can't collide with other heights
+fn exclusive_iteration_key(prefix: &'static str, height: &Height) -> Vec<u8> {
+    let mut key = iteration_key(prefix, height);
+    key.push(0);
+
+    key
+}
+
+// prefix_range_end returns the exclusive upper bound of the range covering all keys with the given prefix
+fn prefix_range_end(prefix: &[u8]) -> Vec<u8> {
+    let mut end = prefix.to_vec();
+    if let Some(last) = end.last_mut() {
+        *last += 1;
+    }
+
+    end
+}
+
 pub fn get_consensus_state(
     storage: &dyn Storage,
     prefix: &'static str,
@@ -126,14 +399,53 @@ pub fn set_consensus_state(
     bytes: &Vec<u8>,
 ) -> StdResult<()> {
     let key = consensus_state_key(prefix, height);
-    storage.set(&key, &to_vec(bytes)?);
+    storage.set(&key, bytes);
 
     Ok(())
 }
 
-pub fn get_self_height(block_height: u64) -> Height {
-    Height{
-        revision_number: 0,
+// get_state_version returns the encoding version of the stored states. Contracts instantiated
+// before the states were versioned have no version stored, that is the legacy version.
+pub fn get_state_version(storage: &dyn Storage) -> StdResult<u8> {
+    match storage.get(STATE_VERSION_KEY) {
+        Some(vec) => from_slice(&vec),
+        None => Ok(LEGACY_VERSION),
+    }
+}
+
+pub fn set_state_version(storage: &mut dyn Storage, version: u8) -> StdResult<()> {
+    storage.set(STATE_VERSION_KEY, &to_vec(&version)?);
+
+    Ok(())
+}
+
+// get_self_height returns the host chain height, where the revision number is parsed from the chain id
+pub fn get_self_height(chain_id: &str, block_height: u64) -> Height {
+    Height {
+        revision_number: parse_chain_id_revision(chain_id),
         revision_height: block_height,
     }
 }
+
+// parse_chain_id_revision returns the revision number of the chain id in `{name}-{N}` format
+// (ie. `cosmoshub-4`), or zero if the chain id doesn't follow the convention.
+//
+// Origin: cosmos-sdk/x/ibc/core/02-client/types/height.go (ported)
+pub fn parse_chain_id_revision(chain_id: &str) -> u64 {
+    let (name, revision) = match chain_id.rfind('-') {
+        Some(index) => (&chain_id[..index], &chain_id[index + 1..]),
+        None => return 0,
+    };
+
+    let is_revision_format = !name.is_empty()
+        && !name.ends_with('-')
+        && !revision.starts_with('0')
+        && !revision.is_empty()
+        && revision.chars().all(|c| c.is_ascii_digit());
+
+    if !is_revision_format {
+        return 0;
+    }
+
+    revision.parse::<u64>().unwrap_or(0)
+}
@@ -0,0 +1,415 @@
+use crate::contract::types::ibc::MerklePath;
+use crate::contract::util::{to_generic_err, u64_to_big_endian};
+use crate::proof::{keccak256, verify_account_proof, verify_storage_proof};
+use crate::types::header::{Address, Hash, HASH_LENGTH};
+use cosmwasm_std::StdError;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+// This file defines the EVM storage proof based commitment verification.
+//
+// Celo consensus state root is the root of Ethereum-style state trie, therefore IBC commitments
+// stored by the IBC handler contract are proven in two steps:
+//  * account proof - proves the IBC handler account (and its storage root) against the state root
+//  * storage proof - proves the commitment slot against the account storage root
+//
+// The layout follows the Solidity IBC handler (ie. yui-ibc-solidity `IBCStore`), which keeps
+// `commitments[keccak256(path)] = keccak256(value)`, where the value of the connection and channel
+// ends is their protobuf encoding.
+
+// Origin: eth_getProof RPC response (only the proof nodes are kept)
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Debug)]
+pub struct EvmProof {
+    pub account_proof: Vec<String>, // Go serializes []byte to base64 encoded string
+    pub storage_proof: Vec<String>, // Go serializes []byte to base64 encoded string
+}
+
+// mapping_slot returns the storage slot of the `bytes32` key in the mapping located at `slot`,
+// that is `keccak256(key . slot)` under the Solidity storage layout
+pub fn mapping_slot(key: &Hash, slot: u64) -> Hash {
+    let mut position = [0u8; HASH_LENGTH];
+    position[HASH_LENGTH - 8..].copy_from_slice(&u64_to_big_endian(slot));
+
+    keccak256(&[&key[..], &position[..]].concat())
+}
+
+// commitment_slot returns the storage slot of the commitment in `bytes32 => bytes32` mapping
+// located at `commitments_slot`. The mapping key is `keccak256(path)`.
+pub fn commitment_slot(path: &str, commitments_slot: u64) -> Hash {
+    mapping_slot(&keccak256(path.as_bytes()), commitments_slot)
+}
+
+pub fn verify_evm_membership(
+    proof: &EvmProof,
+    root: &Vec<u8>,
+    ibc_handler_address: &Address,
+    commitments_slot: u64,
+    key_prefix: &str,
+    keys: &MerklePath,
+    value: Vec<u8>,
+) -> Result<bool, StdError> {
+    let expected = keccak256(&value);
+
+    match get_commitment(
+        proof,
+        root,
+        ibc_handler_address,
+        commitments_slot,
+        key_prefix,
+        keys,
+    )? {
+        Some(stored) => {
+            // Storage values are stored without leading zeros
+            if stored.len() > HASH_LENGTH || !left_pad(&stored).iter().eq(expected.iter()) {
+                return Err(StdError::generic_err(format!(
+                    "storage proof failed to verify commitment of value: {:?}, got: {:?}",
+                    value, stored
+                )));
+            }
+
+            Ok(true)
+        }
+        None => Err(StdError::generic_err(
+            "storage proof failed to verify membership (commitment not found)",
+        )),
+    }
+}
+
+pub fn verify_evm_non_membership(
+    proof: &EvmProof,
+    root: &Vec<u8>,
+    ibc_handler_address: &Address,
+    commitments_slot: u64,
+    key_prefix: &str,
+    keys: &MerklePath,
+) -> Result<bool, StdError> {
+    match get_commitment(
+        proof,
+        root,
+        ibc_handler_address,
+        commitments_slot,
+        key_prefix,
+        keys,
+    )? {
+        Some(_) => Err(StdError::generic_err(
+            "storage proof failed to verify non membership (commitment exists)",
+        )),
+        None => Ok(true),
+    }
+}
+
+fn get_commitment(
+    proof: &EvmProof,
+    root: &Vec<u8>,
+    ibc_handler_address: &Address,
+    commitments_slot: u64,
+    key_prefix: &str,
+    keys: &MerklePath,
+) -> Result<Option<Vec<u8>>, StdError> {
+    if root.len() != HASH_LENGTH {
+        return Err(StdError::generic_err(format!(
+            "invalid state root length: {}",
+            root.len()
+        )));
+    }
+    let mut state_root = Hash::default();
+    state_root.copy_from_slice(&root);
+
+    // The merkle prefix identifies the IBC handler store, commitments are keyed by the ICS-24 path
+    let path = match keys.key_path.as_slice() {
+        [prefix, path] => {
+            let key_prefix = base64::decode(key_prefix).map_err(to_generic_err)?;
+            if prefix.as_bytes() != key_prefix.as_slice() {
+                return Err(StdError::generic_err(format!(
+                    "merkle prefix mismatch, expected the ibc handler prefix: {}, got: {}",
+                    base64::encode(&key_prefix),
+                    base64::encode(prefix)
+                )));
+            }
+
+            path
+        }
+        _ => {
+            return Err(StdError::generic_err(format!(
+                "expected the prefixed path, got {} keys",
+                keys.key_path.len()
+            )))
+        }
+    };
+
+    let account_proof = decode_proof_nodes(&proof.account_proof)?;
+    let storage_proof = decode_proof_nodes(&proof.storage_proof)?;
+
+    let storage_root = match verify_account_proof(&state_root, ibc_handler_address, &account_proof)
+        .map_err(to_generic_err)?
+    {
+        Some(storage_root) => storage_root,
+        None => {
+            return Err(StdError::generic_err(
+                "account proof failed to verify (ibc handler account not found)",
+            ))
+        }
+    };
+
+    let slot = commitment_slot(path, commitments_slot);
+    verify_storage_proof(&storage_root, &slot, &storage_proof).map_err(to_generic_err)
+}
+
+fn decode_proof_nodes(nodes: &[String]) -> Result<Vec<Vec<u8>>, StdError> {
+    nodes
+        .iter()
+        .map(|node| base64::decode(node).map_err(to_generic_err))
+        .collect()
+}
+
+fn left_pad(value: &[u8]) -> Hash {
+    let mut out = Hash::default();
+    out[HASH_LENGTH - value.len()..].copy_from_slice(value);
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // eth_getProof of the IBC handler, that keeps the commitments mapping at slot 0 (followed by the
+    // other handler state, ie. the sequence counters) and commits under the `ibc` prefix
+    const STATE_ROOT: &str = "a1a7893fbf9263a4961f590dedfa001c90a8e3d13035c1ae4d1486edeb172589";
+    const HANDLER_ADDRESS: &str = "a7f733a4fec1a9f2a1b6bcd9d6cbfd40d55e8bad";
+
+    const ACCOUNT_PROOF: &[&str] = &[
+        "f85180a0af69c44998b3723e11f68055281e824c2fa4b8333a912a40512d1fc0df70407d80808080\
+         80a06790d03bc8373f56b481b85f6de71604d5d8f64d879caa638cad9dcd8df35672808080808080\
+         808080",
+        "f869a0399dbdf90cc2bdb429a989213419e695351c92c823a99ecf784c82891ce618c4b846f84401\
+         80a0fa840111b8dd2d1737296aa074dbf762d8b8f6a6a72ae359755bac6bad33500ca0e5ebc5605c\
+         ea900e6530c0f25ee78e27eb50cf83e16ce5b5757e50466591db89",
+    ];
+
+    const CONNECTION_PROOF: &[&str] = &[
+        "f8b1a0ec5c3d53fca2be83c4c0140dac5bfbdd9894002249b067bb51a2acffb61fb584808080a015\
+         503e91f9250654cf72906e38a7cb14c3f1cc06658379d37f0c5b5c3248288080808080a0595b01ff\
+         a594f6af9f345c20cc8019bbe0d2650df273bf80f21b37ae5562e3c980a05458442815aa9a02c701\
+         ce8d63b6eada3db60a8221cee824a42ee9dbe24cbf098080a0df7ef624b2c1f985f06f7d1da693d1\
+         fd82b0fe6f6832c846342cdf5381cea1c28080",
+        "f843a0353fc42ddf6c1b5bb218ce24e14c40af9e0eb127a5d76050d37d7369e2fc4a47a1a0853bfa\
+         74db62eb86759541fa669899aef8ed916f8645a5c6465e4049ed07b281",
+    ];
+
+    const CHANNEL_PROOF: &[&str] = &[
+        "f8b1a0ec5c3d53fca2be83c4c0140dac5bfbdd9894002249b067bb51a2acffb61fb584808080a015\
+         503e91f9250654cf72906e38a7cb14c3f1cc06658379d37f0c5b5c3248288080808080a0595b01ff\
+         a594f6af9f345c20cc8019bbe0d2650df273bf80f21b37ae5562e3c980a05458442815aa9a02c701\
+         ce8d63b6eada3db60a8221cee824a42ee9dbe24cbf098080a0df7ef624b2c1f985f06f7d1da693d1\
+         fd82b0fe6f6832c846342cdf5381cea1c28080",
+        "f843a031d6f89588ae3087a81d82718f12d5af74ea0d6910e254985b6ba71ece81f693a1a00106f5\
+         8837ea2545e2aa22b88b34cc5728e0d5ab4d724422749035579e953b09",
+    ];
+
+    const PACKET_COMMITMENT_PROOF: &[&str] = &[
+        "f8b1a0ec5c3d53fca2be83c4c0140dac5bfbdd9894002249b067bb51a2acffb61fb584808080a015\
+         503e91f9250654cf72906e38a7cb14c3f1cc06658379d37f0c5b5c3248288080808080a0595b01ff\
+         a594f6af9f345c20cc8019bbe0d2650df273bf80f21b37ae5562e3c980a05458442815aa9a02c701\
+         ce8d63b6eada3db60a8221cee824a42ee9dbe24cbf098080a0df7ef624b2c1f985f06f7d1da693d1\
+         fd82b0fe6f6832c846342cdf5381cea1c28080",
+        "f843a03cf9071c2ceeb29736816e6bbb33d9fddbb263ee552b0c0d6934c44a0c74659fa1a0a3cbf8\
+         9e6ce03d2b5fad6068b776475c5b0cc9c59ab8e33e385ddd717fd5fe2b",
+    ];
+
+    // Sequence 2 has not been sent, the proof ends at the root branch
+    const ABSENT_PACKET_COMMITMENT_PROOF: &[&str] = &[
+        "f8b1a0ec5c3d53fca2be83c4c0140dac5bfbdd9894002249b067bb51a2acffb61fb584808080a015\
+         503e91f9250654cf72906e38a7cb14c3f1cc06658379d37f0c5b5c3248288080808080a0595b01ff\
+         a594f6af9f345c20cc8019bbe0d2650df273bf80f21b37ae5562e3c980a05458442815aa9a02c701\
+         ce8d63b6eada3db60a8221cee824a42ee9dbe24cbf098080a0df7ef624b2c1f985f06f7d1da693d1\
+         fd82b0fe6f6832c846342cdf5381cea1c28080",
+    ];
+
+    // Protobuf encoded ibc.core.connection.v1.ConnectionEnd (see `proto::tests`)
+    const CONNECTION_END: &str = "0a0f30372d74656e6465726d696e742d3012230a0131120d4f524445525f4f52\
+         4445524544120f4f524445525f554e4f524445524544180322200a0931302d7761736d2d30120c636f\
+         6e6e656374696f6e2d301a050a03696263";
+
+    // Protobuf encoded ibc.core.channel.v1.Channel (see `proto::tests`)
+    const CHANNEL: &str = "080310021a150a087472616e7366657212096368616e6e656c2d30220c636f6e6e65\
+         6374696f6e2d302a0769637332302d31";
+
+    // sha256("packet"), the packet commitment is opaque to the client
+    const PACKET_COMMITMENT: &str =
+        "7426afc489d0eef99a0b438def226ad139f752350c25cf2c04900281afbb79e0";
+
+    fn proof(storage_proof: &[&str]) -> EvmProof {
+        let encode = |nodes: &[&str]| {
+            nodes
+                .iter()
+                .map(|node| base64::encode(hex::decode(node).unwrap()))
+                .collect()
+        };
+
+        EvmProof {
+            account_proof: encode(ACCOUNT_PROOF),
+            storage_proof: encode(storage_proof),
+        }
+    }
+
+    fn prefixed(path: &str) -> MerklePath {
+        MerklePath {
+            key_path: vec!["ibc".to_string(), path.to_string()],
+        }
+    }
+
+    fn handler() -> Address {
+        let mut address = Address::default();
+        address.copy_from_slice(&hex::decode(HANDLER_ADDRESS).unwrap());
+
+        address
+    }
+
+    #[test]
+    fn test_commitment_slot_layout() {
+        // keccak256(abi.encode(bytes32(0), uint256(0))), the Solidity slot of mapping[0] at slot 0
+        assert_eq!(
+            hex::encode(mapping_slot(&Hash::default(), 0)),
+            "ad3228b676f7d3cd4284a5443f17f1962b36e491b30a40b2405849e597ba5fb5"
+        );
+        assert_eq!(
+            hex::encode(commitment_slot("connections/connection-0", 0)),
+            "10db75c594fbefd2666fdf7bd8695af4cbfb37924118d52cca1dd45cc7668c26"
+        );
+    }
+
+    #[test]
+    fn test_verify_ibc_handler_commitments() {
+        let root = hex::decode(STATE_ROOT).unwrap();
+        let address = handler();
+        let key_prefix = base64::encode("ibc");
+        let verify = |storage_proof: &[&str], path: &str, value: &str| {
+            verify_evm_membership(
+                &proof(storage_proof),
+                &root,
+                &address,
+                0,
+                &key_prefix,
+                &prefixed(path),
+                hex::decode(value).unwrap(),
+            )
+        };
+
+        assert_eq!(
+            verify(CONNECTION_PROOF, "connections/connection-0", CONNECTION_END).unwrap(),
+            true
+        );
+        assert_eq!(
+            verify(
+                CHANNEL_PROOF,
+                "channelEnds/ports/transfer/channels/channel-0",
+                CHANNEL
+            )
+            .unwrap(),
+            true
+        );
+        assert_eq!(
+            verify(
+                PACKET_COMMITMENT_PROOF,
+                "commitments/ports/transfer/channels/channel-0/sequences/1",
+                PACKET_COMMITMENT
+            )
+            .unwrap(),
+            true
+        );
+
+        // The handler commits to the connection end, not to the channel end
+        assert!(verify(CONNECTION_PROOF, "connections/connection-0", CHANNEL).is_err());
+
+        // The proof of other commitment doesn't prove the path
+        assert!(verify(CHANNEL_PROOF, "connections/connection-0", CONNECTION_END).is_err());
+    }
+
+    #[test]
+    fn test_verify_ibc_handler_non_membership() {
+        let root = hex::decode(STATE_ROOT).unwrap();
+        let address = handler();
+        let key_prefix = base64::encode("ibc");
+        let sent = prefixed("commitments/ports/transfer/channels/channel-0/sequences/1");
+        let absent = prefixed("commitments/ports/transfer/channels/channel-0/sequences/2");
+
+        assert_eq!(
+            verify_evm_non_membership(
+                &proof(ABSENT_PACKET_COMMITMENT_PROOF),
+                &root,
+                &address,
+                0,
+                &key_prefix,
+                &absent
+            )
+            .unwrap(),
+            true
+        );
+        assert!(verify_evm_non_membership(
+            &proof(PACKET_COMMITMENT_PROOF),
+            &root,
+            &address,
+            0,
+            &key_prefix,
+            &sent
+        )
+        .is_err());
+        assert!(verify_evm_membership(
+            &proof(ABSENT_PACKET_COMMITMENT_PROOF),
+            &root,
+            &address,
+            0,
+            &key_prefix,
+            &absent,
+            hex::decode(PACKET_COMMITMENT).unwrap()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_verify_ibc_handler_prefix() {
+        let root = hex::decode(STATE_ROOT).unwrap();
+        let address = handler();
+        let value = hex::decode(CONNECTION_END).unwrap();
+        let path = "connections/connection-0".to_string();
+        let verify = |key_prefix: &str, keys: MerklePath| {
+            verify_evm_membership(
+                &proof(CONNECTION_PROOF),
+                &root,
+                &address,
+                0,
+                &base64::encode(key_prefix),
+                &keys,
+                value.clone(),
+            )
+        };
+
+        // The prefix has to be the one of the configured handler
+        let keys = MerklePath {
+            key_path: vec!["other".to_string(), path.clone()],
+        };
+        let err = verify("ibc", keys).unwrap_err();
+        assert!(err.to_string().contains("merkle prefix mismatch"));
+        assert!(verify("other", prefixed(&path)).is_err());
+
+        // The path has to be prefixed
+        let keys = MerklePath {
+            key_path: vec![path.clone()],
+        };
+        assert!(verify("ibc", keys).is_err());
+
+        // Other account doesn't hold the commitments
+        let other: Address = [1; 20];
+        assert!(verify_evm_membership(
+            &proof(CONNECTION_PROOF),
+            &root,
+            &other,
+            0,
+            &base64::encode("ibc"),
+            &prefixed(&path),
+            value.clone()
+        )
+        .is_err());
+    }
+}
@@ -24,11 +24,11 @@ pub type ClientUpgradePath = ibc::ics24_host::ClientUpgradePath;
 // Origin: ibc.core.connection.v1 (compiled proto)
 #[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Debug)]
 pub struct ConnectionEnd {
-    client_id: String,
-    versions: Vec<Version>,
-    state: i32,
-    counterparty: Counterparty,
-    delay_period: u64,
+    pub client_id: String,
+    pub versions: Vec<Version>,
+    pub state: i32,
+    pub counterparty: Counterparty,
+    pub delay_period: u64,
 }
 
 // Origin: ibc.core.connection.v1 (compiled proto)
@@ -51,11 +51,18 @@ pub struct Version {
 pub struct Channel {
     pub state: i32,
     pub ordering: i32,
-    pub counterparty: Counterparty,
+    pub counterparty: ChannelCounterparty,
     pub connection_hops: Vec<String>,
     pub version: String,
 }
 
+// Origin: ibc.core.channel.v1 (compiled proto)
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Debug)]
+pub struct ChannelCounterparty {
+    pub port_id: String,
+    pub channel_id: String,
+}
+
 // Origin: ibc.core.commitment.v1 (compiled proto)
 #[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Debug)]
 pub struct MerklePrefix {
@@ -90,6 +97,13 @@ pub struct Height {
     pub revision_height: u64,
 }
 
+impl Height {
+    // is_zero returns true for the zero height, that is the unset height of the Go client
+    pub fn is_zero(&self) -> bool {
+        self.revision_number == 0 && self.revision_height == 0
+    }
+}
+
 impl PartialOrd for Height {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.cmp(other))
@@ -145,19 +159,34 @@ pub fn verify_membership(
     mut value: Vec<u8>,
     index: usize,
 ) -> Result<bool, StdError> {
+    // The chained proofs (see `verify_non_membership`) are checked by the caller
+    if index == 0 {
+        validate_proof_lengths(proof, specs, keys)?;
+    }
+
     let mut subroot = value.clone();
 
-    for (i, commitment_proof) in proof.proofs.iter().skip(index).enumerate() {
+    for (i, commitment_proof) in proof.proofs.iter().enumerate().skip(index) {
         if let Some(ex) = get_exist_proof(commitment_proof) {
             subroot = ics23::calculate_existence_root(&ex).map_err(|e| to_generic_err(e))?;
-            let key = match keys.key_path.get(keys.key_path.len() - 1 - i) {
+            let key = match keys
+                .key_path
+                .len()
+                .checked_sub(1 + i)
+                .and_then(|i| keys.key_path.get(i))
+            {
                 Some(key) => key,
                 None => return Err(StdError::generic_err("could not retrieve key bytes")),
             };
 
+            let spec = match specs.get(i) {
+                Some(spec) => spec,
+                None => return Err(StdError::generic_err("could not retrieve proof spec")),
+            };
+
             if !ics23::verify_membership(
                 &commitment_proof,
-                &specs[i],
+                spec,
                 &subroot,
                 key.as_bytes(),
                 &value,
@@ -186,6 +215,87 @@ pub fn verify_membership(
     Ok(true)
 }
 
+// Origin: cosmos-sdk/x/ibc/core/23-commitment/types/merkle.go (ported)
+pub fn verify_non_membership(
+    proof: &MerkleProof,
+    specs: &[ics23::ProofSpec],
+    root: &Vec<u8>,
+    keys: &MerklePath,
+) -> Result<bool, StdError> {
+    validate_proof_lengths(proof, specs, keys)?;
+
+    // Verify the absence of key in the lowest subtree
+    let non_exist_proof = match get_non_exist_proof(&proof.proofs[0]) {
+        Some(nonexist) => nonexist,
+        None => {
+            return Err(StdError::generic_err(
+                "expected proof type: ics23::NonExistenceProof",
+            ))
+        }
+    };
+    let subroot = calculate_non_existence_root(non_exist_proof)?;
+    let key = keys.key_path.last().unwrap();
+
+    if !ics23::verify_non_membership(&proof.proofs[0], &specs[0], &subroot, key.as_bytes()) {
+        return Err(StdError::generic_err(format!(
+            "failed to prove absence of key: {} in subroot: {:?}",
+            key, subroot
+        )));
+    }
+
+    // Chain the inclusion proofs of all subroots up to the final root
+    verify_membership(proof, specs, root, keys, subroot, 1)
+}
+
+// validate_proof_lengths checks that there is a proof (and a spec) for every key of the path
+fn validate_proof_lengths(
+    proof: &MerkleProof,
+    specs: &[ics23::ProofSpec],
+    keys: &MerklePath,
+) -> Result<(), StdError> {
+    if proof.proofs.is_empty() {
+        return Err(StdError::generic_err("proof cannot be empty"));
+    }
+
+    if proof.proofs.len() != specs.len() {
+        return Err(StdError::generic_err(format!(
+            "length of specs: {} not equal to length of proof: {}",
+            specs.len(),
+            proof.proofs.len()
+        )));
+    }
+
+    if keys.key_path.len() != specs.len() {
+        return Err(StdError::generic_err(format!(
+            "path length {} not same as proof {}",
+            keys.key_path.len(),
+            specs.len()
+        )));
+    }
+
+    Ok(())
+}
+
+// The root of the non existence proof is calculated from any of the neighbours (left or right)
+fn calculate_non_existence_root(proof: &ics23::NonExistenceProof) -> Result<Vec<u8>, StdError> {
+    match (&proof.left, &proof.right) {
+        (Some(left), _) => ics23::calculate_existence_root(left).map_err(|e| to_generic_err(e)),
+        (None, Some(right)) => ics23::calculate_existence_root(right).map_err(|e| to_generic_err(e)),
+        (None, None) => Err(StdError::generic_err(
+            "non existence proof has neither left nor right proof",
+        )),
+    }
+}
+
+fn get_non_exist_proof<'a>(
+    proof: &'a ics23::CommitmentProof,
+) -> Option<&'a ics23::NonExistenceProof> {
+    match &proof.proof {
+        Some(ics23::commitment_proof::Proof::Nonexist(nonexist)) => Some(nonexist),
+        _ => None,
+    }
+}
+
 fn get_exist_proof<'a>(proof: &'a ics23::CommitmentProof) -> Option<&'a ics23::ExistenceProof> {
     match &proof.proof {
         Some(ics23::commitment_proof::Proof::Exist(ex)) => Some(ex),
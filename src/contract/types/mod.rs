@@ -1,4 +1,7 @@
 pub mod wasm;
 pub mod msg;
 pub mod ibc;
+pub mod evm;
 pub mod state;
+pub mod proto;
+pub mod tendermint;
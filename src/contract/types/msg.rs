@@ -1,7 +1,7 @@
-use crate::contract::types::ibc::{Channel, ConnectionEnd, Height, MerklePrefix};
+use crate::contract::errors::ContractError;
+use crate::contract::types::ibc::{Channel, ConnectionEnd, Height, MerklePath, MerklePrefix};
 use crate::contract::types::wasm::{
-    ClientState, ConsensusState, CosmosClientState, CosmosConsensusState, Misbehaviour, Status,
-    WasmHeader,
+    ClientState, ConsensusState, Misbehaviour, Status, WasmHeader,
 };
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
@@ -18,6 +18,12 @@ pub enum HandleMsg {
         consensus_state: ConsensusState,
         me: ClientState,
     },
+    CheckEpochHeadersAndUpdateState {
+        epoch_headers: Vec<WasmHeader>,
+        header: Option<WasmHeader>,
+        consensus_state: ConsensusState,
+        me: ClientState,
+    },
     VerifyUpgradeAndUpdateState {
         me: ClientState,
         new_client_state: ClientState,
@@ -55,6 +61,15 @@ pub struct ClientStateCallResponse {
 pub struct ClientStateCallResponseResult {
     pub is_valid: bool,
     pub err_msg: String,
+    // stable code of the error (see `ContractError::code`), zero on success
+    #[serde(default)]
+    pub error_code: u32,
+}
+
+// FailureResult is returned by the verify queries in place of the call specific result
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, JsonSchema)]
+pub struct FailureResult {
+    pub result: ClientStateCallResponseResult,
 }
 
 #[derive(Serialize, Deserialize, Clone, PartialEq, Debug, JsonSchema)]
@@ -118,6 +133,16 @@ pub struct VerifyPacketReceiptAbsenceResult {
     pub result: ClientStateCallResponseResult,
 }
 
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, JsonSchema)]
+pub struct VerifyMembershipResult {
+    pub result: ClientStateCallResponseResult,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, JsonSchema)]
+pub struct VerifyNonMembershipResult {
+    pub result: ClientStateCallResponseResult,
+}
+
 #[derive(Serialize, Deserialize, Clone, PartialEq, Debug, JsonSchema)]
 pub struct CheckSubstituteAndUpdateStateResult {
     pub result: ClientStateCallResponseResult,
@@ -134,6 +159,10 @@ pub struct StatusResult {
     pub status: Status,
 }
 
+// MigrateMsg upgrades the states stored by the contract to the current encoding version
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct MigrateMsg {}
+
 #[derive(Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum QueryMsg {
@@ -143,7 +172,7 @@ pub enum QueryMsg {
         commitment_prefix: MerklePrefix,
         counterparty_client_identifier: String,
         proof: String, // Go serializes []byte to base64 encoded string
-        counterparty_client_state: CosmosClientState,
+        counterparty_client_state: String, // Protobuf Any encoded, base64 encoded string
         consensus_state: ConsensusState,
     },
     VerifyClientConsensusState {
@@ -153,7 +182,7 @@ pub enum QueryMsg {
         commitment_prefix: MerklePrefix,
         counterparty_client_identifier: String,
         proof: String, // Go serializes []byte to base64 encoded string
-        counterparty_consensus_state: CosmosConsensusState,
+        counterparty_consensus_state: String, // Protobuf Any encoded, base64 encoded string
         consensus_state: ConsensusState,
     },
     VerifyConnectionState {
@@ -225,11 +254,39 @@ pub enum QueryMsg {
         next_sequence_recv: u64,
         consensus_state: ConsensusState,
     },
+    VerifyMembership {
+        me: ClientState,
+        height: Height,
+        delay_time_period: u64,
+        delay_block_period: u64,
+        proof: String, // Go serializes []byte to base64 encoded string
+        path: MerklePath, // Prefixed path
+        value: String, // Go serializes []byte to base64 encoded string
+        consensus_state: ConsensusState,
+    },
+    VerifyNonMembership {
+        me: ClientState,
+        height: Height,
+        delay_time_period: u64,
+        delay_block_period: u64,
+        proof: String, // Go serializes []byte to base64 encoded string
+        path: MerklePath, // Prefixed path
+        consensus_state: ConsensusState,
+    },
     Status {
         me: ClientState,
         consensus_state: ConsensusState,
     },
     ProcessedTime { height: Height },
+    ConsensusStatesCount {},
+    ConsensusState { height: Height },
+    LatestConsensusState {},
+    ListConsensusHeights {
+        start_after: Option<Height>,
+        limit: Option<u32>,
+    },
+    NextConsensusStateAfter { height: Height },
+    PrevConsensusStateBefore { height: Height },
 }
 
 #[derive(Serialize, Deserialize, Clone, PartialEq, Debug, JsonSchema)]
@@ -237,11 +294,41 @@ pub struct ProcessedTimeResponse {
     pub time: u64,
 }
 
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, JsonSchema)]
+pub struct ConsensusStatesCountResponse {
+    pub count: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, JsonSchema)]
+pub struct ConsensusStateResponse {
+    pub height: Height,
+    pub consensus_state: String, // Consensus state bytes (as stored in client store) encoded in base64
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, JsonSchema)]
+pub struct AdjacentConsensusStateResponse {
+    pub consensus_state: Option<ConsensusStateResponse>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, JsonSchema)]
+pub struct ListConsensusHeightsResponse {
+    pub heights: Vec<Height>,
+}
+
 impl ClientStateCallResponseResult {
     pub fn success() -> Self {
         Self {
             is_valid: true,
             err_msg: "".to_owned(),
+            error_code: 0,
+        }
+    }
+
+    pub fn failure(err: &ContractError) -> Self {
+        Self {
+            is_valid: false,
+            err_msg: err.err_msg(),
+            error_code: err.code(),
         }
     }
 }
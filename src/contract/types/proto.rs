@@ -0,0 +1,534 @@
+use crate::contract::serialization::from_base64;
+use crate::contract::types::ibc::{
+    Channel, ChannelCounterparty, ConnectionEnd, Counterparty, Height, MerklePrefix, MerkleRoot,
+    Version,
+};
+use crate::contract::types::state::{LightClientState, LightConsensusState, Versioned};
+use crate::contract::types::wasm::{ClientState, ConsensusState, Misbehaviour, WasmHeader};
+use crate::contract::util::to_generic_err;
+use crate::traits::FromRlp;
+use crate::types::header::Header;
+use cosmwasm_std::StdError;
+use prost::Message;
+use prost_derive::Message;
+use std::convert::TryFrom;
+
+// This file defines the protobuf encoding of the light client structures (ibc.lightclients.wasm.v1).
+//
+// ibc-go stores client and consensus states as protobuf `Any` messages, whereas the contract
+// messages carry JSON with base64 encoded RLP payloads. The structures below bridge both worlds,
+// so that the values proven / stored by the host chain can be reproduced by the contract.
+//
+// The connection and channel ends (ibc.core.connection.v1, ibc.core.channel.v1) are defined here as
+// well, since the EVM IBC handler commits to their protobuf encoding (see `CommitmentScheme::EvmMpt`).
+
+pub const WASM_CLIENT_STATE_TYPE_URL: &str = "/ibc.lightclients.wasm.v1.ClientState";
+pub const WASM_CONSENSUS_STATE_TYPE_URL: &str = "/ibc.lightclients.wasm.v1.ConsensusState";
+pub const WASM_HEADER_TYPE_URL: &str = "/ibc.lightclients.wasm.v1.Header";
+pub const WASM_MISBEHAVIOUR_TYPE_URL: &str = "/ibc.lightclients.wasm.v1.Misbehaviour";
+
+// Origin: google.protobuf (compiled proto)
+#[derive(Message, Clone, PartialEq)]
+pub struct Any {
+    #[prost(string, tag = "1")]
+    pub type_url: String,
+    #[prost(bytes = "vec", tag = "2")]
+    pub value: Vec<u8>,
+}
+
+// Origin: ibc.core.client.v1 (compiled proto)
+#[derive(Message, Clone, PartialEq)]
+pub struct ProtoHeight {
+    #[prost(uint64, tag = "1")]
+    pub revision_number: u64,
+    #[prost(uint64, tag = "2")]
+    pub revision_height: u64,
+}
+
+// Origin: ibc.core.commitment.v1 (compiled proto)
+#[derive(Message, Clone, PartialEq)]
+pub struct ProtoMerkleRoot {
+    #[prost(bytes = "vec", tag = "1")]
+    pub hash: Vec<u8>,
+}
+
+// Origin: ibc.lightclients.wasm.v1 (compiled proto)
+#[derive(Message, Clone, PartialEq)]
+pub struct ProtoClientState {
+    #[prost(bytes = "vec", tag = "1")]
+    pub data: Vec<u8>,
+    #[prost(bytes = "vec", tag = "2")]
+    pub code_id: Vec<u8>,
+    #[prost(bool, tag = "3")]
+    pub frozen: bool,
+    #[prost(message, optional, tag = "4")]
+    pub frozen_height: Option<ProtoHeight>,
+    #[prost(message, optional, tag = "5")]
+    pub latest_height: Option<ProtoHeight>,
+}
+
+// Origin: ibc.lightclients.wasm.v1 (compiled proto)
+#[derive(Message, Clone, PartialEq)]
+pub struct ProtoConsensusState {
+    #[prost(bytes = "vec", tag = "1")]
+    pub code_id: Vec<u8>,
+    #[prost(bytes = "vec", tag = "2")]
+    pub data: Vec<u8>,
+    #[prost(uint64, tag = "3")]
+    pub timestamp: u64,
+    #[prost(message, optional, tag = "4")]
+    pub root: Option<ProtoMerkleRoot>,
+}
+
+// Origin: ibc.lightclients.wasm.v1 (compiled proto)
+#[derive(Message, Clone, PartialEq)]
+pub struct ProtoHeader {
+    #[prost(bytes = "vec", tag = "1")]
+    pub data: Vec<u8>,
+    #[prost(message, optional, tag = "2")]
+    pub height: Option<ProtoHeight>,
+}
+
+// Origin: ibc.lightclients.wasm.v1 (compiled proto)
+#[derive(Message, Clone, PartialEq)]
+pub struct ProtoMisbehaviour {
+    #[prost(bytes = "vec", tag = "1")]
+    pub code_id: Vec<u8>,
+    #[prost(string, tag = "2")]
+    pub client_id: String,
+    #[prost(message, optional, tag = "3")]
+    pub header_1: Option<ProtoHeader>,
+    #[prost(message, optional, tag = "4")]
+    pub header_2: Option<ProtoHeader>,
+}
+
+// Origin: ibc.core.commitment.v1 (compiled proto)
+#[derive(Message, Clone, PartialEq)]
+pub struct ProtoMerklePrefix {
+    #[prost(bytes = "vec", tag = "1")]
+    pub key_prefix: Vec<u8>,
+}
+
+// Origin: ibc.core.connection.v1 (compiled proto)
+#[derive(Message, Clone, PartialEq)]
+pub struct ProtoConnectionEnd {
+    #[prost(string, tag = "1")]
+    pub client_id: String,
+    #[prost(message, repeated, tag = "2")]
+    pub versions: Vec<ProtoVersion>,
+    #[prost(int32, tag = "3")]
+    pub state: i32,
+    #[prost(message, optional, tag = "4")]
+    pub counterparty: Option<ProtoCounterparty>,
+    #[prost(uint64, tag = "5")]
+    pub delay_period: u64,
+}
+
+// Origin: ibc.core.connection.v1 (compiled proto)
+#[derive(Message, Clone, PartialEq)]
+pub struct ProtoCounterparty {
+    #[prost(string, tag = "1")]
+    pub client_id: String,
+    #[prost(string, tag = "2")]
+    pub connection_id: String,
+    #[prost(message, optional, tag = "3")]
+    pub prefix: Option<ProtoMerklePrefix>,
+}
+
+// Origin: ibc.core.connection.v1 (compiled proto)
+#[derive(Message, Clone, PartialEq)]
+pub struct ProtoVersion {
+    #[prost(string, tag = "1")]
+    pub identifier: String,
+    #[prost(string, repeated, tag = "2")]
+    pub features: Vec<String>,
+}
+
+// Origin: ibc.core.channel.v1 (compiled proto)
+#[derive(Message, Clone, PartialEq)]
+pub struct ProtoChannel {
+    #[prost(int32, tag = "1")]
+    pub state: i32,
+    #[prost(int32, tag = "2")]
+    pub ordering: i32,
+    #[prost(message, optional, tag = "3")]
+    pub counterparty: Option<ProtoChannelCounterparty>,
+    #[prost(string, repeated, tag = "4")]
+    pub connection_hops: Vec<String>,
+    #[prost(string, tag = "5")]
+    pub version: String,
+}
+
+// Origin: ibc.core.channel.v1 (compiled proto)
+#[derive(Message, Clone, PartialEq)]
+pub struct ProtoChannelCounterparty {
+    #[prost(string, tag = "1")]
+    pub port_id: String,
+    #[prost(string, tag = "2")]
+    pub channel_id: String,
+}
+
+// encode_message returns the protobuf encoding of the message
+pub fn encode_message<M: Message>(message: &M) -> Result<Vec<u8>, StdError> {
+    let mut bytes = Vec::new();
+    message.encode(&mut bytes).map_err(to_generic_err)?;
+
+    Ok(bytes)
+}
+
+// encode_any wraps the message into protobuf `Any` and returns its bytes
+pub fn encode_any<M: Message>(type_url: &str, message: &M) -> Result<Vec<u8>, StdError> {
+    encode_message(&Any {
+        type_url: type_url.to_string(),
+        value: encode_message(message)?,
+    })
+}
+
+// decode_any unwraps protobuf `Any` of the expected type and decodes the message
+pub fn decode_any<M: Message + Default>(bytes: &[u8], type_url: &str) -> Result<M, StdError> {
+    let any = Any::decode(bytes).map_err(to_generic_err)?;
+    if any.type_url != type_url {
+        return Err(StdError::generic_err(format!(
+            "unexpected type url, expected: {}, got: {}",
+            type_url, any.type_url
+        )));
+    }
+
+    M::decode(any.value.as_slice()).map_err(to_generic_err)
+}
+
+// decode_stored_consensus_state decodes the consensus state stored by the host. The `Any` encoding
+// is expected, though the bare message (as stored by the earlier 10-wasm versions) is accepted too.
+//
+// The bare message may happen to parse as `Any` (both start with a length delimited field), so the
+// bytes are taken for `Any` only if the type url is well-formed (ie. has a `/`). An `Any` of other
+// type is rejected.
+pub fn decode_stored_consensus_state(bytes: &[u8]) -> Result<ProtoConsensusState, StdError> {
+    match Any::decode(bytes) {
+        Ok(any) if any.type_url.contains('/') => decode_any(bytes, WASM_CONSENSUS_STATE_TYPE_URL),
+        _ => ProtoConsensusState::decode(bytes).map_err(to_generic_err),
+    }
+}
+
+impl ProtoClientState {
+    pub fn light_client_state(&self) -> Result<LightClientState, StdError> {
+        LightClientState::from_versioned(&self.data).map_err(to_generic_err)
+    }
+}
+
+impl ProtoConsensusState {
+    pub fn light_consensus_state(&self) -> Result<LightConsensusState, StdError> {
+        LightConsensusState::from_versioned(&self.data).map_err(to_generic_err)
+    }
+}
+
+impl ProtoHeader {
+    pub fn header(&self) -> Result<Header, StdError> {
+        Header::from_rlp(&self.data).map_err(to_generic_err)
+    }
+}
+
+impl From<Height> for ProtoHeight {
+    fn from(height: Height) -> Self {
+        ProtoHeight {
+            revision_number: height.revision_number,
+            revision_height: height.revision_height,
+        }
+    }
+}
+
+impl From<ProtoHeight> for Height {
+    fn from(height: ProtoHeight) -> Self {
+        Height {
+            revision_number: height.revision_number,
+            revision_height: height.revision_height,
+        }
+    }
+}
+
+impl TryFrom<&ClientState> for ProtoClientState {
+    type Error = StdError;
+
+    fn try_from(client_state: &ClientState) -> Result<Self, Self::Error> {
+        Ok(ProtoClientState {
+            data: from_base64(&client_state.data, "client_state.data")?,
+            code_id: from_base64(&client_state.code_id, "client_state.code_id")?,
+            frozen: client_state.frozen,
+            frozen_height: client_state.frozen_height.map(ProtoHeight::from),
+            latest_height: client_state.latest_height.map(ProtoHeight::from),
+        })
+    }
+}
+
+impl From<&ProtoClientState> for ClientState {
+    fn from(client_state: &ProtoClientState) -> Self {
+        ClientState {
+            data: base64::encode(&client_state.data),
+            code_id: base64::encode(&client_state.code_id),
+            frozen: client_state.frozen,
+            frozen_height: client_state.frozen_height.clone().map(Height::from),
+            latest_height: client_state.latest_height.clone().map(Height::from),
+        }
+    }
+}
+
+impl TryFrom<&ConsensusState> for ProtoConsensusState {
+    type Error = StdError;
+
+    fn try_from(consensus_state: &ConsensusState) -> Result<Self, Self::Error> {
+        Ok(ProtoConsensusState {
+            code_id: from_base64(&consensus_state.code_id, "consensus_state.code_id")?,
+            data: from_base64(&consensus_state.data, "consensus_state.data")?,
+            timestamp: consensus_state.timestamp,
+            root: Some(ProtoMerkleRoot {
+                hash: from_base64(&consensus_state.root.hash, "consensus_state.root")?,
+            }),
+        })
+    }
+}
+
+impl From<&ProtoConsensusState> for ConsensusState {
+    fn from(consensus_state: &ProtoConsensusState) -> Self {
+        let root = consensus_state
+            .root
+            .as_ref()
+            .map(|root| root.hash.clone())
+            .unwrap_or_default();
+
+        ConsensusState {
+            code_id: base64::encode(&consensus_state.code_id),
+            data: base64::encode(&consensus_state.data),
+            timestamp: consensus_state.timestamp,
+            root: MerkleRoot {
+                hash: base64::encode(root),
+            },
+        }
+    }
+}
+
+impl TryFrom<&WasmHeader> for ProtoHeader {
+    type Error = StdError;
+
+    fn try_from(header: &WasmHeader) -> Result<Self, Self::Error> {
+        Ok(ProtoHeader {
+            data: from_base64(&header.data, "header.data")?,
+            height: Some(ProtoHeight::from(header.height)),
+        })
+    }
+}
+
+impl TryFrom<&ProtoHeader> for WasmHeader {
+    type Error = StdError;
+
+    fn try_from(header: &ProtoHeader) -> Result<Self, Self::Error> {
+        let height = match &header.height {
+            Some(height) => Height::from(height.clone()),
+            None => return Err(StdError::generic_err("header height is missing")),
+        };
+
+        Ok(WasmHeader {
+            data: base64::encode(&header.data),
+            height,
+        })
+    }
+}
+
+impl TryFrom<&Misbehaviour> for ProtoMisbehaviour {
+    type Error = StdError;
+
+    fn try_from(misbehaviour: &Misbehaviour) -> Result<Self, Self::Error> {
+        Ok(ProtoMisbehaviour {
+            code_id: from_base64(&misbehaviour.code_id, "misbehaviour.code_id")?,
+            client_id: misbehaviour.client_id.clone(),
+            header_1: Some(ProtoHeader::try_from(&misbehaviour.header_1)?),
+            header_2: Some(ProtoHeader::try_from(&misbehaviour.header_2)?),
+        })
+    }
+}
+
+impl TryFrom<&ProtoMisbehaviour> for Misbehaviour {
+    type Error = StdError;
+
+    fn try_from(misbehaviour: &ProtoMisbehaviour) -> Result<Self, Self::Error> {
+        let (header_1, header_2) = match (&misbehaviour.header_1, &misbehaviour.header_2) {
+            (Some(header_1), Some(header_2)) => (header_1, header_2),
+            _ => return Err(StdError::generic_err("misbehaviour header is missing")),
+        };
+
+        Ok(Misbehaviour {
+            code_id: base64::encode(&misbehaviour.code_id),
+            client_id: misbehaviour.client_id.clone(),
+            header_1: WasmHeader::try_from(header_1)?,
+            header_2: WasmHeader::try_from(header_2)?,
+        })
+    }
+}
+
+impl TryFrom<&MerklePrefix> for ProtoMerklePrefix {
+    type Error = StdError;
+
+    fn try_from(prefix: &MerklePrefix) -> Result<Self, Self::Error> {
+        Ok(ProtoMerklePrefix {
+            key_prefix: from_base64(&prefix.key_prefix, "prefix.key_prefix")?,
+        })
+    }
+}
+
+impl TryFrom<&Counterparty> for ProtoCounterparty {
+    type Error = StdError;
+
+    fn try_from(counterparty: &Counterparty) -> Result<Self, Self::Error> {
+        Ok(ProtoCounterparty {
+            client_id: counterparty.client_id.clone(),
+            connection_id: counterparty.connection_id.clone(),
+            prefix: Some(ProtoMerklePrefix::try_from(&counterparty.prefix)?),
+        })
+    }
+}
+
+impl From<&Version> for ProtoVersion {
+    fn from(version: &Version) -> Self {
+        ProtoVersion {
+            identifier: version.identifier.clone(),
+            features: version.features.clone(),
+        }
+    }
+}
+
+impl TryFrom<&ConnectionEnd> for ProtoConnectionEnd {
+    type Error = StdError;
+
+    fn try_from(connection_end: &ConnectionEnd) -> Result<Self, Self::Error> {
+        Ok(ProtoConnectionEnd {
+            client_id: connection_end.client_id.clone(),
+            versions: connection_end.versions.iter().map(ProtoVersion::from).collect(),
+            state: connection_end.state,
+            counterparty: Some(ProtoCounterparty::try_from(&connection_end.counterparty)?),
+            delay_period: connection_end.delay_period,
+        })
+    }
+}
+
+impl From<&ChannelCounterparty> for ProtoChannelCounterparty {
+    fn from(counterparty: &ChannelCounterparty) -> Self {
+        ProtoChannelCounterparty {
+            port_id: counterparty.port_id.clone(),
+            channel_id: counterparty.channel_id.clone(),
+        }
+    }
+}
+
+impl TryFrom<&Channel> for ProtoChannel {
+    type Error = StdError;
+
+    fn try_from(channel: &Channel) -> Result<Self, Self::Error> {
+        Ok(ProtoChannel {
+            state: channel.state,
+            ordering: channel.ordering,
+            counterparty: Some(ProtoChannelCounterparty::from(&channel.counterparty)),
+            connection_hops: channel.connection_hops.clone(),
+            version: channel.version.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_client_state_as_any() {
+        let client_state = ClientState {
+            data: base64::encode(vec![1, 2, 3]),
+            code_id: base64::encode(vec![4, 5, 6]),
+            frozen: false,
+            frozen_height: None,
+            latest_height: Some(Height {
+                revision_number: 0,
+                revision_height: 10,
+            }),
+        };
+
+        let proto = ProtoClientState::try_from(&client_state).unwrap();
+        let bytes = encode_any(WASM_CLIENT_STATE_TYPE_URL, &proto).unwrap();
+
+        let decoded: ProtoClientState = decode_any(&bytes, WASM_CLIENT_STATE_TYPE_URL).unwrap();
+        assert_eq!(ClientState::from(&decoded), client_state);
+
+        // Type url has to match
+        let decoded: Result<ProtoConsensusState, _> =
+            decode_any(&bytes, WASM_CONSENSUS_STATE_TYPE_URL);
+        assert!(decoded.is_err());
+    }
+
+    #[test]
+    fn encodes_connection_and_channel_ends() {
+        // The wire bytes of ibc.core.connection.v1.ConnectionEnd and ibc.core.channel.v1.Channel
+        let connection_end = ConnectionEnd {
+            client_id: String::from("07-tendermint-0"),
+            versions: vec![Version {
+                identifier: String::from("1"),
+                features: vec![
+                    String::from("ORDER_ORDERED"),
+                    String::from("ORDER_UNORDERED"),
+                ],
+            }],
+            state: 3,
+            counterparty: Counterparty {
+                client_id: String::from("10-wasm-0"),
+                connection_id: String::from("connection-0"),
+                prefix: MerklePrefix {
+                    key_prefix: base64::encode("ibc"),
+                },
+            },
+            delay_period: 0,
+        };
+        let bytes =
+            encode_message(&ProtoConnectionEnd::try_from(&connection_end).unwrap()).unwrap();
+        assert_eq!(
+            hex::encode(bytes),
+            "0a0f30372d74656e6465726d696e742d3012230a0131120d4f524445525f4f524445524544120f4f52\
+             4445525f554e4f524445524544180322200a0931302d7761736d2d30120c636f6e6e656374696f6e\
+             2d301a050a03696263"
+        );
+
+        let channel = Channel {
+            state: 3,
+            ordering: 2,
+            counterparty: ChannelCounterparty {
+                port_id: String::from("transfer"),
+                channel_id: String::from("channel-0"),
+            },
+            connection_hops: vec![String::from("connection-0")],
+            version: String::from("ics20-1"),
+        };
+        let bytes = encode_message(&ProtoChannel::try_from(&channel).unwrap()).unwrap();
+        assert_eq!(
+            hex::encode(bytes),
+            "080310021a150a087472616e7366657212096368616e6e656c2d30220c636f6e6e656374696f6e2d\
+             302a0769637332302d31"
+        );
+    }
+
+    #[test]
+    fn decodes_stored_consensus_state() {
+        let consensus_state = ProtoConsensusState {
+            code_id: vec![1, 2, 3],
+            data: vec![4, 5, 6],
+            timestamp: 123,
+            root: Some(ProtoMerkleRoot { hash: vec![7; 32] }),
+        };
+
+        let any = encode_any(WASM_CONSENSUS_STATE_TYPE_URL, &consensus_state).unwrap();
+        assert_eq!(decode_stored_consensus_state(&any).unwrap(), consensus_state);
+
+        let mut bare = Vec::new();
+        consensus_state.encode(&mut bare).unwrap();
+        assert_eq!(decode_stored_consensus_state(&bare).unwrap(), consensus_state);
+
+        // Any of other type is not taken for the bare message
+        let any = encode_any(WASM_CLIENT_STATE_TYPE_URL, &consensus_state).unwrap();
+        let err = decode_stored_consensus_state(&any).unwrap_err();
+        assert!(err.to_string().contains("unexpected type url"));
+    }
+}
@@ -1,7 +1,11 @@
+use crate::contract::types::ibc::Height;
 use crate::errors::{Error, Kind};
+use crate::serialization::rlp::rlp_list_field_from_bytes;
 use crate::traits::{FromRlp, ToRlp, StateConfig};
+use crate::types::header::Address;
 use crate::types::state::Snapshot;
 
+use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
 use rlp_derive::{RlpEncodable, RlpDecodable};
 
 pub type LightConsensusState = Snapshot;
@@ -19,6 +23,192 @@ pub struct LightClientState {
 
     pub allow_update_after_misbehavior: bool,
     pub allow_update_after_expiry: bool,
+
+    pub commitment_scheme: CommitmentScheme,
+    pub prefix_rule: PrefixRule,
+
+    // Celo block numbers at which the consecutive revisions (1, 2, ...) start, in ascending
+    // order (ie. the L2 migration block). Empty if the chain has never been upgraded.
+    pub revision_start_heights: Vec<u64>,
+}
+
+/// CommitmentScheme defines how the IBC commitments are proven against the consensus state root
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub enum CommitmentScheme {
+    /// ICS-23 merkle proofs. The `proof_specs` describe every proof of the (multi-store) proof
+    /// chain, starting from the innermost store (ie. `[Iavl, Tendermint]` for cosmos-sdk chains)
+    Ics23 { proof_specs: Vec<ProofSpec> },
+
+    /// EVM storage proofs (`eth_getProof`) of the IBC handler contract. Commitments are expected
+    /// to be stored as `keccak256(value)` in a `bytes32 => bytes32` mapping at `commitments_slot`,
+    /// keyed by `keccak256(path)`. The connection and channel ends are committed in their protobuf
+    /// encoding. The proven paths must carry the merkle prefix of the handler (`key_prefix`, base64
+    /// encoded like `MerklePrefix.key_prefix`).
+    EvmMpt {
+        #[serde(with = "crate::serialization::bytes::hexstring")]
+        ibc_handler_address: Address,
+        commitments_slot: u64,
+        key_prefix: String,
+    },
+}
+
+/// ProofSpec identifies the ICS-23 proof spec of a single store in the proof chain
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub enum ProofSpec {
+    Iavl,
+    Tendermint,
+
+    /// Protobuf encoded `ics23.ProofSpec`, for stores with a non-standard layout
+    Custom {
+        #[serde(with = "crate::serialization::bytes::hexstring")]
+        spec: Vec<u8>,
+    },
+}
+
+/// PrefixRule defines how the merkle prefix is applied to the ICS-24 paths
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub enum PrefixRule {
+    /// The prefix provided by the relayer (counterparty connection prefix) is used
+    Counterparty,
+
+    /// The prefix provided by the relayer must be equal to the configured one
+    /// (base64 encoded, like `MerklePrefix.key_prefix`)
+    Fixed { key_prefix: String },
+
+    /// The paths are proven without a prefix
+    NoPrefix,
+}
+
+impl CommitmentScheme {
+    /// Returns the ICS-23 scheme used by the cosmos-sdk chains (IAVL store within Tendermint
+    /// multistore)
+    pub fn cosmos_sdk() -> Self {
+        CommitmentScheme::Ics23 {
+            proof_specs: vec![ProofSpec::Iavl, ProofSpec::Tendermint],
+        }
+    }
+}
+
+const COMMITMENT_SCHEME_ICS23: u8 = 0;
+const COMMITMENT_SCHEME_EVM_MPT: u8 = 1;
+
+const PROOF_SPEC_IAVL: u8 = 0;
+const PROOF_SPEC_TENDERMINT: u8 = 1;
+const PROOF_SPEC_CUSTOM: u8 = 2;
+
+const PREFIX_RULE_COUNTERPARTY: u8 = 0;
+const PREFIX_RULE_FIXED: u8 = 1;
+const PREFIX_RULE_NO_PREFIX: u8 = 2;
+
+impl Encodable for CommitmentScheme {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        match self {
+            CommitmentScheme::Ics23 { proof_specs } => {
+                s.begin_list(2);
+                s.append(&COMMITMENT_SCHEME_ICS23);
+                s.append_list::<ProofSpec, _>(proof_specs);
+            }
+            CommitmentScheme::EvmMpt {
+                ibc_handler_address,
+                commitments_slot,
+                key_prefix,
+            } => {
+                s.begin_list(4);
+                s.append(&COMMITMENT_SCHEME_EVM_MPT);
+                s.append(&ibc_handler_address.as_ref());
+                s.append(commitments_slot);
+                s.append(key_prefix);
+            }
+        }
+    }
+}
+
+impl Decodable for CommitmentScheme {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        let scheme: u8 = rlp.val_at(0)?;
+
+        match scheme {
+            COMMITMENT_SCHEME_ICS23 => Ok(CommitmentScheme::Ics23 {
+                proof_specs: rlp.list_at(1)?,
+            }),
+            COMMITMENT_SCHEME_EVM_MPT => Ok(CommitmentScheme::EvmMpt {
+                ibc_handler_address: rlp_list_field_from_bytes(rlp, 1)?,
+                commitments_slot: rlp.val_at(2)?,
+                key_prefix: rlp.val_at(3)?,
+            }),
+            _ => Err(DecoderError::Custom("unknown commitment scheme")),
+        }
+    }
+}
+
+impl Encodable for ProofSpec {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        match self {
+            ProofSpec::Iavl => {
+                s.begin_list(1);
+                s.append(&PROOF_SPEC_IAVL);
+            }
+            ProofSpec::Tendermint => {
+                s.begin_list(1);
+                s.append(&PROOF_SPEC_TENDERMINT);
+            }
+            ProofSpec::Custom { spec } => {
+                s.begin_list(2);
+                s.append(&PROOF_SPEC_CUSTOM);
+                s.append(spec);
+            }
+        }
+    }
+}
+
+impl Decodable for ProofSpec {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        let spec: u8 = rlp.val_at(0)?;
+
+        match spec {
+            PROOF_SPEC_IAVL => Ok(ProofSpec::Iavl),
+            PROOF_SPEC_TENDERMINT => Ok(ProofSpec::Tendermint),
+            PROOF_SPEC_CUSTOM => Ok(ProofSpec::Custom {
+                spec: rlp.val_at(1)?,
+            }),
+            _ => Err(DecoderError::Custom("unknown proof spec")),
+        }
+    }
+}
+
+impl Encodable for PrefixRule {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        match self {
+            PrefixRule::Counterparty => {
+                s.begin_list(1);
+                s.append(&PREFIX_RULE_COUNTERPARTY);
+            }
+            PrefixRule::Fixed { key_prefix } => {
+                s.begin_list(2);
+                s.append(&PREFIX_RULE_FIXED);
+                s.append(key_prefix);
+            }
+            PrefixRule::NoPrefix => {
+                s.begin_list(1);
+                s.append(&PREFIX_RULE_NO_PREFIX);
+            }
+        }
+    }
+}
+
+impl Decodable for PrefixRule {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        let rule: u8 = rlp.val_at(0)?;
+
+        match rule {
+            PREFIX_RULE_COUNTERPARTY => Ok(PrefixRule::Counterparty),
+            PREFIX_RULE_FIXED => Ok(PrefixRule::Fixed {
+                key_prefix: rlp.val_at(1)?,
+            }),
+            PREFIX_RULE_NO_PREFIX => Ok(PrefixRule::NoPrefix),
+            _ => Err(DecoderError::Custom("unknown prefix rule")),
+        }
+    }
 }
 
 impl ToRlp for LightClientState {
@@ -36,6 +226,178 @@ impl FromRlp for LightClientState {
     }
 }
 
+/// Versioned encoding of the contract state, that is `<version> || rlp(state)`. The layout of the
+/// state can evolve, while the states encoded by the older contracts remain decodable.
+///
+/// The legacy (unversioned) encodings are plain RLP lists, which always start with a byte >= 0xc0,
+/// so they can't be mistaken for the version byte.
+pub trait Versioned: Sized {
+    /// Version of the encoding produced by `to_versioned`
+    const VERSION: u8;
+
+    fn to_versioned(&self) -> Vec<u8>;
+    fn from_versioned(bytes: &[u8]) -> Result<Self, Error>;
+}
+
+/// Version of the legacy (unversioned) encoding
+pub const LEGACY_VERSION: u8 = 0;
+
+const RLP_LIST_OFFSET: u8 = 0xc0;
+
+// split_version returns the encoding version along with the RLP encoded state
+fn split_version(bytes: &[u8]) -> Result<(u8, &[u8]), Error> {
+    match bytes.first() {
+        Some(prefix) if *prefix >= RLP_LIST_OFFSET => Ok((LEGACY_VERSION, bytes)),
+        Some(version) => Ok((*version, &bytes[1..])),
+        None => Err(Kind::RlpDecodeError.context("empty input").into()),
+    }
+}
+
+fn with_version(version: u8, payload: Vec<u8>) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(payload.len() + 1);
+    bytes.push(version);
+    bytes.extend(payload);
+
+    bytes
+}
+
+fn unsupported_version(version: u8) -> Error {
+    Kind::RlpDecodeError
+        .context(format!("unsupported encoding version: {}", version))
+        .into()
+}
+
+/// LightClientStateV0 is the legacy (unversioned) layout of the light client state, prior to the
+/// configurable commitment scheme, prefix rule and revisions
+#[derive(RlpDecodable, RlpEncodable, Clone, PartialEq, Debug)]
+pub struct LightClientStateV0 {
+    pub epoch_size: u64,
+    pub allowed_clock_skew: u64,
+    pub trusting_period: u64,
+    pub upgrade_path: Vec<String>,
+
+    pub verify_epoch_headers: bool,
+    pub verify_non_epoch_headers: bool,
+    pub verify_header_timestamp: bool,
+
+    pub allow_update_after_misbehavior: bool,
+    pub allow_update_after_expiry: bool,
+}
+
+impl From<LightClientStateV0> for LightClientState {
+    fn from(state: LightClientStateV0) -> Self {
+        Self {
+            epoch_size: state.epoch_size,
+            allowed_clock_skew: state.allowed_clock_skew,
+            trusting_period: state.trusting_period,
+            upgrade_path: state.upgrade_path,
+
+            verify_epoch_headers: state.verify_epoch_headers,
+            verify_non_epoch_headers: state.verify_non_epoch_headers,
+            verify_header_timestamp: state.verify_header_timestamp,
+
+            allow_update_after_misbehavior: state.allow_update_after_misbehavior,
+            allow_update_after_expiry: state.allow_update_after_expiry,
+
+            // The legacy clients were proving the cosmos-sdk (IAVL) commitments only
+            commitment_scheme: CommitmentScheme::cosmos_sdk(),
+            prefix_rule: PrefixRule::Counterparty,
+            revision_start_heights: vec![],
+        }
+    }
+}
+
+impl Versioned for LightClientState {
+    const VERSION: u8 = 1;
+
+    fn to_versioned(&self) -> Vec<u8> {
+        with_version(Self::VERSION, self.to_rlp())
+    }
+
+    fn from_versioned(bytes: &[u8]) -> Result<Self, Error> {
+        let (version, payload) = split_version(bytes)?;
+
+        match version {
+            LEGACY_VERSION => rlp::decode::<LightClientStateV0>(payload)
+                .map(LightClientState::from)
+                .map_err(|err| Kind::RlpDecodeError.context(err).into()),
+            1 => LightClientState::from_rlp(payload),
+            version => Err(unsupported_version(version)),
+        }
+    }
+}
+
+// The layout of the consensus state hasn't changed since the legacy version, the version byte is
+// there to allow future changes
+impl Versioned for LightConsensusState {
+    const VERSION: u8 = 1;
+
+    fn to_versioned(&self) -> Vec<u8> {
+        with_version(Self::VERSION, self.to_rlp())
+    }
+
+    fn from_versioned(bytes: &[u8]) -> Result<Self, Error> {
+        let (version, payload) = split_version(bytes)?;
+
+        match version {
+            LEGACY_VERSION | 1 => LightConsensusState::from_rlp(payload),
+            version => Err(unsupported_version(version)),
+        }
+    }
+}
+
+impl LightClientState {
+    /// Returns a copy of the state with all client customizable fields zeroed out, so that only
+    /// the chain-specified parameters remain (ie. as committed by the chain in the upgrade store)
+    pub fn zero_custom_fields(&self) -> Self {
+        Self {
+            epoch_size: self.epoch_size,
+            upgrade_path: self.upgrade_path.clone(),
+            commitment_scheme: self.commitment_scheme.clone(),
+            prefix_rule: self.prefix_rule.clone(),
+            revision_start_heights: self.revision_start_heights.clone(),
+
+            allowed_clock_skew: 0,
+            trusting_period: 0,
+            verify_epoch_headers: false,
+            verify_non_epoch_headers: false,
+            verify_header_timestamp: false,
+            allow_update_after_misbehavior: false,
+            allow_update_after_expiry: false,
+        }
+    }
+
+    /// Maps Celo block number to the IBC height, where the revision number is the number of
+    /// revisions started at or before the block
+    pub fn celo_height(&self, block_number: u64) -> Height {
+        let revision_number = self
+            .revision_start_heights
+            .iter()
+            .take_while(|start| **start <= block_number)
+            .count();
+
+        Height {
+            revision_number: revision_number as u64,
+            revision_height: block_number,
+        }
+    }
+
+    /// Returns a copy of the state with client customizable fields taken from `other`
+    pub fn with_custom_fields(&self, other: &LightClientState) -> Self {
+        Self {
+            allowed_clock_skew: other.allowed_clock_skew,
+            trusting_period: other.trusting_period,
+            verify_epoch_headers: other.verify_epoch_headers,
+            verify_non_epoch_headers: other.verify_non_epoch_headers,
+            verify_header_timestamp: other.verify_header_timestamp,
+            allow_update_after_misbehavior: other.allow_update_after_misbehavior,
+            allow_update_after_expiry: other.allow_update_after_expiry,
+
+            ..self.clone()
+        }
+    }
+}
+
 impl StateConfig for LightClientState {
     fn epoch_size(&self) -> u64 { self.epoch_size }
     fn allowed_clock_skew(&self) -> u64 { self.allowed_clock_skew }
@@ -44,3 +406,67 @@ impl StateConfig for LightClientState {
     fn verify_non_epoch_headers(&self) -> bool { self.verify_non_epoch_headers }
     fn verify_header_timestamp(&self) -> bool { self.verify_header_timestamp }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::header::Hash;
+
+    // Light client state encoded by the legacy (unversioned) contract
+    const LEGACY_LIGHT_CLIENT_STATE: &str = "d305058203e8c887757067726164650180018001";
+
+    // Light consensus state encoded by the legacy (unversioned) contract
+    const LEGACY_LIGHT_CONSENSUS_STATE: &str =
+        "e80a64c0a01111111111111111111111111111111111111111111111111111111111111111c3808080";
+
+    #[test]
+    fn test_decode_legacy_light_client_state() {
+        let bytes = hex::decode(LEGACY_LIGHT_CLIENT_STATE).unwrap();
+        let state = LightClientState::from_versioned(&bytes).unwrap();
+
+        assert_eq!(state.epoch_size, 5);
+        assert_eq!(state.allowed_clock_skew, 5);
+        assert_eq!(state.trusting_period, 1000);
+        assert_eq!(state.upgrade_path, vec![String::from("upgrade")]);
+        assert_eq!(state.verify_epoch_headers, true);
+        assert_eq!(state.verify_non_epoch_headers, false);
+        assert_eq!(state.verify_header_timestamp, true);
+        assert_eq!(state.allow_update_after_misbehavior, false);
+        assert_eq!(state.allow_update_after_expiry, true);
+
+        // Fields introduced later on take the legacy behaviour
+        assert_eq!(state.commitment_scheme, CommitmentScheme::cosmos_sdk());
+        assert_eq!(state.prefix_rule, PrefixRule::Counterparty);
+        assert_eq!(state.revision_start_heights, Vec::<u64>::new());
+
+        // Re-encoding yields the current version
+        let encoded = state.to_versioned();
+        assert_eq!(encoded[0], LightClientState::VERSION);
+        assert_eq!(LightClientState::from_versioned(&encoded).unwrap(), state);
+    }
+
+    #[test]
+    fn test_decode_legacy_light_consensus_state() {
+        let bytes = hex::decode(LEGACY_LIGHT_CONSENSUS_STATE).unwrap();
+        let state = LightConsensusState::from_versioned(&bytes).unwrap();
+
+        assert_eq!(state.number, 10);
+        assert_eq!(state.timestamp, 100);
+        assert_eq!(state.validators.len(), 0);
+        let hash: Hash = [0x11_u8; 32];
+        assert_eq!(state.hash, hash);
+
+        let encoded = state.to_versioned();
+        assert_eq!(encoded[0], LightConsensusState::VERSION);
+        assert_eq!(LightConsensusState::from_versioned(&encoded).unwrap(), state);
+    }
+
+    #[test]
+    fn test_decode_unsupported_version() {
+        let mut bytes = LightConsensusState::new().to_versioned();
+        bytes[0] = LightConsensusState::VERSION + 1;
+
+        assert_eq!(LightConsensusState::from_versioned(&bytes).is_err(), true);
+        assert_eq!(LightClientState::from_versioned(&[]).is_err(), true);
+    }
+}
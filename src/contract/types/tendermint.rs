@@ -0,0 +1,111 @@
+use crate::contract::types::proto::{decode_any, ProtoHeight, ProtoMerkleRoot};
+use cosmwasm_std::StdError;
+use prost_derive::Message;
+
+// This file defines the counterparty (Tendermint light client) structures, as stored by ibc-go on
+// the other side of the bridge.
+//
+// ibc-go stores client and consensus states as protobuf `Any` messages, therefore the proof values
+// are the exact `Any` bytes. The structures below are used to make sure the relayer provided bytes
+// actually hold the Tendermint client / consensus state.
+
+pub const TENDERMINT_CLIENT_STATE_TYPE_URL: &str = "/ibc.lightclients.tendermint.v1.ClientState";
+pub const TENDERMINT_CONSENSUS_STATE_TYPE_URL: &str =
+    "/ibc.lightclients.tendermint.v1.ConsensusState";
+
+// Origin: google.protobuf (compiled proto)
+#[derive(Message, Clone, PartialEq)]
+pub struct Duration {
+    #[prost(int64, tag = "1")]
+    pub seconds: i64,
+    #[prost(int32, tag = "2")]
+    pub nanos: i32,
+}
+
+// Origin: google.protobuf (compiled proto)
+#[derive(Message, Clone, PartialEq)]
+pub struct Timestamp {
+    #[prost(int64, tag = "1")]
+    pub seconds: i64,
+    #[prost(int32, tag = "2")]
+    pub nanos: i32,
+}
+
+// Origin: ibc.lightclients.tendermint.v1 (compiled proto)
+#[derive(Message, Clone, PartialEq)]
+pub struct Fraction {
+    #[prost(uint64, tag = "1")]
+    pub numerator: u64,
+    #[prost(uint64, tag = "2")]
+    pub denominator: u64,
+}
+
+// Origin: ibc.lightclients.tendermint.v1 (compiled proto)
+#[derive(Message, Clone, PartialEq)]
+pub struct TendermintClientState {
+    #[prost(string, tag = "1")]
+    pub chain_id: String,
+    #[prost(message, optional, tag = "2")]
+    pub trust_level: Option<Fraction>,
+    #[prost(message, optional, tag = "3")]
+    pub trusting_period: Option<Duration>,
+    #[prost(message, optional, tag = "4")]
+    pub unbonding_period: Option<Duration>,
+    #[prost(message, optional, tag = "5")]
+    pub max_clock_drift: Option<Duration>,
+    #[prost(message, optional, tag = "6")]
+    pub frozen_height: Option<ProtoHeight>,
+    #[prost(message, optional, tag = "7")]
+    pub latest_height: Option<ProtoHeight>,
+    #[prost(message, repeated, tag = "8")]
+    pub proof_specs: Vec<ics23::ProofSpec>,
+    #[prost(string, repeated, tag = "9")]
+    pub upgrade_path: Vec<String>,
+    #[prost(bool, tag = "10")]
+    pub allow_update_after_expiry: bool,
+    #[prost(bool, tag = "11")]
+    pub allow_update_after_misbehaviour: bool,
+}
+
+// Origin: ibc.lightclients.tendermint.v1 (compiled proto)
+#[derive(Message, Clone, PartialEq)]
+pub struct TendermintConsensusState {
+    #[prost(message, optional, tag = "1")]
+    pub timestamp: Option<Timestamp>,
+    #[prost(message, optional, tag = "2")]
+    pub root: Option<ProtoMerkleRoot>,
+    #[prost(bytes = "vec", tag = "3")]
+    pub next_validators_hash: Vec<u8>,
+}
+
+// decode_client_state decodes `Any` encoded Tendermint client state
+pub fn decode_client_state(bytes: &[u8]) -> Result<TendermintClientState, StdError> {
+    let client_state: TendermintClientState = decode_any(bytes, TENDERMINT_CLIENT_STATE_TYPE_URL)?;
+
+    if client_state.chain_id.is_empty() {
+        return Err(StdError::generic_err(
+            "counterparty client state chain id cannot be empty",
+        ));
+    }
+
+    if client_state.latest_height.is_none() {
+        return Err(StdError::generic_err(
+            "counterparty client state latest height is missing",
+        ));
+    }
+
+    Ok(client_state)
+}
+
+// decode_consensus_state decodes `Any` encoded Tendermint consensus state
+pub fn decode_consensus_state(bytes: &[u8]) -> Result<TendermintConsensusState, StdError> {
+    let consensus_state: TendermintConsensusState =
+        decode_any(bytes, TENDERMINT_CONSENSUS_STATE_TYPE_URL)?;
+
+    match &consensus_state.root {
+        Some(root) if !root.hash.is_empty() => Ok(consensus_state),
+        _ => Err(StdError::generic_err(
+            "counterparty consensus state root cannot be empty",
+        )),
+    }
+}
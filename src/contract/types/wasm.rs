@@ -1,22 +1,6 @@
 use crate::contract::types::ibc::{Height, MerkleRoot};
 use serde::{Deserialize, Serialize};
 use schemars::JsonSchema;
-use prost_derive::Message;
-
-// Without the other side of the bridge (Tendermint LC on Celo)
-// we don't know how the consensus or client state will look like.
-//
-// NOTE: This is just a placeholder
-#[derive(Serialize, Deserialize, JsonSchema)]
-pub struct CosmosConsensusState {
-    pub root: MerkleRoot,
-}
-
-// NOTE: This is just a placeholder
-#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, JsonSchema)]
-pub struct CosmosClientState {
-    pub latest_height: Height,
-}
 
 #[derive(Serialize, Deserialize, Clone, PartialEq, Debug, JsonSchema)]
 pub struct ConsensusState {
@@ -26,14 +10,6 @@ pub struct ConsensusState {
     pub root: MerkleRoot,
 }
 
-#[derive(Message, Serialize, Deserialize, Clone, PartialEq)]
-pub struct PartialConsensusState {
-    #[prost(bytes = "vec", tag = "1")]
-    pub code_id: Vec<u8>,
-    #[prost(bytes = "vec", tag = "2")]
-    pub data: Vec<u8>,
-}
-
 #[derive(Serialize, Deserialize, Clone, PartialEq, Debug, JsonSchema)]
 pub struct ClientState {
     pub data: String,    // Go serializes []byte to base64 encoded string
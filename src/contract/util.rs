@@ -1,3 +1,5 @@
+use crate::contract::errors::ContractError;
+use crate::contract::types::msg::{ClientStateCallResponseResult, FailureResult};
 use byteorder::{BigEndian, ByteOrder};
 use cosmwasm_std::{attr, to_vec, Binary};
 use cosmwasm_std::{HandleResponse, StdError, StdResult};
@@ -11,7 +13,7 @@ pub fn u64_to_big_endian(value: u64) -> Vec<u8> {
     buf.to_vec()
 }
 
-pub fn wrap_response<T>(result: T, action: &'static str) -> Result<HandleResponse, StdError>
+pub fn wrap_response<T>(result: T, action: &'static str) -> Result<HandleResponse, ContractError>
 where
     T: Serialize,
 {
@@ -24,6 +26,25 @@ where
     })
 }
 
+// wrap_failure builds a response carrying the failed call result, so that the caller can tell the
+// error apart by its code. Queries return the response data only (see `to_binary`), so the code is
+// part of the serialized result (`error_code`), the attributes repeat it for the handle calls
+pub fn wrap_failure(err: &ContractError, action: &'static str) -> Result<HandleResponse, StdError> {
+    let response_data = Binary(to_vec(&FailureResult {
+        result: ClientStateCallResponseResult::failure(err),
+    })?);
+
+    Ok(HandleResponse {
+        messages: vec![],
+        attributes: vec![
+            attr("action", action),
+            attr("error_code", err.code()),
+            attr("error", err.to_string()),
+        ],
+        data: Some(response_data),
+    })
+}
+
 pub fn to_generic_err<T>(err: T) -> StdError
 where
     T: Display,
@@ -32,9 +32,24 @@ pub enum Kind {
     #[error("BLS invalid public key")]
     BlsInvalidPublicKey,
 
+    #[error("invalid merkle patricia trie proof: {msg}")]
+    InvalidProof { msg: &'static str },
+
     #[error("header verification failed: {msg}")]
     HeaderVerificationError { msg: &'static str },
 
+    #[error("storage error")]
+    StorageError,
+
+    #[error("header source error")]
+    HeaderSourceError,
+
+    #[error("invalid proof bundle: {msg}")]
+    InvalidBundle { msg: &'static str },
+
+    #[error("invalid checkpoint: {msg}")]
+    InvalidCheckpoint { msg: &'static str },
+
     #[error("unkown error occurred")]
     Unknown,
 }
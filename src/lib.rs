@@ -9,9 +9,19 @@ mod serialization;
 mod state;
 mod istanbul;
 mod bls;
+mod proof;
 mod traits;
 mod macros;
 mod errors;
+mod storage;
+mod bundle;
+mod checkpoint;
+#[cfg(feature = "sync")]
+mod sync;
+// the mocked chain backs the tests, and the mocked header source (see `sync::MockSource`)
+#[cfg(any(test, feature = "sync"))]
+#[cfg_attr(not(feature = "sync"), allow(dead_code))]
+mod mock;
 
 #[macro_use]
 extern crate serde;
@@ -30,6 +40,10 @@ pub use types::{
     header::Hash,
     istanbul::SerializedPublicKey,
     istanbul::IstanbulExtra,
+    istanbul::IstanbulExtraVanity,
+    istanbul::IstanbulAggregatedSeal,
+    proof::AccountProof,
+    proof::StorageProof,
     state::Validator,
     state::Snapshot,
     state::Config
@@ -40,17 +54,50 @@ pub use istanbul::{
     get_epoch_last_block_number,
 };
 pub use state::State;
+pub use bundle::{BundleHeader, BundleMetadata, ProofBundle, BUNDLE_VERSION};
+pub use checkpoint::Checkpoint;
 pub use errors::{Error, Kind};
 pub use traits::{
     FromBytes,
     DefaultFrom,
     ToRlp,
-    FromRlp
+    FromRlp,
+    Storage
+};
+#[cfg(feature = "sync")]
+pub use traits::HeaderSource;
+pub use storage::MemoryStorage;
+#[cfg(feature = "sled-storage")]
+pub use storage::SledStorage;
+#[cfg(feature = "wasm-contract")]
+pub use storage::CosmwasmStorage;
+#[cfg(feature = "sync")]
+pub use sync::{
+    CrossCheck,
+    CrossChecker,
+    EpochSync,
+    Evidence,
+    Fixture,
+    FollowEvent,
+    Follower,
+    JsonRpcSource,
+    MockChain,
+    MockSource,
+    ProofEntry,
+    RecordingSource,
+    ReplaySource,
+    RetryPolicy,
 };
 pub use bls::verify_aggregated_seal;
+pub use proof::{
+    verify_proof,
+    verify_account_proof,
+    verify_storage_proof,
+    keccak256,
+};
 
 #[cfg(feature = "wasm-contract")]
 pub mod contract;
 
 #[cfg(all(feature = "wasm-contract", target_arch = "wasm32"))]
-cosmwasm_std::create_entry_points!(contract);
+cosmwasm_std::create_entry_points_with_migration!(contract);
@@ -0,0 +1,203 @@
+// This module provides the deterministic Celo chain generated in memory, so that the sealed
+// headers are available to the tests regardless of the features (see `sync::MockSource` for the
+// header source on top of it).
+use crate::algebra::{bls12_377::Fr, CanonicalSerialize};
+use crate::istanbul::is_last_block_of_epoch;
+use crate::proof::keccak256;
+use crate::types::header::{Address, Hash, Header};
+use crate::types::istanbul::{
+    IstanbulAggregatedSeal, IstanbulExtra, IstanbulExtraVanity, IstanbulMsg, SerializedPublicKey,
+};
+use crate::types::state::Validator;
+use bls_crypto::{hash_to_curve::try_and_increment::DIRECT_HASH_TO_G1, PrivateKey};
+use num_bigint::BigInt as Integer;
+
+/// Timestamp of the mocked genesis block
+pub(crate) const GENESIS_TIME: u64 = 1_600_000_000;
+
+/// Number of seconds between the mocked blocks
+const BLOCK_TIME: u64 = 5;
+
+/// MockChain is the deterministic Celo chain generated in memory. Every block is sealed by all
+/// the validators of the current epoch, the validator keys are derived from their index (1, 2,
+/// ...), so that the chain can be rebuilt by the tests at will.
+pub struct MockChain {
+    epoch_size: u64,
+    headers: Vec<Header>,
+    validators: Vec<u64>,
+    pending_validators: Option<Vec<u64>>,
+    next_validator: u64,
+    forks: u64,
+}
+
+impl MockChain {
+    /// Returns the chain holding the genesis block only, that introduces the initial validators
+    pub fn new(epoch_size: u64, validators_count: u64) -> Self {
+        let mut chain = Self {
+            epoch_size,
+            headers: Vec::new(),
+            validators: Vec::new(),
+            pending_validators: None,
+            next_validator: 1,
+            forks: 0,
+        };
+
+        let validators = chain.new_validators(validators_count);
+        let mut genesis = Header::new();
+        genesis.time = GENESIS_TIME;
+        genesis.extra = to_extra(
+            to_validators(&validators),
+            Integer::from(0),
+            IstanbulAggregatedSeal::new(),
+        );
+
+        chain.headers.push(genesis);
+        chain.validators = validators;
+
+        chain
+    }
+
+    pub fn epoch_size(&self) -> u64 {
+        self.epoch_size
+    }
+
+    /// Returns the header at the given block number
+    pub fn header(&self, number: u64) -> Option<&Header> {
+        self.headers.get(number as usize)
+    }
+
+    /// Returns all the headers, starting from the genesis block
+    pub fn headers(&self) -> &[Header] {
+        &self.headers
+    }
+
+    pub fn latest(&self) -> &Header {
+        self.headers.last().expect("genesis block")
+    }
+
+    /// Returns the validators sealing the next block
+    pub fn validators(&self) -> Vec<Validator> {
+        to_validators(&self.validators)
+    }
+
+    /// Replaces the validator set with the new validators. The change is announced in the next
+    /// epoch header and applies to the blocks after it.
+    pub fn rotate_validators(&mut self, validators_count: u64) {
+        self.pending_validators = Some(self.new_validators(validators_count));
+    }
+
+    /// Appends the given number of sealed blocks to the chain
+    pub fn extend(&mut self, count: u64) {
+        for _ in 0..count {
+            let parent = self.latest();
+            let number = self.headers.len() as u64;
+
+            let mut header = Header::new();
+            header.parent_hash = parent.hash().expect("mocked header hash");
+            header.number = Integer::from(number);
+            header.time = parent.time + BLOCK_TIME;
+            header.root = keccak256(&[number.to_be_bytes(), self.forks.to_be_bytes()].concat());
+
+            let (added, removed) = match &self.pending_validators {
+                Some(validators) if is_last_block_of_epoch(number, self.epoch_size) => (
+                    to_validators(validators),
+                    (Integer::from(1) << self.validators.len()) - 1,
+                ),
+                _ => (Vec::new(), Integer::from(0)),
+            };
+
+            // the header hash doesn't cover the aggregated seal
+            header.extra = to_extra(
+                added.clone(),
+                removed.clone(),
+                IstanbulAggregatedSeal::new(),
+            );
+            let seal = self.seal(&header.hash().expect("mocked header hash"));
+            header.extra = to_extra(added, removed, seal);
+
+            if is_last_block_of_epoch(number, self.epoch_size) {
+                if let Some(validators) = self.pending_validators.take() {
+                    self.validators = validators;
+                }
+            }
+
+            self.headers.push(header);
+        }
+    }
+
+    /// Drops the blocks after the given block number, so that the chain can be extended with
+    /// the competing blocks (the fork has different block hashes). The validator set changes
+    /// are not reverted.
+    pub fn fork(&mut self, number: u64) {
+        self.headers.truncate(number as usize + 1);
+        self.forks += 1;
+    }
+
+    fn new_validators(&mut self, count: u64) -> Vec<u64> {
+        let validators = (self.next_validator..self.next_validator + count).collect();
+        self.next_validator += count;
+
+        validators
+    }
+
+    // The aggregated signature of all the validators equals the signature made with the sum of
+    // their private keys, so that every block is signed once.
+    fn seal(&self, hash: &Hash) -> IstanbulAggregatedSeal {
+        // the round 0 is encoded as empty bytes
+        let message = [&hash[..], &[IstanbulMsg::Commit as u8][..]].concat();
+        let key = PrivateKey::from(Fr::from(self.validators.iter().sum::<u64>()));
+
+        let mut signature = Vec::new();
+        key.sign(&message, &[], &*DIRECT_HASH_TO_G1)
+            .expect("mocked seal")
+            .serialize(&mut signature)
+            .expect("mocked seal serialization");
+
+        IstanbulAggregatedSeal {
+            bitmap: (Integer::from(1) << self.validators.len()) - 1,
+            signature,
+            round: Integer::from(0),
+        }
+    }
+}
+
+fn to_validators(indexes: &[u64]) -> Vec<Validator> {
+    indexes
+        .iter()
+        .map(|index| {
+            let mut bytes = Vec::new();
+            PrivateKey::from(Fr::from(*index))
+                .to_public()
+                .serialize(&mut bytes)
+                .expect("mocked public key serialization");
+
+            let mut public_key: SerializedPublicKey = [0; 96];
+            public_key.copy_from_slice(&bytes);
+
+            let mut address = Address::default();
+            address[12..].copy_from_slice(&index.to_be_bytes());
+
+            Validator {
+                address,
+                public_key,
+            }
+        })
+        .collect()
+}
+
+fn to_extra(
+    added_validators: Vec<Validator>,
+    removed_validators: Integer,
+    aggregated_seal: IstanbulAggregatedSeal,
+) -> Vec<u8> {
+    let extra = IstanbulExtra {
+        added_validators: added_validators.iter().map(|v| v.address).collect(),
+        added_validators_public_keys: added_validators.iter().map(|v| v.public_key).collect(),
+        removed_validators,
+        seal: Vec::new(),
+        aggregated_seal,
+        parent_aggregated_seal: IstanbulAggregatedSeal::new(),
+    };
+
+    extra.to_rlp(&IstanbulExtraVanity::default())
+}
@@ -0,0 +1,276 @@
+use crate::errors::{Error, Kind};
+use crate::slice_as_array_ref;
+use crate::types::header::{Address, Hash, HASH_LENGTH};
+use rlp::Rlp;
+use sha3::{Digest, Keccak256};
+
+/// Verifies Merkle Patricia Trie proof (as returned by `eth_getProof`) of the `key` against the
+/// trie `root`. The key is hashed (secure trie), so raw account address or storage slot is expected.
+///
+/// Returns the value stored under the key, or `None` if the proof shows the key is absent.
+pub fn verify_proof(root: &Hash, key: &[u8], proof: &[Vec<u8>]) -> Result<Option<Vec<u8>>, Error> {
+    let path = to_nibbles(&keccak256(key));
+    let mut offset = 0;
+    let mut proof_nodes = proof.iter();
+
+    // Reference to the next node: either 32-byte hash or an inlined node (shorter than 32 bytes)
+    let mut reference: Vec<u8> = root.to_vec();
+
+    loop {
+        let node = if reference.len() == HASH_LENGTH {
+            let node = proof_nodes
+                .next()
+                .ok_or_else(|| invalid_proof("proof is missing trie nodes"))?;
+
+            if keccak256(node)[..] != reference[..] {
+                return Err(invalid_proof("trie node hash mismatch"));
+            }
+
+            node.clone()
+        } else {
+            reference.clone()
+        };
+
+        let rlp = Rlp::new(&node);
+        if !rlp.is_list() {
+            // Empty trie
+            if rlp.is_empty() {
+                return Ok(None);
+            }
+
+            return Err(invalid_proof("trie node is expected to be a list"));
+        }
+
+        match rlp.item_count().map_err(rlp_error)? {
+            // Branch node
+            17 => {
+                if offset == path.len() {
+                    let value = rlp.at(16).and_then(|v| v.data().map(|d| d.to_vec()));
+                    let value = value.map_err(rlp_error)?;
+
+                    return Ok(if value.is_empty() { None } else { Some(value) });
+                }
+
+                let child = rlp.at(path[offset] as usize).map_err(rlp_error)?;
+                offset += 1;
+
+                match child_reference(&child)? {
+                    Some(child) => reference = child,
+                    None => return Ok(None),
+                }
+            }
+
+            // Extension or leaf node
+            2 => {
+                let encoded_path = rlp.at(0).and_then(|p| p.data().map(|d| d.to_vec()));
+                let (node_path, is_leaf) = decode_compact(&encoded_path.map_err(rlp_error)?)?;
+                let remaining = &path[offset..];
+
+                if is_leaf {
+                    if remaining != &node_path[..] {
+                        return Ok(None);
+                    }
+
+                    let value = rlp.at(1).and_then(|v| v.data().map(|d| d.to_vec()));
+                    return Ok(Some(value.map_err(rlp_error)?));
+                }
+
+                if !remaining.starts_with(&node_path) {
+                    return Ok(None);
+                }
+                offset += node_path.len();
+
+                let child = rlp.at(1).map_err(rlp_error)?;
+                match child_reference(&child)? {
+                    Some(child) => reference = child,
+                    None => return Err(invalid_proof("extension node has no child")),
+                }
+            }
+
+            _ => return Err(invalid_proof("unexpected number of trie node items")),
+        }
+    }
+}
+
+/// Verifies the account proof against the state root and returns the account storage root,
+/// or `None` if the proof shows that the account doesn't exist.
+pub fn verify_account_proof(
+    state_root: &Hash,
+    address: &Address,
+    proof: &[Vec<u8>],
+) -> Result<Option<Hash>, Error> {
+    match verify_proof(state_root, address, proof)? {
+        Some(account) => {
+            // Account is encoded as: [nonce, balance, storage_root, code_hash]
+            let rlp = Rlp::new(&account);
+            let storage_root = rlp
+                .at(2)
+                .and_then(|r| r.data().map(|d| d.to_vec()))
+                .map_err(rlp_error)?;
+
+            Ok(Some(
+                slice_as_array_ref!(&storage_root[..], HASH_LENGTH)?.to_owned(),
+            ))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Verifies the storage proof against the account storage root and returns the value stored
+/// in the slot (without leading zeros), or `None` if the slot is empty.
+pub fn verify_storage_proof(
+    storage_root: &Hash,
+    slot: &Hash,
+    proof: &[Vec<u8>],
+) -> Result<Option<Vec<u8>>, Error> {
+    match verify_proof(storage_root, slot, proof)? {
+        Some(value) => {
+            let value = Rlp::new(&value).data().map_err(rlp_error)?.to_vec();
+
+            Ok(Some(value))
+        }
+        None => Ok(None),
+    }
+}
+
+pub fn keccak256(data: &[u8]) -> Hash {
+    let digest = Keccak256::digest(data);
+
+    let mut hash = Hash::default();
+    hash.copy_from_slice(&digest[..HASH_LENGTH]);
+
+    hash
+}
+
+fn child_reference(child: &Rlp) -> Result<Option<Vec<u8>>, Error> {
+    if child.is_list() {
+        return Ok(Some(child.as_raw().to_vec()));
+    }
+
+    let data = child.data().map_err(rlp_error)?;
+    match data.len() {
+        0 => Ok(None),
+        HASH_LENGTH => Ok(Some(data.to_vec())),
+        _ => Err(invalid_proof("invalid trie node reference")),
+    }
+}
+
+// Decodes the hex-prefix encoded path. Returns the nibbles and whether the node is a leaf.
+fn decode_compact(encoded: &[u8]) -> Result<(Vec<u8>, bool), Error> {
+    if encoded.is_empty() {
+        return Err(invalid_proof("empty trie node path"));
+    }
+
+    let nibbles = to_nibbles(encoded);
+    let flag = nibbles[0];
+    if flag > 3 {
+        return Err(invalid_proof("invalid trie node path flag"));
+    }
+
+    let is_leaf = flag >= 2;
+    let is_odd = flag & 1 == 1;
+    let skip = if is_odd { 1 } else { 2 };
+
+    Ok((nibbles[skip..].to_vec(), is_leaf))
+}
+
+fn to_nibbles(bytes: &[u8]) -> Vec<u8> {
+    bytes.iter().flat_map(|b| vec![b >> 4, b & 0x0f]).collect()
+}
+
+fn invalid_proof(msg: &'static str) -> Error {
+    Kind::InvalidProof { msg }.into()
+}
+
+fn rlp_error(err: rlp::DecoderError) -> Error {
+    Kind::RlpDecodeError.context(err).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rlp::RlpStream;
+
+    fn leaf(path: &[u8], value: &[u8]) -> Vec<u8> {
+        let mut s = RlpStream::new_list(2);
+        s.append(&compact(path, true));
+        s.append(&value);
+        s.out()
+    }
+
+    fn compact(nibbles: &[u8], is_leaf: bool) -> Vec<u8> {
+        let flag = if is_leaf { 2 } else { 0 };
+        let mut out = Vec::new();
+
+        let rest = if nibbles.len() % 2 == 1 {
+            out.push(((flag + 1) << 4) | nibbles[0]);
+            &nibbles[1..]
+        } else {
+            out.push(flag << 4);
+            nibbles
+        };
+
+        for pair in rest.chunks(2) {
+            out.push((pair[0] << 4) | pair[1]);
+        }
+
+        out
+    }
+
+    fn branch(children: Vec<(u8, Hash)>) -> Vec<u8> {
+        let mut s = RlpStream::new_list(17);
+        for i in 0..16 {
+            match children.iter().find(|(nibble, _)| *nibble == i) {
+                Some((_, hash)) => s.append(&hash.as_ref()),
+                None => s.append_empty_data(),
+            };
+        }
+        s.append_empty_data();
+        s.out()
+    }
+
+    #[test]
+    fn verifies_single_leaf_trie() {
+        let key = b"key";
+        let path = to_nibbles(&keccak256(key));
+        let node = leaf(&path, b"value");
+        let root = keccak256(&node);
+
+        assert_eq!(
+            verify_proof(&root, key, &[node.clone()]).unwrap(),
+            Some(b"value".to_vec())
+        );
+
+        // Absent key diverges at the leaf
+        assert_eq!(verify_proof(&root, b"other", &[node.clone()]).unwrap(), None);
+
+        // Tampered value
+        let tampered = leaf(&path, b"eulav");
+        assert!(verify_proof(&root, key, &[tampered]).is_err());
+    }
+
+    #[test]
+    fn verifies_branch_trie() {
+        let key = b"key";
+        let path = to_nibbles(&keccak256(key));
+        let leaf_node = leaf(&path[1..], b"value");
+        let branch_node = branch(vec![(path[0], keccak256(&leaf_node))]);
+        let root = keccak256(&branch_node);
+
+        assert_eq!(
+            verify_proof(&root, key, &[branch_node.clone(), leaf_node.clone()]).unwrap(),
+            Some(b"value".to_vec())
+        );
+
+        // Missing nodes
+        assert!(verify_proof(&root, key, &[branch_node.clone()]).is_err());
+    }
+
+    #[test]
+    fn verifies_empty_trie() {
+        let empty = vec![0x80];
+        let root = keccak256(&empty);
+
+        assert_eq!(verify_proof(&root, b"key", &[empty]).unwrap(), None);
+    }
+}
@@ -148,3 +148,43 @@ pub(crate) mod hexvec {
             .serialize(serializer)
     }
 }
+
+pub(crate) mod hexbytesvec {
+    use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
+    /// Deserialize vector of hex strings into Vec<Vec<u8>>
+    pub(crate) fn deserialize<'de, D>(deserializer: D) -> Result<Vec<Vec<u8>>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let items: Vec<String> = Deserialize::deserialize(deserializer)?;
+
+        items
+            .iter()
+            .map(|item| {
+                if !item.starts_with("0x") {
+                    return Err(D::Error::custom(format!(
+                        "hex string should start with '0x', got: {}",
+                        item
+                    )));
+                }
+
+                hex::decode(&item[2..]).map_err(|e| {
+                    D::Error::custom(format!("failed to decode hex data, got: {}", e))
+                })
+            })
+            .collect()
+    }
+
+    /// Serialize from &[Vec<u8>] into vector of strings
+    pub(crate) fn serialize<S>(value: &[Vec<u8>], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        value
+            .iter()
+            .map(|v| format!("0x{}", hex::encode(v)))
+            .collect::<Vec<String>>()
+            .serialize(serializer)
+    }
+}
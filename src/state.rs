@@ -1,7 +1,8 @@
 use crate::bls::verify_aggregated_seal;
+use crate::checkpoint::Checkpoint;
 use crate::errors::{Error, Kind};
 use crate::istanbul::is_last_block_of_epoch;
-use crate::traits::StateConfig;
+use crate::traits::{StateConfig, Storage};
 use crate::types::header::{Address, Header};
 use crate::types::istanbul::IstanbulExtra;
 use crate::types::state::{Snapshot, Validator};
@@ -21,10 +22,38 @@ impl<'a> State<'a> {
         State { snapshot, config }
     }
 
+    /// Restores the state from the latest snapshot in the storage (if any)
+    pub fn from_storage(
+        storage: &dyn Storage,
+        config: &'a dyn StateConfig,
+    ) -> Result<Option<Self>, Error> {
+        Ok(storage
+            .get_latest_snapshot()?
+            .map(|snapshot| State::new(snapshot, config)))
+    }
+
+    /// Starts the state from the trusted checkpoint, once verified (see `Checkpoint::verify`)
+    pub fn from_checkpoint(
+        checkpoint: &Checkpoint,
+        config: &'a dyn StateConfig,
+        current_timestamp: u64,
+    ) -> Result<Self, Error> {
+        Ok(State::new(checkpoint.verify(config, current_timestamp)?, config))
+    }
+
     pub fn snapshot(&self) -> &Snapshot {
         &self.snapshot
     }
 
+    pub fn epoch_size(&self) -> u64 {
+        self.config.epoch_size()
+    }
+
+    /// Persists the current snapshot in the storage
+    pub fn persist(&self, storage: &mut dyn Storage) -> Result<(), Error> {
+        storage.put_snapshot(&self.snapshot)
+    }
+
     pub fn add_validators(&mut self, validators: Vec<Validator>) -> bool {
         let mut new_address_map: HashMap<Address, bool> = HashMap::new();
 
@@ -0,0 +1,126 @@
+use crate::errors::{Error, Kind};
+use crate::traits::{FromRlp, Storage, ToRlp};
+use crate::types::state::Snapshot;
+use cosmwasm_std::Order;
+use std::convert::TryInto;
+
+const SNAPSHOT_KEY_PREFIX: &[u8] = b"snapshot/";
+const LATEST_KEY: &[u8] = b"latestSnapshot";
+
+/// CosmwasmStorage keeps the snapshots in the contract storage (RLP encoded, keyed by big endian
+/// block number). All the keys are prefixed with the given prefix (ie. `subject/`), so that the
+/// snapshots of different clients can share the store.
+pub struct CosmwasmStorage<'a> {
+    storage: &'a mut dyn cosmwasm_std::Storage,
+    prefix: Vec<u8>,
+}
+
+impl<'a> CosmwasmStorage<'a> {
+    pub fn new(storage: &'a mut dyn cosmwasm_std::Storage, prefix: &str) -> Self {
+        Self {
+            storage,
+            prefix: prefix.as_bytes().to_vec(),
+        }
+    }
+
+    fn snapshot_key(&self, number: u64) -> Vec<u8> {
+        [&self.prefix, SNAPSHOT_KEY_PREFIX, &number.to_be_bytes()[..]].concat()
+    }
+
+    fn latest_key(&self) -> Vec<u8> {
+        [&self.prefix, LATEST_KEY].concat()
+    }
+}
+
+impl<'a> Storage for CosmwasmStorage<'a> {
+    fn get_snapshot(&self, number: u64) -> Result<Option<Snapshot>, Error> {
+        match self.storage.get(&self.snapshot_key(number)) {
+            Some(bytes) => Ok(Some(Snapshot::from_rlp(&bytes)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn put_snapshot(&mut self, snapshot: &Snapshot) -> Result<(), Error> {
+        let key = self.snapshot_key(snapshot.number);
+        self.storage.set(&key, &snapshot.to_rlp());
+
+        if self
+            .get_latest_number()?
+            .map_or(true, |latest| latest < snapshot.number)
+        {
+            let key = self.latest_key();
+            self.storage.set(&key, &snapshot.number.to_be_bytes());
+        }
+
+        Ok(())
+    }
+
+    fn get_latest_number(&self) -> Result<Option<u64>, Error> {
+        match self.storage.get(&self.latest_key()) {
+            Some(bytes) => Ok(Some(to_number(&bytes)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn prune(&mut self, before: u64, limit: usize) -> Result<usize, Error> {
+        let latest = self.get_latest_number()?;
+        let start = self.snapshot_key(0);
+        let end = self.snapshot_key(before);
+        let key_offset = start.len() - 8;
+
+        let mut keys: Vec<Vec<u8>> = Vec::new();
+        for (key, _) in self
+            .storage
+            .range(Some(&start), Some(&end), Order::Ascending)
+        {
+            if keys.len() >= limit {
+                break;
+            }
+
+            if Some(to_number(&key[key_offset..])?) != latest {
+                keys.push(key);
+            }
+        }
+
+        for key in keys.iter() {
+            self.storage.remove(key);
+        }
+
+        Ok(keys.len())
+    }
+}
+
+fn to_number(bytes: &[u8]) -> Result<u64, Error> {
+    let bytes: [u8; 8] = bytes.try_into().map_err(|_| {
+        Error::from(Kind::InvalidDataLength {
+            current: bytes.len(),
+            expected: 8,
+        })
+    })?;
+
+    Ok(u64::from_be_bytes(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::tests::{check_storage, new_snapshot};
+    use cosmwasm_std::testing::MockStorage;
+
+    #[test]
+    fn test_cosmwasm_storage() {
+        let mut store = MockStorage::new();
+        check_storage(&mut CosmwasmStorage::new(&mut store, ""));
+    }
+
+    #[test]
+    fn test_cosmwasm_storage_prefix() {
+        let mut store = MockStorage::new();
+        CosmwasmStorage::new(&mut store, "subject/")
+            .put_snapshot(&new_snapshot(10))
+            .unwrap();
+
+        let storage = CosmwasmStorage::new(&mut store, "substitute/");
+        assert_eq!(storage.get_latest_number().unwrap(), None);
+    }
+}
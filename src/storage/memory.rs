@@ -0,0 +1,65 @@
+use crate::errors::Error;
+use crate::traits::Storage;
+use crate::types::state::Snapshot;
+use std::collections::BTreeMap;
+
+/// MemoryStorage keeps the snapshots in memory
+#[derive(Clone, Debug, Default)]
+pub struct MemoryStorage {
+    snapshots: BTreeMap<u64, Snapshot>,
+    latest: Option<u64>,
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Storage for MemoryStorage {
+    fn get_snapshot(&self, number: u64) -> Result<Option<Snapshot>, Error> {
+        Ok(self.snapshots.get(&number).cloned())
+    }
+
+    fn put_snapshot(&mut self, snapshot: &Snapshot) -> Result<(), Error> {
+        self.snapshots.insert(snapshot.number, snapshot.clone());
+
+        if self.latest.map_or(true, |latest| latest < snapshot.number) {
+            self.latest = Some(snapshot.number);
+        }
+
+        Ok(())
+    }
+
+    fn get_latest_number(&self) -> Result<Option<u64>, Error> {
+        Ok(self.latest)
+    }
+
+    fn prune(&mut self, before: u64, limit: usize) -> Result<usize, Error> {
+        let latest = self.latest;
+        let numbers: Vec<u64> = self
+            .snapshots
+            .range(..before)
+            .map(|(number, _)| *number)
+            .filter(|number| Some(*number) != latest)
+            .take(limit)
+            .collect();
+
+        for number in numbers.iter() {
+            self.snapshots.remove(number);
+        }
+
+        Ok(numbers.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::tests::check_storage;
+
+    #[test]
+    fn test_memory_storage() {
+        check_storage(&mut MemoryStorage::new());
+    }
+}
@@ -0,0 +1,60 @@
+// This module provides the `Storage` implementations, so that the snapshots are persisted the same
+// way by every light client user:
+//  * memory - for the tests and short-lived processes
+//  * sled - for the long running processes (ie. sync daemon), gated by `sled-storage` feature
+//  * cosmwasm - for the contract, gated by `wasm-contract` feature. The contract keeps the verified
+//    snapshots next to the consensus states, which are laid out by the host (see `contract::store`)
+mod memory;
+pub use memory::MemoryStorage;
+
+#[cfg(feature = "sled-storage")]
+mod sled_db;
+#[cfg(feature = "sled-storage")]
+pub use sled_db::SledStorage;
+
+#[cfg(feature = "wasm-contract")]
+mod cosmwasm;
+#[cfg(feature = "wasm-contract")]
+pub use cosmwasm::CosmwasmStorage;
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use crate::traits::Storage;
+    use crate::types::state::Snapshot;
+
+    pub fn new_snapshot(number: u64) -> Snapshot {
+        let mut snapshot = Snapshot::new();
+        snapshot.number = number;
+        snapshot.timestamp = number * 5;
+
+        snapshot
+    }
+
+    // check_storage runs the common scenario against the storage implementation
+    pub fn check_storage(storage: &mut dyn Storage) {
+        assert_eq!(storage.get_latest_snapshot().unwrap(), None);
+        assert_eq!(storage.get_snapshot(10).unwrap(), None);
+
+        for number in [10, 20, 30].iter() {
+            storage.put_snapshot(&new_snapshot(*number)).unwrap();
+        }
+        assert_eq!(storage.get_latest_number().unwrap(), Some(30));
+        assert_eq!(storage.get_snapshot(20).unwrap(), Some(new_snapshot(20)));
+        assert_eq!(storage.get_epoch_snapshot(1, 10).unwrap(), Some(new_snapshot(10)));
+
+        // Older snapshot doesn't move the latest pointer
+        storage.put_snapshot(&new_snapshot(25)).unwrap();
+        assert_eq!(storage.get_latest_snapshot().unwrap(), Some(new_snapshot(30)));
+
+        // Pruning is bounded by the limit
+        assert_eq!(storage.prune(30, 2).unwrap(), 2);
+        assert_eq!(storage.get_snapshot(10).unwrap(), None);
+        assert_eq!(storage.get_snapshot(20).unwrap(), None);
+        assert_eq!(storage.get_snapshot(25).unwrap(), Some(new_snapshot(25)));
+
+        // The latest snapshot is always kept
+        assert_eq!(storage.prune(100, 10).unwrap(), 1);
+        assert_eq!(storage.get_snapshot(25).unwrap(), None);
+        assert_eq!(storage.get_latest_snapshot().unwrap(), Some(new_snapshot(30)));
+    }
+}
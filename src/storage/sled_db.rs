@@ -0,0 +1,141 @@
+use crate::errors::{Error, Kind};
+use crate::traits::{FromRlp, Storage, ToRlp};
+use crate::types::state::Snapshot;
+use std::convert::TryInto;
+use std::path::Path;
+
+const SNAPSHOTS_TREE: &[u8] = b"snapshots";
+const LATEST_KEY: &[u8] = b"latestSnapshot";
+
+/// SledStorage keeps the snapshots in the sled database (RLP encoded, keyed by big endian block
+/// number). Every write is flushed to disk, so that the process can be safely resumed.
+///
+/// The storage only uses the `snapshots` tree and `latestSnapshot` key of the database, therefore
+/// the database can be shared with the other components.
+#[derive(Clone)]
+pub struct SledStorage {
+    db: sled::Db,
+    snapshots: sled::Tree,
+}
+
+impl SledStorage {
+    pub fn new(db: sled::Db) -> Result<Self, Error> {
+        let snapshots = db.open_tree(SNAPSHOTS_TREE).map_err(to_storage_err)?;
+
+        Ok(Self { db, snapshots })
+    }
+
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        Self::new(sled::open(path).map_err(to_storage_err)?)
+    }
+
+    /// Returns the underlying database
+    pub fn db(&self) -> &sled::Db {
+        &self.db
+    }
+}
+
+impl Storage for SledStorage {
+    fn get_snapshot(&self, number: u64) -> Result<Option<Snapshot>, Error> {
+        match self
+            .snapshots
+            .get(number.to_be_bytes())
+            .map_err(to_storage_err)?
+        {
+            Some(bytes) => Ok(Some(Snapshot::from_rlp(&bytes)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn put_snapshot(&mut self, snapshot: &Snapshot) -> Result<(), Error> {
+        self.snapshots
+            .insert(snapshot.number.to_be_bytes(), snapshot.to_rlp())
+            .map_err(to_storage_err)?;
+
+        if self
+            .get_latest_number()?
+            .map_or(true, |latest| latest < snapshot.number)
+        {
+            self.db
+                .insert(LATEST_KEY, &snapshot.number.to_be_bytes())
+                .map_err(to_storage_err)?;
+        }
+
+        self.db.flush().map_err(to_storage_err)?;
+
+        Ok(())
+    }
+
+    fn get_latest_number(&self) -> Result<Option<u64>, Error> {
+        match self.db.get(LATEST_KEY).map_err(to_storage_err)? {
+            Some(bytes) => Ok(Some(to_number(&bytes)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn prune(&mut self, before: u64, limit: usize) -> Result<usize, Error> {
+        let latest = self.get_latest_number()?;
+
+        let mut numbers: Vec<u64> = Vec::new();
+        for item in self.snapshots.range(..before.to_be_bytes()) {
+            if numbers.len() >= limit {
+                break;
+            }
+
+            let (key, _) = item.map_err(to_storage_err)?;
+            let number = to_number(&key)?;
+            if Some(number) != latest {
+                numbers.push(number);
+            }
+        }
+
+        for number in numbers.iter() {
+            self.snapshots
+                .remove(number.to_be_bytes())
+                .map_err(to_storage_err)?;
+        }
+        self.db.flush().map_err(to_storage_err)?;
+
+        Ok(numbers.len())
+    }
+}
+
+fn to_number(bytes: &[u8]) -> Result<u64, Error> {
+    let bytes: [u8; 8] = bytes.try_into().map_err(|_| {
+        Error::from(Kind::InvalidDataLength {
+            current: bytes.len(),
+            expected: 8,
+        })
+    })?;
+
+    Ok(u64::from_be_bytes(bytes))
+}
+
+fn to_storage_err(err: sled::Error) -> Error {
+    Kind::StorageError.context(err).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::tests::check_storage;
+
+    #[test]
+    fn test_sled_storage() {
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        check_storage(&mut SledStorage::new(db).unwrap());
+    }
+
+    #[test]
+    fn test_sled_storage_resume() {
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        let mut storage = SledStorage::new(db.clone()).unwrap();
+        storage
+            .put_snapshot(&crate::storage::tests::new_snapshot(10))
+            .unwrap();
+
+        // The storage reopened on the same database sees the latest snapshot
+        let storage = SledStorage::new(db).unwrap();
+        assert_eq!(storage.get_latest_number().unwrap(), Some(10));
+    }
+}
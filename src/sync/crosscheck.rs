@@ -0,0 +1,277 @@
+use crate::errors::{Error, Kind};
+use crate::state::State;
+use crate::sync::retry::RetryPolicy;
+use crate::traits::HeaderSource;
+use crate::types::header::{Hash, Header};
+use futures::future::join_all;
+use num::cast::ToPrimitive;
+
+#[cfg(feature = "wasm-contract")]
+use crate::contract::payload::wasm_header;
+#[cfg(feature = "wasm-contract")]
+use crate::contract::serialization::from_base64_versioned;
+#[cfg(feature = "wasm-contract")]
+use crate::contract::types::{
+    msg::HandleMsg,
+    state::LightClientState,
+    wasm::{ClientState, ConsensusState, Misbehaviour},
+};
+#[cfg(feature = "wasm-contract")]
+use cosmwasm_std::StdError;
+
+/// Evidence is the proof of the validators misbehaviour: two different headers at the same
+/// height, both sealed by the quorum of the validator set
+#[derive(Clone, PartialEq, Debug)]
+pub struct Evidence {
+    pub header_1: Header,
+    pub header_2: Header,
+}
+
+/// CrossCheck is the outcome of comparing the headers served by the sources at the same height
+#[derive(Clone, PartialEq, Debug)]
+pub enum CrossCheck {
+    /// The validly sealed header. The sources that served a different header (without a valid
+    /// seal) are listed as faulty, by their index.
+    Header { header: Header, faulty: Vec<usize> },
+
+    /// The sources served conflicting headers, both validly sealed
+    Misbehaviour(Evidence),
+}
+
+/// CrossChecker queries the same header from several sources (ie. independent RPC endpoints),
+/// so that a single endpoint can't feed the light client with a fork unnoticed.
+///
+/// Whenever the sources disagree on the header hash, all the served headers are verified
+/// against the validator set of the state. A header without a valid seal marks its source as
+/// faulty, while two validly sealed headers are an evidence of the validators misbehaviour, to
+/// be submitted to the light client contract.
+pub struct CrossChecker<'a> {
+    sources: Vec<&'a dyn HeaderSource>,
+    retry: RetryPolicy,
+}
+
+impl<'a> CrossChecker<'a> {
+    pub fn new(sources: Vec<&'a dyn HeaderSource>) -> Self {
+        Self {
+            sources,
+            retry: RetryPolicy::default(),
+        }
+    }
+
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Fetches the header at the given height from all the sources (concurrently) and compares
+    /// them. The unavailable sources are skipped, unless none of the sources served the header.
+    pub async fn check(&self, state: &State<'_>, number: u64) -> Result<CrossCheck, Error> {
+        let responses = join_all(self.sources.iter().map(|source| {
+            let source = *source;
+            self.retry.run(move || source.header_by_number(number))
+        }))
+        .await;
+
+        // headers grouped by hash, along with the sources that served them
+        let mut headers: Vec<(Hash, Header, Vec<usize>)> = Vec::new();
+        let mut faulty = Vec::new();
+        let mut last_err = None;
+        for (i, response) in responses.into_iter().enumerate() {
+            let header = match response {
+                Ok(header) => header,
+                Err(e) => {
+                    last_err = Some(e);
+                    continue;
+                }
+            };
+
+            // the header of another height is a fault of the source
+            if header.number.to_u64() != Some(number) {
+                faulty.push(i);
+                continue;
+            }
+
+            let hash = header.hash()?;
+            match headers.iter_mut().find(|(h, _, _)| *h == hash) {
+                Some((_, _, served_by)) => served_by.push(i),
+                None => headers.push((hash, header, vec![i])),
+            }
+        }
+
+        if headers.is_empty() {
+            return Err(last_err.unwrap_or_else(|| {
+                Kind::HeaderVerificationError {
+                    msg: "none of the sources served the header",
+                }
+                .into()
+            }));
+        }
+
+        // the single header still has to be verified, to tell whether the source is faulty
+        let (mut valid, invalid): (Vec<_>, Vec<_>) = headers
+            .into_iter()
+            .partition(|(_, header, _)| state.verify_header_seal(header).is_ok());
+
+        for (_, _, served_by) in invalid.into_iter() {
+            faulty.extend(served_by);
+        }
+        faulty.sort_unstable();
+
+        match valid.len() {
+            0 => Err(Kind::HeaderVerificationError {
+                msg: "none of the served headers is validly sealed",
+            }
+            .into()),
+            1 => Ok(CrossCheck::Header {
+                header: valid.remove(0).1,
+                faulty,
+            }),
+            _ => Ok(CrossCheck::Misbehaviour(Evidence {
+                header_1: valid.remove(0).1,
+                header_2: valid.remove(0).1,
+            })),
+        }
+    }
+}
+
+#[cfg(feature = "wasm-contract")]
+impl Evidence {
+    /// Returns the `CheckMisbehaviourAndUpdateState` message, ready to be submitted to the
+    /// contract. Both headers are verified against the same trusted consensus state, that is the
+    /// one of the epoch the headers were sealed in.
+    pub fn to_handle_msg(
+        &self,
+        client_id: &str,
+        me: ClientState,
+        consensus_state: ConsensusState,
+    ) -> Result<HandleMsg, StdError> {
+        let light_client_state: LightClientState =
+            from_base64_versioned(&me.data, "msg.light_client_state")?;
+
+        Ok(HandleMsg::CheckMisbehaviourAndUpdateState {
+            misbehaviour: Misbehaviour {
+                code_id: me.code_id.clone(),
+                client_id: client_id.to_string(),
+                header_1: wasm_header(&self.header_1, &light_client_state),
+                header_2: wasm_header(&self.header_2, &light_client_state),
+            },
+            me,
+            consensus_state_1: consensus_state.clone(),
+            consensus_state_2: consensus_state,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sync::mock::{MockChain, MockSource};
+    use crate::sync::replay::{Fixture, ReplaySource};
+    use crate::types::state::{Config, Snapshot};
+
+    fn config() -> Config {
+        Config {
+            epoch_size: 5,
+            allowed_clock_skew: 5,
+            verify_epoch_headers: true,
+            verify_non_epoch_headers: true,
+            verify_header_timestamp: true,
+        }
+    }
+
+    fn chain() -> MockChain {
+        let mut chain = MockChain::new(5, 4);
+        chain.extend(7);
+
+        chain
+    }
+
+    async fn state<'a>(source: &dyn HeaderSource, config: &'a Config) -> State<'a> {
+        let mut state = State::new(Snapshot::new(), config);
+        for number in [0, 5].iter() {
+            let header = source.header_by_number(*number).await.unwrap();
+            state.insert_header(&header, u64::MAX / 2).unwrap();
+        }
+
+        state
+    }
+
+    #[tokio::test]
+    async fn test_cross_check_agreement() {
+        let (source_1, source_2) = (MockSource::new(chain()), MockSource::new(chain()));
+        let config = config();
+        let state = state(&source_1, &config).await;
+
+        let result = CrossChecker::new(vec![&source_1, &source_2])
+            .check(&state, 7)
+            .await
+            .unwrap();
+
+        let header = source_1.header_by_number(7).await.unwrap();
+        assert_eq!(
+            result,
+            CrossCheck::Header {
+                header,
+                faulty: vec![]
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cross_check_faulty_source() {
+        let source_1 = MockSource::new(chain());
+        let config = config();
+        let state = state(&source_1, &config).await;
+
+        // the second source serves the tampered header
+        let header = source_1.header_by_number(7).await.unwrap();
+        let mut tampered = header.clone();
+        tampered.gas_used += 1;
+        let source_2 = ReplaySource::new(Fixture {
+            latest: None,
+            headers: vec![tampered],
+            proofs: vec![],
+        });
+
+        let result = CrossChecker::new(vec![&source_1, &source_2, &source_1])
+            .check(&state, 7)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            result,
+            CrossCheck::Header {
+                header,
+                faulty: vec![1]
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cross_check_misbehaviour() {
+        let (source_1, source_2) = (MockSource::new(chain()), MockSource::new(chain()));
+        let config = config();
+        let state = state(&source_1, &config).await;
+
+        // the validators seal the competing block at the same height
+        {
+            let chain = source_2.chain();
+            let mut chain = chain.write().unwrap();
+            chain.fork(6);
+            chain.extend(1);
+        }
+
+        let result = CrossChecker::new(vec![&source_1, &source_2])
+            .check(&state, 7)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            result,
+            CrossCheck::Misbehaviour(Evidence {
+                header_1: source_1.header_by_number(7).await.unwrap(),
+                header_2: source_2.header_by_number(7).await.unwrap(),
+            })
+        );
+    }
+}
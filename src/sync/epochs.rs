@@ -0,0 +1,240 @@
+use crate::errors::Error;
+use crate::istanbul::get_epoch_last_block_number;
+use crate::state::State;
+use crate::sync::retry::RetryPolicy;
+use crate::sync::to_source_err;
+use crate::traits::{HeaderSource, Storage};
+use crate::types::header::Header;
+use crate::types::state::Snapshot;
+use futures::stream::{self, StreamExt};
+use num::cast::ToPrimitive;
+
+/// Default number of epoch headers fetched concurrently
+const DEFAULT_WINDOW: usize = 8;
+
+/// EpochSync builds up the validator set by applying the epoch headers (the last header of each
+/// epoch) fetched from the header source.
+///
+/// Up to `window` epoch headers are fetched concurrently (each fetch is retried according to
+/// the retry policy), but they're applied to the state strictly in order: each epoch header
+/// carries the validator set for the next one, so an epoch can't be skipped. The sync stops at
+/// the first header that can't be fetched or fails verification, leaving the state (and the
+/// storage) at the last verified epoch.
+pub struct EpochSync<'a> {
+    source: &'a dyn HeaderSource,
+    window: usize,
+    retry: RetryPolicy,
+}
+
+impl<'a> EpochSync<'a> {
+    pub fn new(source: &'a dyn HeaderSource) -> Self {
+        Self {
+            source,
+            window: DEFAULT_WINDOW,
+            retry: RetryPolicy::default(),
+        }
+    }
+
+    pub fn with_window(mut self, window: usize) -> Self {
+        self.window = window.max(1);
+        self
+    }
+
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Applies the epoch headers of the epochs in `[from_epoch, to_epoch)` range to the state.
+    /// The snapshot is persisted in the storage after every epoch, then `on_epoch` is called
+    /// with the epoch number and the snapshot (ie. to report the progress). Returns the number
+    /// of applied epochs.
+    pub async fn sync<F>(
+        &self,
+        state: &mut State<'_>,
+        storage: &mut dyn Storage,
+        from_epoch: u64,
+        to_epoch: u64,
+        current_timestamp: u64,
+        mut on_epoch: F,
+    ) -> Result<u64, Error>
+    where
+        F: FnMut(u64, &Snapshot),
+    {
+        self.apply(
+            state,
+            from_epoch,
+            to_epoch,
+            current_timestamp,
+            |epoch, snapshot| {
+                storage.put_snapshot(snapshot)?;
+                on_epoch(epoch, snapshot);
+
+                Ok(())
+            },
+        )
+        .await
+    }
+
+    /// Applies the epoch headers like `sync`, but leaves the snapshots to the `on_epoch` callback
+    /// (called after every epoch). The sync stops at the first error returned by the callback.
+    pub async fn apply<F>(
+        &self,
+        state: &mut State<'_>,
+        from_epoch: u64,
+        to_epoch: u64,
+        current_timestamp: u64,
+        mut on_epoch: F,
+    ) -> Result<u64, Error>
+    where
+        F: FnMut(u64, &Snapshot) -> Result<(), Error>,
+    {
+        let epoch_size = state.epoch_size();
+        let mut headers = stream::iter(from_epoch..to_epoch)
+            .map(|epoch| self.fetch(get_epoch_last_block_number(epoch, epoch_size)))
+            .buffered(self.window);
+
+        let mut epoch = from_epoch;
+        while let Some(header) = headers.next().await {
+            state.insert_header(&header?, current_timestamp)?;
+            on_epoch(epoch, state.snapshot())?;
+
+            epoch += 1;
+        }
+
+        Ok(epoch - from_epoch)
+    }
+
+    async fn fetch(&self, number: u64) -> Result<Header, Error> {
+        let header = self
+            .retry
+            .run(|| self.source.header_by_number(number))
+            .await?;
+
+        // a header of another height would be applied as the non-epoch one
+        if header.number.to_u64() != Some(number) {
+            return Err(to_source_err(format!(
+                "expected header {}, got {}",
+                number, header.number
+            )));
+        }
+
+        Ok(header)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::Kind;
+    use crate::storage::MemoryStorage;
+    use crate::sync::mock::{MockChain, MockSource};
+    use crate::sync::replay::{Fixture, ReplaySource};
+    use crate::types::state::Config;
+
+    const EPOCH_SIZE: u64 = 5;
+
+    fn config() -> Config {
+        Config {
+            epoch_size: EPOCH_SIZE,
+            allowed_clock_skew: 5,
+            verify_epoch_headers: true,
+            verify_non_epoch_headers: true,
+            verify_header_timestamp: true,
+        }
+    }
+
+    fn chain() -> MockChain {
+        let mut chain = MockChain::new(EPOCH_SIZE, 4);
+        chain.extend(12);
+        chain.rotate_validators(3);
+        chain.extend(12);
+
+        chain
+    }
+
+    fn now(chain: &MockChain) -> u64 {
+        chain.latest().time
+    }
+
+    #[tokio::test]
+    async fn test_sync_epochs() {
+        let chain = chain();
+        let (now, validators) = (now(&chain), chain.validators());
+        let source = MockSource::new(chain);
+
+        let config = config();
+        let mut state = State::new(Snapshot::new(), &config);
+        let mut storage = MemoryStorage::new();
+        let mut epochs = Vec::new();
+
+        let applied = EpochSync::new(&source)
+            .with_window(3)
+            .sync(&mut state, &mut storage, 0, 5, now, |epoch, _| {
+                epochs.push(epoch)
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(applied, 5);
+        assert_eq!(epochs, vec![0, 1, 2, 3, 4]);
+        assert_eq!(state.snapshot().number, 20);
+        assert_eq!(state.snapshot().validators, validators);
+        assert_eq!(
+            storage.get_latest_snapshot().unwrap().as_ref(),
+            Some(state.snapshot())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sync_stops_at_invalid_header() {
+        let chain = chain();
+        let now = now(&chain);
+
+        // the epoch 3 header is tampered with, so the seal doesn't match
+        let mut fixture = Fixture::default();
+        for number in (0..=20).step_by(EPOCH_SIZE as usize) {
+            let mut header = chain.header(number).unwrap().clone();
+            if number == 15 {
+                header.gas_used += 1;
+            }
+
+            fixture.headers.push(header);
+        }
+        let source = ReplaySource::new(fixture);
+
+        let config = config();
+        let mut state = State::new(Snapshot::new(), &config);
+        let mut storage = MemoryStorage::new();
+
+        let err = EpochSync::new(&source)
+            .sync(&mut state, &mut storage, 0, 5, now, |_, _| {})
+            .await
+            .unwrap_err();
+
+        assert!(matches!(err.kind(), Kind::BlsVerifyError));
+        assert_eq!(state.snapshot().number, 10);
+        assert_eq!(storage.get_latest_number().unwrap(), Some(10));
+    }
+
+    #[tokio::test]
+    async fn test_sync_stops_at_missing_header() {
+        let chain = chain();
+        let now = now(&chain);
+        let source = MockSource::new(chain);
+
+        let config = config();
+        let mut state = State::new(Snapshot::new(), &config);
+        let mut storage = MemoryStorage::new();
+
+        // epoch 5 isn't produced yet
+        let err = EpochSync::new(&source)
+            .with_retry_policy(RetryPolicy::none())
+            .sync(&mut state, &mut storage, 0, 6, now, |_, _| {})
+            .await
+            .unwrap_err();
+
+        assert!(matches!(err.kind(), Kind::HeaderSourceError));
+        assert_eq!(state.snapshot().number, 20);
+    }
+}
@@ -0,0 +1,526 @@
+use crate::errors::{Error, Kind};
+use crate::istanbul::{get_epoch_number, is_last_block_of_epoch};
+use crate::state::State;
+use crate::sync::epochs::EpochSync;
+use crate::sync::retry::RetryPolicy;
+use crate::sync::to_source_err;
+use crate::traits::HeaderSource;
+use crate::types::header::{Hash, Header};
+use crate::types::state::Snapshot;
+use futures::stream::{self, BoxStream, Stream, StreamExt};
+use futures::SinkExt;
+use num::cast::ToPrimitive;
+use serde_json::json;
+use std::collections::VecDeque;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio_tungstenite::tungstenite::Message;
+
+/// Default number of blocks built on top of the header, before the header is considered final
+const DEFAULT_CONFIRMATIONS: u64 = 1;
+
+/// Default interval between the latest header polls
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// FollowEvent is emitted by the `Follower` for every verified header
+#[derive(Clone, PartialEq, Debug)]
+pub enum FollowEvent {
+    /// The header has been verified against the validator set of its epoch
+    Header(Header),
+
+    /// The epoch header has been verified and applied, the snapshot holds the validator set for
+    /// the next epoch (ie. to be persisted)
+    Epoch { epoch: u64, snapshot: Snapshot },
+}
+
+/// Follower keeps verifying the new headers of the chain, as they're produced.
+///
+/// The new heads are received from `newHeads` WebSocket subscription (if configured), or by
+/// polling the latest header of the header source. The subscription is only attempted once,
+/// whenever it can't be established or drops, the follower falls back to polling.
+///
+/// Before a header is verified, it has to be buried under `confirmations` blocks. Up to then,
+/// the header is kept on the pending branch, that is rebuilt (by walking the parent hashes back
+/// to the last verified header) whenever a head doesn't extend it, so that the reorgs and the
+/// short-lived forks are never emitted. The epoch headers are applied to the state on the epoch
+/// boundaries, missed epochs (ie. when the follower has been lagging behind for more than an
+/// epoch) are synced with `EpochSync` first.
+///
+/// The state has to hold at least the genesis validator set.
+pub struct Follower<'a> {
+    source: &'a dyn HeaderSource,
+    state: State<'a>,
+    retry: RetryPolicy,
+    confirmations: u64,
+    poll_interval: Duration,
+    ws_url: Option<String>,
+    heads: Option<BoxStream<'static, Result<Header, Error>>>,
+    last_polled: Option<Hash>,
+
+    // last verified header and the unconfirmed branch on top of it
+    last: Option<Header>,
+    pending: VecDeque<Header>,
+    events: VecDeque<FollowEvent>,
+}
+
+impl<'a> Follower<'a> {
+    pub fn new(source: &'a dyn HeaderSource, state: State<'a>) -> Self {
+        Self {
+            source,
+            state,
+            retry: RetryPolicy::default(),
+            confirmations: DEFAULT_CONFIRMATIONS,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            ws_url: None,
+            heads: None,
+            last_polled: None,
+            last: None,
+            pending: VecDeque::new(),
+            events: VecDeque::new(),
+        }
+    }
+
+    /// Subscribes to the new heads via celo-geth WebSocket endpoint (ie. `ws://127.0.0.1:8546`)
+    pub fn with_websocket(mut self, url: &str) -> Self {
+        self.ws_url = Some(url.to_string());
+        self
+    }
+
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    pub fn with_confirmations(mut self, confirmations: u64) -> Self {
+        self.confirmations = confirmations;
+        self
+    }
+
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    pub fn state(&self) -> &State<'a> {
+        &self.state
+    }
+
+    pub fn into_state(self) -> State<'a> {
+        self.state
+    }
+
+    /// Returns the stream of events. The stream ends after the first error (ie. a header that
+    /// fails verification).
+    pub fn into_stream(self) -> impl Stream<Item = Result<FollowEvent, Error>> + 'a {
+        stream::unfold(Some(self), |follower| async move {
+            let mut follower = follower?;
+            match follower.next_event().await {
+                Ok(event) => Some((Ok(event), Some(follower))),
+                Err(e) => Some((Err(e), None)),
+            }
+        })
+    }
+
+    /// Waits for the next event
+    pub async fn next_event(&mut self) -> Result<FollowEvent, Error> {
+        loop {
+            if let Some(event) = self.events.pop_front() {
+                return Ok(event);
+            }
+
+            let head = self.next_head().await?;
+            self.on_head(head).await?;
+        }
+    }
+
+    async fn next_head(&mut self) -> Result<Header, Error> {
+        if let Some(url) = self.ws_url.take() {
+            self.heads = subscribe_new_heads(&url).await.ok();
+        }
+
+        if let Some(heads) = self.heads.as_mut() {
+            match heads.next().await {
+                Some(Ok(head)) => return Ok(head),
+                // the subscription dropped, fall back to polling
+                _ => self.heads = None,
+            }
+        }
+
+        loop {
+            let source = self.source;
+            let head = self.retry.run(|| source.latest_header()).await?;
+
+            let hash = head.hash()?;
+            if self.last_polled != Some(hash) {
+                self.last_polled = Some(hash);
+                return Ok(head);
+            }
+
+            tokio::time::delay_for(self.poll_interval).await;
+        }
+    }
+
+    async fn on_head(&mut self, head: Header) -> Result<(), Error> {
+        let number = to_number(&head)?;
+        let epoch_size = self.state.epoch_size();
+
+        // the head at (or below) the verified header is either known or a stale fork
+        let last_number = match &self.last {
+            Some(last) => to_number(last)?,
+            None => self.state.snapshot().number,
+        };
+        if number <= last_number {
+            return Ok(());
+        }
+
+        // the follower lagged behind for more than an epoch (or just started), instead of
+        // walking back the whole gap, the missed epochs are synced and the branch is restarted
+        if number > last_number + epoch_size || self.last.is_none() {
+            self.catch_up(number).await?;
+        }
+
+        let hash = head.hash()?;
+        if self
+            .pending
+            .iter()
+            .any(|header| header.hash().ok() == Some(hash))
+        {
+            return Ok(());
+        }
+
+        let extends_branch = match self.pending.back() {
+            Some(tip) => head.parent_hash == tip.hash()?,
+            None => false,
+        };
+        if extends_branch {
+            self.pending.push_back(head);
+        } else {
+            self.pending = self.branch(head).await?;
+        }
+
+        while self.pending.len() as u64 > self.confirmations {
+            let header = self.pending.pop_front().expect("pending header");
+            self.verify(header)?;
+        }
+
+        Ok(())
+    }
+
+    // catch_up syncs the epochs preceding the head epoch, the verified branch is reset
+    async fn catch_up(&mut self, number: u64) -> Result<(), Error> {
+        let epoch_size = self.state.epoch_size();
+        let from_epoch = get_epoch_number(self.state.snapshot().number, epoch_size) + 1;
+        let to_epoch = get_epoch_number(number, epoch_size);
+        if from_epoch >= to_epoch {
+            return Ok(());
+        }
+
+        let events = &mut self.events;
+        EpochSync::new(self.source)
+            .with_retry_policy(self.retry.clone())
+            .apply(
+                &mut self.state,
+                from_epoch,
+                to_epoch,
+                current_timestamp(),
+                |epoch, snapshot| {
+                    events.push_back(FollowEvent::Epoch {
+                        epoch,
+                        snapshot: snapshot.clone(),
+                    });
+
+                    Ok(())
+                },
+            )
+            .await?;
+
+        self.last = None;
+        self.pending.clear();
+
+        Ok(())
+    }
+
+    // branch returns the headers from the one following the last verified header up to the
+    // head (unless nothing has been verified yet, then the branch starts at the head)
+    async fn branch(&self, head: Header) -> Result<VecDeque<Header>, Error> {
+        let mut branch = VecDeque::new();
+        branch.push_front(head);
+
+        let last = match &self.last {
+            Some(last) => last,
+            None => return Ok(branch),
+        };
+        let (last_number, last_hash) = (to_number(last)?, last.hash()?);
+
+        loop {
+            let first = branch.front().expect("branch head");
+            if to_number(first)? == last_number + 1 {
+                if first.parent_hash != last_hash {
+                    return Err(Kind::HeaderVerificationError {
+                        msg: "the chain forked below the last verified header",
+                    }
+                    .into());
+                }
+
+                return Ok(branch);
+            }
+
+            // the pending headers are reused (ie. when only the tip has been reorganized)
+            let parent_hash = &first.parent_hash.clone();
+            let parent = match self
+                .pending
+                .iter()
+                .find(|header| header.hash().ok().as_ref() == Some(parent_hash))
+            {
+                Some(header) => header.clone(),
+                None => {
+                    let source = self.source;
+                    self.retry
+                        .run(move || source.header_by_hash(parent_hash))
+                        .await?
+                }
+            };
+
+            branch.push_front(parent);
+        }
+    }
+
+    // verify checks the final header against the validator set of its epoch, the epoch headers
+    // are applied to the state
+    fn verify(&mut self, header: Header) -> Result<(), Error> {
+        let number = to_number(&header)?;
+        let epoch_size = self.state.epoch_size();
+
+        if is_last_block_of_epoch(number, epoch_size) {
+            self.state.insert_header(&header, current_timestamp())?;
+            self.events.push_back(FollowEvent::Header(header.clone()));
+            self.events.push_back(FollowEvent::Epoch {
+                epoch: get_epoch_number(number, epoch_size),
+                snapshot: self.state.snapshot().clone(),
+            });
+        } else {
+            self.state.verify_header(&header, current_timestamp())?;
+            self.events.push_back(FollowEvent::Header(header.clone()));
+        }
+
+        self.last = Some(header);
+
+        Ok(())
+    }
+}
+
+// subscribe_new_heads returns the stream of the heads received from `newHeads` subscription
+async fn subscribe_new_heads(
+    url: &str,
+) -> Result<BoxStream<'static, Result<Header, Error>>, Error> {
+    #[derive(Deserialize)]
+    struct Notification {
+        params: Option<NotificationParams>,
+        error: Option<serde_json::Value>,
+    }
+
+    #[derive(Deserialize)]
+    struct NotificationParams {
+        result: Header,
+    }
+
+    let (mut ws, _) = tokio_tungstenite::connect_async(url)
+        .await
+        .map_err(to_source_err)?;
+
+    let request = json!({
+        "jsonrpc": "2.0",
+        "method": "eth_subscribe",
+        "params": ["newHeads"],
+        "id": 1,
+    });
+    ws.send(Message::Text(request.to_string()))
+        .await
+        .map_err(to_source_err)?;
+
+    let heads = ws.filter_map(|message| async move {
+        match message {
+            Ok(Message::Text(text)) => match serde_json::from_str::<Notification>(&text) {
+                Ok(Notification {
+                    params: Some(params),
+                    ..
+                }) => Some(Ok(params.result)),
+                Ok(Notification {
+                    error: Some(error), ..
+                }) => Some(Err(to_source_err(format!("subscription error: {}", error)))),
+                // ie. the subscription id
+                Ok(_) => None,
+                Err(e) => Some(Err(to_source_err(e))),
+            },
+            Ok(Message::Close(_)) => Some(Err(to_source_err("subscription closed"))),
+            Ok(_) => None,
+            Err(e) => Some(Err(to_source_err(e))),
+        }
+    });
+
+    Ok(heads.boxed())
+}
+
+fn to_number(header: &Header) -> Result<u64, Error> {
+    header
+        .number
+        .to_u64()
+        .ok_or_else(|| to_source_err(format!("invalid header number: {}", header.number)))
+}
+
+fn current_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::istanbul::get_epoch_last_block_number;
+    use crate::sync::mock::{MockChain, MockSource};
+    use crate::types::state::Config;
+    use std::sync::{Arc, RwLock};
+
+    const EPOCH_SIZE: u64 = 5;
+
+    fn config() -> Config {
+        Config {
+            epoch_size: EPOCH_SIZE,
+            allowed_clock_skew: 5,
+            verify_epoch_headers: true,
+            verify_non_epoch_headers: true,
+            verify_header_timestamp: true,
+        }
+    }
+
+    // state returns the state synced up to the given epoch
+    async fn state<'a>(source: &MockSource, config: &'a Config, epoch: u64) -> State<'a> {
+        let mut state = State::new(Snapshot::new(), config);
+        for epoch in 0..=epoch {
+            let number = get_epoch_last_block_number(epoch, EPOCH_SIZE);
+            let header = source.header_by_number(number).await.unwrap();
+            state.insert_header(&header, current_timestamp()).unwrap();
+        }
+
+        state
+    }
+
+    fn header(chain: &Arc<RwLock<MockChain>>, number: u64) -> Header {
+        chain.read().unwrap().header(number).unwrap().clone()
+    }
+
+    fn verified(events: &VecDeque<FollowEvent>) -> Vec<Header> {
+        events
+            .iter()
+            .filter_map(|event| match event {
+                FollowEvent::Header(header) => Some(header.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_follow_reorg() {
+        let mut chain = MockChain::new(EPOCH_SIZE, 4);
+        chain.extend(8);
+        let source = MockSource::new(chain);
+        let chain = source.chain();
+
+        let config = config();
+        let state = state(&source, &config, 1).await;
+        let mut follower = Follower::new(&source, state).with_confirmations(2);
+
+        for number in 6..=8 {
+            follower.on_head(header(&chain, number)).await.unwrap();
+        }
+        assert_eq!(verified(&follower.events), vec![header(&chain, 6)]);
+
+        // the blocks 7 and 8 are replaced by the competing ones
+        let stale = header(&chain, 7);
+        chain.write().unwrap().fork(6);
+        chain.write().unwrap().extend(3);
+        follower.on_head(header(&chain, 9)).await.unwrap();
+
+        let headers = verified(&follower.events);
+        assert_eq!(headers, vec![header(&chain, 6), header(&chain, 7)]);
+        assert_ne!(headers[1], stale);
+
+        // the stale head is ignored
+        follower.on_head(stale).await.unwrap();
+        assert_eq!(follower.pending.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_follow_catch_up() {
+        let mut chain = MockChain::new(EPOCH_SIZE, 4);
+        chain.extend(12);
+        chain.rotate_validators(3);
+        chain.extend(6);
+        let source = MockSource::new(chain);
+        let chain = source.chain();
+
+        let config = config();
+        let state = state(&source, &config, 1).await;
+        let mut follower = Follower::new(&source, state).with_confirmations(0);
+
+        // epochs 2 and 3 are synced before the head is verified
+        follower.on_head(header(&chain, 17)).await.unwrap();
+        let epochs: Vec<u64> = follower
+            .events
+            .iter()
+            .filter_map(|event| match event {
+                FollowEvent::Epoch { epoch, .. } => Some(*epoch),
+                _ => None,
+            })
+            .collect();
+
+        assert_eq!(epochs, vec![2, 3]);
+        assert_eq!(verified(&follower.events), vec![header(&chain, 17)]);
+        assert_eq!(
+            follower.state().snapshot().validators,
+            chain.read().unwrap().validators()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_follow_stream() {
+        let mut chain = MockChain::new(EPOCH_SIZE, 4);
+        chain.extend(7);
+        let source = MockSource::new(chain);
+        let chain = source.chain();
+
+        // the chain keeps growing in the background
+        tokio::spawn(async move {
+            for _ in 0..6 {
+                tokio::time::delay_for(Duration::from_millis(10)).await;
+                chain.write().unwrap().extend(1);
+            }
+        });
+
+        let config = config();
+        let state = state(&source, &config, 1).await;
+        let events = Follower::new(&source, state)
+            .with_poll_interval(Duration::from_millis(1))
+            .into_stream();
+        futures::pin_mut!(events);
+
+        let mut numbers = Vec::new();
+        let mut epochs = Vec::new();
+        while !numbers.contains(&12) {
+            let event = tokio::time::timeout(Duration::from_secs(10), events.next())
+                .await
+                .unwrap()
+                .unwrap()
+                .unwrap();
+
+            match event {
+                FollowEvent::Header(header) => numbers.push(to_number(&header).unwrap()),
+                FollowEvent::Epoch { epoch, .. } => epochs.push(epoch),
+            }
+        }
+
+        assert!(numbers.windows(2).all(|pair| pair[1] == pair[0] + 1));
+        assert_eq!(epochs, vec![2]);
+    }
+}
@@ -0,0 +1,144 @@
+use crate::errors::Error;
+use crate::sync::to_source_err;
+use crate::traits::HeaderSource;
+use crate::types::header::{Address, Hash, Header};
+use crate::types::proof::AccountProof;
+use async_trait::async_trait;
+use std::sync::{Arc, RwLock};
+
+pub use crate::mock::MockChain;
+
+/// MockSource serves the headers of the mocked chain. The chain is shared, so that it can be
+/// extended while the source is in use.
+#[derive(Clone)]
+pub struct MockSource {
+    chain: Arc<RwLock<MockChain>>,
+}
+
+impl MockSource {
+    pub fn new(chain: MockChain) -> Self {
+        Self {
+            chain: Arc::new(RwLock::new(chain)),
+        }
+    }
+
+    pub fn chain(&self) -> Arc<RwLock<MockChain>> {
+        self.chain.clone()
+    }
+}
+
+#[async_trait]
+impl HeaderSource for MockSource {
+    async fn header_by_number(&self, number: u64) -> Result<Header, Error> {
+        self.chain
+            .read()
+            .unwrap()
+            .header(number)
+            .cloned()
+            .ok_or_else(|| to_source_err(format!("header {} not found", number)))
+    }
+
+    async fn header_by_hash(&self, hash: &Hash) -> Result<Header, Error> {
+        let chain = self.chain.read().unwrap();
+        for header in chain.headers().iter() {
+            if &header.hash()? == hash {
+                return Ok(header.clone());
+            }
+        }
+
+        Err(to_source_err(format!(
+            "header 0x{} not found",
+            hex::encode(hash)
+        )))
+    }
+
+    async fn latest_header(&self) -> Result<Header, Error> {
+        Ok(self.chain.read().unwrap().latest().clone())
+    }
+
+    async fn account_proof(
+        &self,
+        _address: &Address,
+        _keys: &[Hash],
+        _number: u64,
+    ) -> Result<AccountProof, Error> {
+        Err(to_source_err(
+            "account proofs are not supported by the mocked chain",
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::istanbul::get_epoch_last_block_number;
+    use crate::mock::GENESIS_TIME;
+    use crate::state::State;
+    use crate::types::state::{Config, Snapshot};
+    use num_bigint::BigInt as Integer;
+
+    fn config(epoch_size: u64) -> Config {
+        Config {
+            epoch_size,
+            allowed_clock_skew: 5,
+            verify_epoch_headers: true,
+            verify_non_epoch_headers: true,
+            verify_header_timestamp: true,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sync_mocked_chain() {
+        let mut chain = MockChain::new(5, 4);
+        chain.extend(7);
+        chain.rotate_validators(3);
+        chain.extend(6);
+        let validators = chain.validators();
+
+        let source = MockSource::new(chain);
+        let config = config(5);
+        let mut state = State::new(Snapshot::new(), &config);
+        let now = GENESIS_TIME + 1_000;
+
+        for epoch in 0..=2 {
+            let number = get_epoch_last_block_number(epoch, 5);
+            let header = source.header_by_number(number).await.unwrap();
+            state.insert_header(&header, now).unwrap();
+        }
+        assert_eq!(state.snapshot().number, 10);
+        assert_eq!(state.snapshot().validators, validators);
+
+        let latest = source.latest_header().await.unwrap();
+        assert_eq!(latest.number, Integer::from(13));
+        state.verify_header(&latest, now).unwrap();
+
+        // the header sealed by the new validators is rejected by the previous validator set
+        let mut state = State::new(Snapshot::new(), &config);
+        for number in [0, 5].iter() {
+            let header = source.header_by_number(*number).await.unwrap();
+            state.insert_header(&header, now).unwrap();
+        }
+        assert!(state.verify_header(&latest, now).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_mock_source() {
+        let source = MockSource::new(MockChain::new(5, 2));
+        source.chain().write().unwrap().extend(3);
+
+        let header = source.header_by_number(2).await.unwrap();
+        assert_eq!(
+            source.latest_header().await.unwrap().number,
+            Integer::from(3)
+        );
+        assert_eq!(
+            source
+                .header_by_hash(&header.hash().unwrap())
+                .await
+                .unwrap(),
+            header
+        );
+        assert_eq!(source.headers_by_number(&[1, 2]).await.unwrap()[1], header);
+        assert!(source.header_by_number(4).await.is_err());
+    }
+}
@@ -0,0 +1,31 @@
+// This module provides the `HeaderSource` implementations and the building blocks of the sync
+// process, gated by `sync` feature:
+//  * rpc - celo-geth JSON-RPC endpoint (batched requests, retries)
+//  * replay - recorded fixtures, so that the sync can be replayed offline
+//  * mock - in-process chain generated on the fly (for the tests)
+// and the sync pipeline on top of them:
+//  * epochs - concurrent epoch headers fetching, applied to the state in order
+//  * follow - continuous verification of the new heads (WebSocket subscription or polling)
+//  * crosscheck - comparison of the headers served by several sources (misbehaviour evidence)
+mod crosscheck;
+mod epochs;
+mod follow;
+mod mock;
+mod replay;
+mod retry;
+mod rpc;
+
+pub use crosscheck::{CrossCheck, CrossChecker, Evidence};
+pub use epochs::EpochSync;
+pub use follow::{FollowEvent, Follower};
+pub use mock::{MockChain, MockSource};
+pub use replay::{Fixture, ProofEntry, RecordingSource, ReplaySource};
+pub use retry::RetryPolicy;
+pub use rpc::JsonRpcSource;
+
+use crate::errors::{Error, Kind};
+use anomaly::BoxError;
+
+pub(crate) fn to_source_err(e: impl Into<BoxError>) -> Error {
+    Kind::HeaderSourceError.context(e).into()
+}
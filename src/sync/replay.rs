@@ -0,0 +1,259 @@
+use crate::errors::Error;
+use crate::sync::to_source_err;
+use crate::traits::HeaderSource;
+use crate::types::header::{Address, Hash, Header};
+use crate::types::proof::AccountProof;
+use async_trait::async_trait;
+use num::cast::ToPrimitive;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Fixture is the recorded set of headers and proofs (JSON serializable), that can be replayed
+/// with `ReplaySource`
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, Default)]
+pub struct Fixture {
+    /// Block number of the header returned as the latest one
+    pub latest: Option<u64>,
+
+    pub headers: Vec<Header>,
+
+    pub proofs: Vec<ProofEntry>,
+}
+
+/// ProofEntry is the account proof recorded at the given block number
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct ProofEntry {
+    pub number: u64,
+    pub proof: AccountProof,
+}
+
+impl Fixture {
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let bytes = std::fs::read(path).map_err(to_source_err)?;
+
+        serde_json::from_slice(&bytes).map_err(to_source_err)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let bytes = serde_json::to_vec_pretty(self).map_err(to_source_err)?;
+
+        std::fs::write(path, bytes).map_err(to_source_err)
+    }
+
+    fn insert_header(&mut self, header: &Header) {
+        if !self.headers.contains(header) {
+            self.headers.push(header.clone());
+        }
+    }
+
+    fn insert_proof(&mut self, number: u64, proof: &AccountProof) {
+        let entry = ProofEntry {
+            number,
+            proof: proof.clone(),
+        };
+
+        if !self.proofs.contains(&entry) {
+            self.proofs.push(entry);
+        }
+    }
+}
+
+/// ReplaySource serves the headers and proofs from the recorded fixture
+pub struct ReplaySource {
+    headers: BTreeMap<u64, Header>,
+    proofs: Vec<ProofEntry>,
+    latest: Option<u64>,
+}
+
+impl ReplaySource {
+    pub fn new(fixture: Fixture) -> Self {
+        let headers = fixture
+            .headers
+            .into_iter()
+            .filter_map(|header| header.number.to_u64().map(|number| (number, header)))
+            .collect();
+
+        Self {
+            headers,
+            proofs: fixture.proofs,
+            latest: fixture.latest,
+        }
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        Ok(Self::new(Fixture::from_file(path)?))
+    }
+}
+
+#[async_trait]
+impl HeaderSource for ReplaySource {
+    async fn header_by_number(&self, number: u64) -> Result<Header, Error> {
+        self.headers
+            .get(&number)
+            .cloned()
+            .ok_or_else(|| to_source_err(format!("header {} not recorded", number)))
+    }
+
+    async fn header_by_hash(&self, hash: &Hash) -> Result<Header, Error> {
+        for header in self.headers.values() {
+            if &header.hash()? == hash {
+                return Ok(header.clone());
+            }
+        }
+
+        Err(to_source_err(format!(
+            "header 0x{} not recorded",
+            hex::encode(hash)
+        )))
+    }
+
+    async fn latest_header(&self) -> Result<Header, Error> {
+        // unless recorded explicitly, the highest header is the latest one
+        let number = match self.latest.or_else(|| self.headers.keys().last().copied()) {
+            Some(number) => number,
+            None => return Err(to_source_err("no headers recorded")),
+        };
+
+        self.header_by_number(number).await
+    }
+
+    async fn account_proof(
+        &self,
+        address: &Address,
+        keys: &[Hash],
+        number: u64,
+    ) -> Result<AccountProof, Error> {
+        self.proofs
+            .iter()
+            .find(|entry| {
+                entry.number == number
+                    && &entry.proof.address == address
+                    && keys.iter().all(|key| {
+                        entry
+                            .proof
+                            .storage_proof
+                            .iter()
+                            .any(|proof| &proof.key == key)
+                    })
+            })
+            .map(|entry| entry.proof.clone())
+            .ok_or_else(|| {
+                to_source_err(format!(
+                    "proof of 0x{} at {} not recorded",
+                    hex::encode(address),
+                    number
+                ))
+            })
+    }
+}
+
+/// RecordingSource passes the requests through to the inner source, recording the responses in
+/// the fixture (ie. to replay the sync against the live node offline)
+pub struct RecordingSource<S: HeaderSource> {
+    inner: S,
+    fixture: Mutex<Fixture>,
+}
+
+impl<S: HeaderSource> RecordingSource<S> {
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner,
+            fixture: Mutex::new(Fixture::default()),
+        }
+    }
+
+    /// Returns the responses recorded so far
+    pub fn fixture(&self) -> Fixture {
+        self.fixture.lock().unwrap().clone()
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        self.fixture().save(path)
+    }
+
+    fn record(&self, header: &Header) {
+        self.fixture.lock().unwrap().insert_header(header);
+    }
+}
+
+#[async_trait]
+impl<S: HeaderSource> HeaderSource for RecordingSource<S> {
+    async fn header_by_number(&self, number: u64) -> Result<Header, Error> {
+        let header = self.inner.header_by_number(number).await?;
+        self.record(&header);
+
+        Ok(header)
+    }
+
+    async fn header_by_hash(&self, hash: &Hash) -> Result<Header, Error> {
+        let header = self.inner.header_by_hash(hash).await?;
+        self.record(&header);
+
+        Ok(header)
+    }
+
+    async fn latest_header(&self) -> Result<Header, Error> {
+        let header = self.inner.latest_header().await?;
+        self.record(&header);
+        self.fixture.lock().unwrap().latest = header.number.to_u64();
+
+        Ok(header)
+    }
+
+    async fn headers_by_number(&self, numbers: &[u64]) -> Result<Vec<Header>, Error> {
+        let headers = self.inner.headers_by_number(numbers).await?;
+        for header in headers.iter() {
+            self.record(header);
+        }
+
+        Ok(headers)
+    }
+
+    async fn account_proof(
+        &self,
+        address: &Address,
+        keys: &[Hash],
+        number: u64,
+    ) -> Result<AccountProof, Error> {
+        let proof = self.inner.account_proof(address, keys, number).await?;
+        self.fixture.lock().unwrap().insert_proof(number, &proof);
+
+        Ok(proof)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sync::mock::{MockChain, MockSource};
+
+    #[tokio::test]
+    async fn test_record_and_replay() {
+        let mut chain = MockChain::new(5, 4);
+        chain.extend(12);
+        let recorder = RecordingSource::new(MockSource::new(chain));
+
+        let latest = recorder.latest_header().await.unwrap();
+        let epochs = recorder.headers_by_number(&[0, 5, 10]).await.unwrap();
+        let fixture = recorder.fixture();
+        assert_eq!(fixture.latest, Some(12));
+        assert_eq!(fixture.headers.len(), 4);
+
+        let path = std::env::temp_dir().join("celo-light-client-replay-fixture.json");
+        fixture.save(&path).unwrap();
+        let replay = ReplaySource::from_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(replay.latest_header().await.unwrap(), latest);
+        assert_eq!(replay.headers_by_number(&[0, 5, 10]).await.unwrap(), epochs);
+        assert_eq!(
+            replay
+                .header_by_hash(&epochs[1].hash().unwrap())
+                .await
+                .unwrap(),
+            epochs[1]
+        );
+        assert!(replay.header_by_number(11).await.is_err());
+    }
+}
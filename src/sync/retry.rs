@@ -0,0 +1,117 @@
+use crate::errors::Error;
+use std::future::Future;
+use std::time::Duration;
+
+/// RetryPolicy describes how the failed requests are retried: the backoff starts at
+/// `initial_backoff` and doubles with every attempt (up to `max_backoff`)
+#[derive(Clone, Debug, PartialEq)]
+pub struct RetryPolicy {
+    /// Number of retries after the first failed attempt
+    pub max_retries: u32,
+
+    /// Delay before the first retry
+    pub initial_backoff: Duration,
+
+    /// Upper bound of the delay between the retries
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(200),
+            max_backoff: Duration::from_secs(5),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Returns the policy that fails on the first error
+    pub fn none() -> Self {
+        Self {
+            max_retries: 0,
+            initial_backoff: Duration::from_millis(0),
+            max_backoff: Duration::from_millis(0),
+        }
+    }
+
+    /// Returns the delay before the given retry attempt (0-based)
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        self.initial_backoff
+            .checked_mul(1 << attempt.min(16))
+            .map_or(self.max_backoff, |backoff| backoff.min(self.max_backoff))
+    }
+
+    /// Runs the operation until it succeeds or the retries are exhausted (the last error is
+    /// returned)
+    pub async fn run<T, F, Fut>(&self, mut operation: F) -> Result<T, Error>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, Error>>,
+    {
+        let mut attempt = 0;
+        loop {
+            match operation().await {
+                Ok(value) => return Ok(value),
+                Err(e) if attempt >= self.max_retries => return Err(e),
+                Err(_) => {
+                    tokio::time::delay_for(self.backoff(attempt)).await;
+                    attempt += 1;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::Kind;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    fn policy(max_retries: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_retries,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(4),
+        }
+    }
+
+    #[test]
+    fn test_backoff() {
+        let policy = policy(10);
+
+        assert_eq!(policy.backoff(0), Duration::from_millis(1));
+        assert_eq!(policy.backoff(1), Duration::from_millis(2));
+        assert_eq!(policy.backoff(2), Duration::from_millis(4));
+        assert_eq!(policy.backoff(3), Duration::from_millis(4));
+        assert_eq!(policy.backoff(100), Duration::from_millis(4));
+    }
+
+    #[tokio::test]
+    async fn test_run() {
+        let attempts = &AtomicU32::new(0);
+        let result = policy(3)
+            .run(|| async move {
+                match attempts.fetch_add(1, Ordering::SeqCst) {
+                    0 | 1 => Err(Kind::HeaderSourceError.into()),
+                    attempt => Ok(attempt),
+                }
+            })
+            .await;
+
+        assert_eq!(result.unwrap(), 2);
+
+        let attempts = &AtomicU32::new(0);
+        let result: Result<(), Error> = policy(3)
+            .run(|| async move {
+                attempts.fetch_add(1, Ordering::SeqCst);
+                Err(Kind::HeaderSourceError.into())
+            })
+            .await;
+
+        assert!(result.is_err());
+        assert_eq!(attempts.load(Ordering::SeqCst), 4);
+    }
+}
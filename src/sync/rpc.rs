@@ -0,0 +1,210 @@
+use crate::errors::Error;
+use crate::sync::retry::RetryPolicy;
+use crate::sync::to_source_err;
+use crate::traits::HeaderSource;
+use crate::types::header::{Address, Hash, Header};
+use crate::types::proof::AccountProof;
+use async_trait::async_trait;
+use hyper::client::{Client, HttpConnector};
+use hyper::{Body, Request};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+
+/// Default number of requests sent in a single JSON-RPC batch
+const DEFAULT_BATCH_SIZE: usize = 32;
+
+/// JsonRpcSource fetches the headers from celo-geth JSON-RPC endpoint. The requests failing on
+/// the transport level (connection errors, non 2xx responses) are retried according to the
+/// retry policy, multiple headers are fetched with the batched requests.
+pub struct JsonRpcSource {
+    client: Client<HttpConnector, Body>,
+    uri: String,
+    retry: RetryPolicy,
+    batch_size: usize,
+}
+
+#[derive(Deserialize)]
+struct Response<T> {
+    id: u64,
+    result: Option<T>,
+    error: Option<RpcError>,
+}
+
+#[derive(Deserialize)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl JsonRpcSource {
+    pub fn new(uri: &str) -> Self {
+        Self {
+            client: Client::new(),
+            uri: uri.to_string(),
+            retry: RetryPolicy::default(),
+            batch_size: DEFAULT_BATCH_SIZE,
+        }
+    }
+
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    async fn call<T: DeserializeOwned>(&self, method: &str, params: Value) -> Result<T, Error> {
+        let buf = self.post(request(0, method, params)).await?;
+        let response: Response<T> = serde_json::from_slice(&buf).map_err(to_source_err)?;
+
+        to_result(method, response)
+    }
+
+    async fn batch_call<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: Vec<Value>,
+    ) -> Result<Vec<T>, Error> {
+        let count = params.len();
+        let body = Value::Array(
+            params
+                .into_iter()
+                .enumerate()
+                .map(|(id, params)| request(id as u64, method, params))
+                .collect(),
+        );
+
+        let buf = self.post(body).await?;
+        let mut responses: Vec<Response<T>> =
+            serde_json::from_slice(&buf).map_err(to_source_err)?;
+
+        // the batch responses may come in any order
+        responses.sort_by_key(|response| response.id);
+        if responses.len() != count || responses.iter().enumerate().any(|(i, r)| r.id != i as u64) {
+            return Err(to_source_err(format!(
+                "{}: invalid batch response (expected {} responses)",
+                method, count
+            )));
+        }
+
+        responses
+            .into_iter()
+            .map(|response| to_result(method, response))
+            .collect()
+    }
+
+    async fn post(&self, body: Value) -> Result<Vec<u8>, Error> {
+        let body = body.to_string();
+
+        self.retry
+            .run(|| {
+                let body = body.clone();
+                async move {
+                    let req = Request::builder()
+                        .method("POST")
+                        .uri(&self.uri)
+                        .header("Content-Type", "application/json")
+                        .body(Body::from(body))
+                        .map_err(to_source_err)?;
+
+                    let response = self.client.request(req).await.map_err(to_source_err)?;
+                    if !response.status().is_success() {
+                        return Err(to_source_err(format!(
+                            "unexpected response status: {}",
+                            response.status()
+                        )));
+                    }
+
+                    let buf = hyper::body::to_bytes(response)
+                        .await
+                        .map_err(to_source_err)?;
+
+                    Ok(buf.to_vec())
+                }
+            })
+            .await
+    }
+}
+
+#[async_trait]
+impl HeaderSource for JsonRpcSource {
+    async fn header_by_number(&self, number: u64) -> Result<Header, Error> {
+        self.call("eth_getBlockByNumber", json!([to_hex(number), false]))
+            .await
+    }
+
+    async fn header_by_hash(&self, hash: &Hash) -> Result<Header, Error> {
+        self.call(
+            "eth_getBlockByHash",
+            json!([format!("0x{}", hex::encode(hash)), false]),
+        )
+        .await
+    }
+
+    async fn latest_header(&self) -> Result<Header, Error> {
+        self.call("eth_getBlockByNumber", json!(["latest", false]))
+            .await
+    }
+
+    async fn headers_by_number(&self, numbers: &[u64]) -> Result<Vec<Header>, Error> {
+        let mut headers = Vec::with_capacity(numbers.len());
+        for chunk in numbers.chunks(self.batch_size) {
+            let params = chunk
+                .iter()
+                .map(|number| json!([to_hex(*number), false]))
+                .collect();
+
+            headers.extend(self.batch_call("eth_getBlockByNumber", params).await?);
+        }
+
+        Ok(headers)
+    }
+
+    async fn account_proof(
+        &self,
+        address: &Address,
+        keys: &[Hash],
+        number: u64,
+    ) -> Result<AccountProof, Error> {
+        let keys: Vec<String> = keys
+            .iter()
+            .map(|key| format!("0x{}", hex::encode(key)))
+            .collect();
+
+        self.call(
+            "eth_getProof",
+            json!([format!("0x{}", hex::encode(address)), keys, to_hex(number)]),
+        )
+        .await
+    }
+}
+
+fn request(id: u64, method: &str, params: Value) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "method": method,
+        "params": params,
+        "id": id,
+    })
+}
+
+fn to_result<T>(method: &str, response: Response<T>) -> Result<T, Error> {
+    if let Some(error) = response.error {
+        return Err(to_source_err(format!(
+            "{}: rpc error {}: {}",
+            method, error.code, error.message
+        )));
+    }
+
+    // celo-geth responds with null result for unknown blocks
+    response
+        .result
+        .ok_or_else(|| to_source_err(format!("{}: not found", method)))
+}
+
+fn to_hex(number: u64) -> String {
+    format!("0x{:x}", number)
+}
@@ -1,4 +1,13 @@
 use crate::errors::Error;
+use crate::istanbul::get_epoch_last_block_number;
+use crate::types::state::Snapshot;
+#[cfg(feature = "sync")]
+use crate::types::{
+    header::{Address, Hash, Header},
+    proof::AccountProof,
+};
+#[cfg(feature = "sync")]
+use async_trait::async_trait;
 
 // "Deafult" trait is implemented for a few selected fixed-array types. Taken we can't implement
 // the trait outside of a crate, we created a new one that mimics the stdlib.
@@ -37,3 +46,68 @@ pub trait StateConfig {
     /// Whether to verify headers time against current time. It's recommended to keep it true
     fn verify_header_timestamp(&self) -> bool;
 }
+
+/// Storage persists the snapshots built up by the light client. Snapshots are keyed by the block
+/// number they were created at, the storage keeps track of the latest one.
+pub trait Storage {
+    /// Returns the snapshot created at the given block number
+    fn get_snapshot(&self, number: u64) -> Result<Option<Snapshot>, Error>;
+
+    /// Stores the snapshot under its block number. The latest pointer is moved to the snapshot,
+    /// unless a newer snapshot has already been stored
+    fn put_snapshot(&mut self, snapshot: &Snapshot) -> Result<(), Error>;
+
+    /// Returns the block number of the latest snapshot
+    fn get_latest_number(&self) -> Result<Option<u64>, Error>;
+
+    /// Removes (at most `limit`) snapshots created before the given block number, the latest
+    /// snapshot is always kept. Returns the number of removed snapshots
+    fn prune(&mut self, before: u64, limit: usize) -> Result<usize, Error>;
+
+    /// Returns the latest snapshot
+    fn get_latest_snapshot(&self) -> Result<Option<Snapshot>, Error> {
+        match self.get_latest_number()? {
+            Some(number) => self.get_snapshot(number),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the snapshot created at the last block of the epoch
+    fn get_epoch_snapshot(&self, epoch: u64, epoch_size: u64) -> Result<Option<Snapshot>, Error> {
+        self.get_snapshot(get_epoch_last_block_number(epoch, epoch_size))
+    }
+}
+
+/// HeaderSource provides the headers (and state proofs) of the Celo chain to be verified by the
+/// light client, ie. celo-geth JSON-RPC endpoint, recorded fixture or a mocked chain
+#[cfg(feature = "sync")]
+#[async_trait]
+pub trait HeaderSource: Send + Sync {
+    /// Returns the header at the given block number
+    async fn header_by_number(&self, number: u64) -> Result<Header, Error>;
+
+    /// Returns the header with the given hash
+    async fn header_by_hash(&self, hash: &Hash) -> Result<Header, Error>;
+
+    /// Returns the latest header known to the source
+    async fn latest_header(&self) -> Result<Header, Error>;
+
+    /// Returns the headers at the given block numbers (in the same order). Sources that can
+    /// fetch many headers at once (ie. batched JSON-RPC requests) should override it
+    async fn headers_by_number(&self, numbers: &[u64]) -> Result<Vec<Header>, Error> {
+        let mut headers = Vec::with_capacity(numbers.len());
+        for number in numbers.iter() {
+            headers.push(self.header_by_number(*number).await?);
+        }
+
+        Ok(headers)
+    }
+
+    /// Returns the merkle proof of the account and its storage slots at the given block number
+    async fn account_proof(
+        &self,
+        address: &Address,
+        keys: &[Hash],
+        number: u64,
+    ) -> Result<AccountProof, Error>;
+}
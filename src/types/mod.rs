@@ -1,3 +1,4 @@
 pub(crate) mod header;
 pub(crate) mod istanbul;
 pub(crate) mod state;
+pub(crate) mod proof;
@@ -0,0 +1,35 @@
+use crate::types::header::{Address, Hash};
+use num_bigint::BigInt as Integer;
+
+/// AccountProof is the merkle proof of the account (and its storage slots) against the state
+/// root, as returned by `eth_getProof` call
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountProof {
+    #[serde(with = "crate::serialization::bytes::hexstring")]
+    pub address: Address,
+
+    /// Trie nodes on the path from the state root to the account
+    #[serde(with = "crate::serialization::bytes::hexbytesvec")]
+    pub account_proof: Vec<Vec<u8>>,
+
+    /// Storage root of the account
+    #[serde(with = "crate::serialization::bytes::hexstring")]
+    pub storage_hash: Hash,
+
+    pub storage_proof: Vec<StorageProof>,
+}
+
+/// StorageProof is the merkle proof of the storage slot against the account storage root
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct StorageProof {
+    #[serde(with = "crate::serialization::bytes::hexstring")]
+    pub key: Hash,
+
+    #[serde(with = "crate::serialization::bytes::hexbigint")]
+    pub value: Integer,
+
+    /// Trie nodes on the path from the storage root to the slot
+    #[serde(with = "crate::serialization::bytes::hexbytesvec")]
+    pub proof: Vec<Vec<u8>>,
+}
@@ -0,0 +1,297 @@
+// Fixtures shared by the contract integration tests.
+//
+// All the fixtures are generated deterministically:
+//  * validators sign with the BLS keys derived from their index (1, 2, ...)
+//  * commitments are proven with the two-level (substore + "ibc" store) ICS-23 proofs, following
+//    the `proof_spec` below
+#![allow(dead_code)]
+
+use algebra::{bls12_377::Fr, CanonicalSerialize};
+use bls_crypto::{hash_to_curve::try_and_increment::DIRECT_HASH_TO_G1, PrivateKey, Signature};
+use celo_light_client::contract::types::ibc::{Height, MerkleProof, MerkleRoot};
+use celo_light_client::contract::types::state::{
+    CommitmentScheme, LightClientState, PrefixRule, ProofSpec, Versioned,
+};
+use celo_light_client::contract::types::wasm::{
+    ClientState, ConsensusState, Misbehaviour, WasmHeader,
+};
+use celo_light_client::{
+    FromRlp, Header, IstanbulAggregatedSeal, IstanbulExtra, IstanbulExtraVanity,
+    SerializedPublicKey, Snapshot, ToRlp, Validator,
+};
+use cosmwasm_std::testing::mock_env;
+use ics23::{
+    calculate_existence_root, commitment_proof::Proof, CommitmentProof, ExistenceProof, HashOp,
+    InnerOp, InnerSpec, LeafOp, LengthOp, NonExistenceProof,
+};
+use num::cast::ToPrimitive;
+use num_bigint::BigInt as Integer;
+use prost::Message;
+
+pub const EPOCH_SIZE: u64 = 10;
+pub const TRUSTING_PERIOD: u64 = 1_000_000;
+pub const STORE_KEY: &str = "ibc";
+
+// IBFT commit message type (see: `IstanbulMsg::Commit`)
+const ISTANBUL_MSG_COMMIT: u8 = 2;
+
+/// Validators is a deterministic validator set, able to seal the headers
+pub struct Validators {
+    keys: Vec<PrivateKey>,
+    pub validators: Vec<Validator>,
+}
+
+impl Validators {
+    pub fn new(count: u64) -> Self {
+        let keys: Vec<PrivateKey> = (1..=count).map(|i| PrivateKey::from(Fr::from(i))).collect();
+        let validators = keys
+            .iter()
+            .enumerate()
+            .map(|(i, key)| {
+                let mut bytes = Vec::new();
+                key.to_public().serialize(&mut bytes).unwrap();
+
+                let mut public_key: SerializedPublicKey = [0; 96];
+                public_key.copy_from_slice(&bytes);
+
+                Validator {
+                    address: [i as u8 + 1; 20],
+                    public_key,
+                }
+            })
+            .collect();
+
+        Self { keys, validators }
+    }
+
+    /// Returns the aggregated seal of all validators over the header hash (round 0)
+    pub fn seal(&self, hash: &[u8]) -> IstanbulAggregatedSeal {
+        // The round 0 is encoded as empty bytes
+        let message = [hash, &[ISTANBUL_MSG_COMMIT][..]].concat();
+        let signatures: Vec<Signature> = self
+            .keys
+            .iter()
+            .map(|key| key.sign(&message, &[], &*DIRECT_HASH_TO_G1).unwrap())
+            .collect();
+
+        let mut signature = Vec::new();
+        Signature::aggregate(&signatures)
+            .serialize(&mut signature)
+            .unwrap();
+
+        IstanbulAggregatedSeal {
+            bitmap: (Integer::from(1) << self.keys.len()) - 1,
+            signature,
+            round: Integer::from(0),
+        }
+    }
+
+    /// Returns the header sealed by the validator set (without validator set changes)
+    pub fn header(&self, number: u64, time: u64) -> Header {
+        let mut header = Header::new();
+        header.number = Integer::from(number);
+        header.time = time;
+        header.root = [number as u8; 32];
+
+        // The header hash doesn't cover the aggregated seal
+        header.extra = get_extra(IstanbulAggregatedSeal::new());
+        let seal = self.seal(&header.hash().unwrap());
+        header.extra = get_extra(seal);
+
+        header
+    }
+
+    /// Returns the snapshot of the validator set at the header
+    pub fn snapshot(&self, header: &Header) -> Snapshot {
+        let extra = IstanbulExtra::from_rlp(&header.extra).unwrap();
+
+        Snapshot {
+            number: header.number.to_u64().unwrap(),
+            timestamp: header.time,
+            validators: self.validators.clone(),
+            hash: header.hash().unwrap(),
+            aggregated_seal: extra.aggregated_seal,
+        }
+    }
+}
+
+fn get_extra(aggregated_seal: IstanbulAggregatedSeal) -> Vec<u8> {
+    let extra = IstanbulExtra {
+        added_validators: vec![],
+        added_validators_public_keys: vec![],
+        removed_validators: Integer::from(0),
+        seal: vec![],
+        aggregated_seal,
+        parent_aggregated_seal: IstanbulAggregatedSeal::new(),
+    };
+
+    extra.to_rlp(&IstanbulExtraVanity::default())
+}
+
+/// Returns the block time of the mocked environment
+pub fn now() -> u64 {
+    mock_env().block.time
+}
+
+pub fn height(revision_height: u64) -> Height {
+    Height {
+        revision_number: 0,
+        revision_height,
+    }
+}
+
+pub fn light_client_state() -> LightClientState {
+    let mut spec = Vec::new();
+    proof_spec().encode(&mut spec).unwrap();
+
+    LightClientState {
+        epoch_size: EPOCH_SIZE,
+        allowed_clock_skew: 5,
+        trusting_period: TRUSTING_PERIOD,
+        upgrade_path: vec![],
+
+        verify_epoch_headers: true,
+        verify_non_epoch_headers: true,
+        verify_header_timestamp: true,
+
+        allow_update_after_misbehavior: false,
+        allow_update_after_expiry: false,
+
+        commitment_scheme: CommitmentScheme::Ics23 {
+            proof_specs: vec![
+                ProofSpec::Custom { spec: spec.clone() },
+                ProofSpec::Custom { spec },
+            ],
+        },
+        prefix_rule: PrefixRule::Counterparty,
+        revision_start_heights: vec![],
+    }
+}
+
+pub fn client_state(latest_height: Height) -> ClientState {
+    ClientState {
+        data: base64::encode(light_client_state().to_versioned()),
+        code_id: String::from(""),
+        frozen: false,
+        frozen_height: None,
+        latest_height: Some(latest_height),
+    }
+}
+
+pub fn consensus_state(snapshot: &Snapshot, root: &[u8]) -> ConsensusState {
+    ConsensusState {
+        code_id: String::from(""),
+        data: base64::encode(snapshot.to_versioned()),
+        timestamp: snapshot.timestamp,
+        root: MerkleRoot {
+            hash: base64::encode(root),
+        },
+    }
+}
+
+pub fn wasm_header(header: &Header) -> WasmHeader {
+    WasmHeader {
+        data: base64::encode(header.to_rlp()),
+        height: height(header.number.to_u64().unwrap()),
+    }
+}
+
+pub fn misbehaviour(header_1: &Header, header_2: &Header) -> Misbehaviour {
+    Misbehaviour {
+        code_id: String::from(""),
+        client_id: String::from("10-wasm-0"),
+        header_1: wasm_header(header_1),
+        header_2: wasm_header(header_2),
+    }
+}
+
+pub fn proof_spec() -> ics23::ProofSpec {
+    ics23::ProofSpec {
+        leaf_spec: Some(leaf_op()),
+        inner_spec: Some(InnerSpec {
+            child_order: vec![0, 1],
+            child_size: 32,
+            min_prefix_length: 1,
+            max_prefix_length: 1,
+            empty_child: vec![],
+            hash: HashOp::Sha256.into(),
+        }),
+        max_depth: 0,
+        min_depth: 0,
+    }
+}
+
+/// Returns the (base64 encoded) proof of the key-value pair, along with the commitment root
+pub fn membership_proof(key: &str, value: &[u8]) -> (String, Vec<u8>) {
+    let proof = get_leaf_proof(key.as_bytes().to_vec(), value.to_vec());
+    let subroot = calculate_existence_root(&proof).unwrap();
+
+    chain_to_root(
+        CommitmentProof {
+            proof: Some(Proof::Exist(proof)),
+        },
+        subroot,
+    )
+}
+
+/// Returns the (base64 encoded) proof of the key absence, along with the commitment root
+pub fn non_membership_proof(key: &str) -> (String, Vec<u8>) {
+    // Absence of the key is proven by the left-most right neighbour in the substore
+    let right = get_leaf_proof(format!("{}~", key).into_bytes(), b"value".to_vec());
+    let subroot = calculate_existence_root(&right).unwrap();
+
+    chain_to_root(
+        CommitmentProof {
+            proof: Some(Proof::Nonexist(NonExistenceProof {
+                key: key.as_bytes().to_vec(),
+                left: None,
+                right: Some(right),
+            })),
+        },
+        subroot,
+    )
+}
+
+// chain_to_root proves the substore root to be stored under the STORE_KEY in the final root
+fn chain_to_root(proof: CommitmentProof, subroot: Vec<u8>) -> (String, Vec<u8>) {
+    let store_proof = get_leaf_proof(STORE_KEY.as_bytes().to_vec(), subroot);
+    let root = calculate_existence_root(&store_proof).unwrap();
+
+    let merkle_proof = MerkleProof {
+        proofs: vec![
+            proof,
+            CommitmentProof {
+                proof: Some(Proof::Exist(store_proof)),
+            },
+        ],
+    };
+
+    (
+        base64::encode(cosmwasm_std::to_vec(&merkle_proof).unwrap()),
+        root,
+    )
+}
+
+// get_leaf_proof returns the proof of the left-most leaf (with an empty right sibling)
+fn get_leaf_proof(key: Vec<u8>, value: Vec<u8>) -> ExistenceProof {
+    ExistenceProof {
+        key,
+        value,
+        leaf: Some(leaf_op()),
+        path: vec![InnerOp {
+            hash: HashOp::Sha256.into(),
+            prefix: vec![1_u8],
+            suffix: vec![0_u8; 32],
+        }],
+    }
+}
+
+fn leaf_op() -> LeafOp {
+    LeafOp {
+        hash: HashOp::Sha256.into(),
+        prehash_key: 0,
+        prehash_value: HashOp::Sha256.into(),
+        length: LengthOp::VarProto.into(),
+        prefix: vec![0_u8],
+    }
+}